@@ -1,5 +1,24 @@
-use crate::util::{color_picker_menu, exec, unchecked_remove_class_from_el};
-use leptos::{use_context, view, Scope};
+use crate::action_button::action_button_id;
+use crate::code::{code_block_menu, is_code_block, is_inline_code, toggle_inline_code};
+use crate::color_picker::{color_picker_menu, show_current_color, ColorPalette, ColorTarget};
+use crate::embed::{default_embed_providers, insert_embed, EmbedProvider};
+use crate::font::{
+    default_font_families, default_font_sizes, font_menu, show_current_font, FontOption,
+};
+use crate::format::{clear_formatting, toggle_subscript, toggle_superscript};
+use crate::highlight::{default_code_languages, CodeLanguage, Highlighter, SharedHighlighter};
+use crate::image::ALIGN_ATTRIBUTE;
+use crate::list::{
+    current_list_kind, indent_list_item, outdent_list_item, toggle_checklist, ListKind,
+};
+use crate::schema::Schema;
+use crate::spellcheck::{SharedSpellChecker, SpellChecker};
+use crate::suggestion::{
+    accept_all_changes, accept_change, is_final_view, is_suggestion_mode, reject_all_changes,
+    reject_change, toggle_final_view, toggle_suggestion_mode, AUTHOR_ATTRIBUTE,
+};
+use crate::util::{content_id, exec, exec_format_block};
+use leptos::{view, Scope};
 use leptos_dom::{document, window, IntoView, View};
 use wasm_bindgen::{JsCast, JsValue};
 use web_sys::HtmlDocument;
@@ -8,6 +27,55 @@ use web_sys::HtmlDocument;
 pub struct ActionExtraData {
     pub menu_key: String,
     pub selected_class: String,
+    pub options: ActionOptions,
+}
+
+/// Per editor settings used by the actions
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActionOptions {
+    /// Colors shown by the text color picker
+    pub text_colors: ColorPalette,
+    /// Colors shown by the highlight color picker
+    pub highlight_colors: ColorPalette,
+    /// Font families shown by the font family dropdown
+    pub font_families: Vec<FontOption>,
+    /// Font sizes shown by the font size dropdown
+    pub font_sizes: Vec<FontOption>,
+    /// Languages shown by the code block dropdown
+    pub code_languages: Vec<CodeLanguage>,
+    /// Highlighter used for the code blocks
+    pub highlighter: SharedHighlighter,
+    /// Providers that can be embedded, iframes that don't match any of them are removed
+    /// from the content
+    pub embed_providers: Vec<EmbedProvider>,
+    /// Spell checker whose misspellings are underlined in the content
+    pub spell_checker: Option<SharedSpellChecker>,
+    /// Author of the changes made in suggestion mode
+    pub suggestion_author: String,
+    /// Content allowed in the editor, which is enforced when content is loaded, pasted,
+    /// dropped or inserted. Any content is allowed if it is `None`
+    pub schema: Option<Schema>,
+    /// Block created when leaving blocks such as code blocks,
+    /// set by the editor from its `default_paragraph_separator`
+    pub paragraph_separator: String,
+}
+
+impl Default for ActionOptions {
+    fn default() -> Self {
+        Self {
+            text_colors: ColorPalette::default(),
+            highlight_colors: ColorPalette::highlight(),
+            font_families: default_font_families(),
+            font_sizes: default_font_sizes(),
+            code_languages: default_code_languages(),
+            highlighter: SharedHighlighter::default(),
+            embed_providers: default_embed_providers(),
+            spell_checker: None,
+            suggestion_author: "Anonymous".to_string(),
+            schema: None,
+            paragraph_separator: "div".to_string(),
+        }
+    }
 }
 
 type ActionIcon = fn(cx: Scope) -> View;
@@ -18,26 +86,148 @@ pub struct Action {
     pub icon: ActionIcon,
     pub compute: fn(ActionExtraData) -> Result<bool, JsValue>,
     pub state: Option<fn(ActionExtraData) -> Result<bool, JsValue>>,
+    /// Actions of the same group are mutually exclusive, only one action of a group is
+    /// shown as selected at a time
+    pub group: Option<String>,
+    /// The elements and attributes of the content that the action creates, which
+    /// `Schema::from_actions` allows
+    pub schema: Option<Schema>,
+}
+
+impl Action {
+    /// An action without a state, a group or a schema. Prefer it to a struct literal, which breaks
+    /// when fields are added to `Action`
+    pub fn new(
+        title: &str,
+        icon: ActionIcon,
+        compute: fn(ActionExtraData) -> Result<bool, JsValue>,
+    ) -> Self {
+        Self {
+            title: title.to_string(),
+            icon,
+            compute,
+            state: None,
+            group: None,
+            schema: None,
+        }
+    }
+
+    /// Sets the function that returns whether the action is applied at the selection
+    pub fn with_state(mut self, state: fn(ActionExtraData) -> Result<bool, JsValue>) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+
+    pub fn with_schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+}
+
+/// Returns the schema of the content of an action, built by `allow`
+fn action_schema(allow: impl FnOnce(&mut Schema) -> &mut Schema) -> Option<Schema> {
+    let mut schema = Schema::new();
+    allow(&mut schema);
+    Some(schema)
+}
+
+/// Blocks that the browsers align with a `text-align` when justifying
+const JUSTIFIED_BLOCKS: [&str; 11] = [
+    "blockquote",
+    "div",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "li",
+    "p",
+    "pre",
+];
+
+/// The schema of the alignment set by the justify actions, a `text-align` on the blocks
+fn justify_schema() -> Option<Schema> {
+    action_schema(|schema| {
+        for tag in JUSTIFIED_BLOCKS {
+            schema.allow_styles(tag, &["text-align"]);
+        }
+        schema
+    })
+}
+
+/// The schema of the suggested changes, created and resolved by the suggestion actions
+fn suggestion_schema() -> Option<Schema> {
+    action_schema(|schema| {
+        schema
+            .allow_marks(&["del", "ins"])
+            .allow_attributes("del", &["datetime", AUTHOR_ATTRIBUTE])
+            .allow_attributes("ins", &["datetime", AUTHOR_ATTRIBUTE])
+    })
 }
 
 #[derive(Clone)]
-pub struct Actions(Vec<Action>);
+pub struct Actions {
+    actions: Vec<Action>,
+    options: ActionOptions,
+}
 
 impl Actions {
     pub fn new() -> Self {
-        Self(vec![])
+        Self {
+            actions: vec![],
+            options: ActionOptions::default(),
+        }
     }
 
     pub fn inner(&self) -> &Vec<Action> {
-        &self.0
+        &self.actions
+    }
+
+    pub fn options(&self) -> &ActionOptions {
+        &self.options
     }
 
     pub fn get_action(&self, title: &str) -> Option<&Action> {
-        self.0.iter().find(|action| action.title == title)
+        self.actions.iter().find(|action| action.title == title)
     }
 
     pub fn remove_action(&mut self, title: &str) {
-        self.0.retain(|action| action.title != title);
+        self.actions.retain(|action| action.title != title);
+    }
+}
+
+/// Turns the blocks of the selection into `tag` blocks, or back into paragraphs if they
+/// already are
+fn toggle_block(data: &ActionExtraData, tag: &str) -> Result<bool, JsValue> {
+    match is_block(tag)? {
+        true => exec_format_block(data.options.paragraph_separator.clone()),
+        false => exec("formatBlock", &format!("<{tag}>")),
+    }
+}
+
+fn is_block(tag: &str) -> Result<bool, JsValue> {
+    let value = document()
+        .dyn_ref::<HtmlDocument>()
+        .expect("")
+        .query_command_value("formatBlock")?;
+    Ok(value.eq_ignore_ascii_case(tag))
+}
+
+/// Applies the justify command, applying `justifyLeft` instead if it is already applied
+fn toggle_justify(command: &str) -> Result<bool, JsValue> {
+    let active = document()
+        .dyn_ref::<HtmlDocument>()
+        .expect("")
+        .query_command_state(command)?;
+    match active && command != "justifyLeft" {
+        true => exec("justifyLeft", ""),
+        false => exec(command, ""),
     }
 }
 
@@ -50,17 +240,24 @@ impl Default for Actions {
 #[derive(Clone)]
 pub struct ActionsBuilder {
     actions: Actions,
+    /// Whether the schema is derived from the actions when they are built
+    actions_schema: bool,
 }
 
 impl ActionsBuilder {
     pub fn new() -> Self {
         Self {
             actions: Actions::new(),
+            actions_schema: false,
         }
     }
 
     pub fn build(&mut self) -> Actions {
-        self.actions.clone()
+        let mut actions = self.actions.clone();
+        if self.actions_schema {
+            actions.options.schema = Some(Schema::from_actions(&actions));
+        }
+        actions
     }
 
     /// Inserts all the default actions
@@ -69,7 +266,11 @@ impl ActionsBuilder {
             .with_italic()
             .with_underline()
             .with_strike_through()
+            .with_subscript()
+            .with_superscript()
+            .with_clear_formatting()
             .with_code()
+            .with_code_block()
             .with_heading1()
             .with_heading2()
             .with_heading3()
@@ -79,21 +280,105 @@ impl ActionsBuilder {
             .with_horizontal_line()
             .with_ordered_list()
             .with_unordered_list()
+            .with_checklist()
+            .with_indent()
+            .with_outdent()
             .with_link()
             .with_image()
+            .with_embed()
             .with_paragraph()
             .with_quote()
             .with_justify_left()
             .with_justify_center()
             .with_justify_right()
+            .with_justify_full()
             .with_text_color()
+            .with_highlight_color()
     }
 
     pub fn add_action(&mut self, action: Action) -> &mut Self {
-        self.actions.0.push(action);
+        self.actions.actions.push(action);
+        self
+    }
+
+    /// Sets the colors shown by the text color picker
+    pub fn with_text_color_palette(&mut self, palette: ColorPalette) -> &mut Self {
+        self.actions.options.text_colors = palette;
+        self
+    }
+
+    /// Sets the colors shown by the highlight color picker
+    pub fn with_highlight_color_palette(&mut self, palette: ColorPalette) -> &mut Self {
+        self.actions.options.highlight_colors = palette;
+        self
+    }
+
+    /// Sets the font families shown by the font family dropdown
+    pub fn with_font_families(&mut self, families: Vec<FontOption>) -> &mut Self {
+        self.actions.options.font_families = families;
+        self
+    }
+
+    /// Sets the font sizes shown by the font size dropdown
+    pub fn with_font_sizes(&mut self, sizes: Vec<FontOption>) -> &mut Self {
+        self.actions.options.font_sizes = sizes;
+        self
+    }
+
+    /// Sets the languages shown by the code block dropdown
+    pub fn with_code_languages(&mut self, languages: Vec<CodeLanguage>) -> &mut Self {
+        self.actions.options.code_languages = languages;
         self
     }
 
+    /// Sets the highlighter used for the code blocks
+    pub fn with_highlighter(&mut self, highlighter: impl Highlighter + 'static) -> &mut Self {
+        self.actions.options.highlighter = SharedHighlighter::new(highlighter);
+        self
+    }
+
+    /// Sets the providers that can be embedded
+    pub fn with_embed_providers(&mut self, providers: Vec<EmbedProvider>) -> &mut Self {
+        self.actions.options.embed_providers = providers;
+        self
+    }
+
+    /// Sets the spell checker whose misspellings are underlined in the content
+    pub fn with_spell_checker(&mut self, checker: impl SpellChecker + 'static) -> &mut Self {
+        self.actions.options.spell_checker = Some(SharedSpellChecker::new(checker));
+        self
+    }
+
+    /// Sets the author of the changes made in suggestion mode
+    pub fn with_suggestion_author(&mut self, author: &str) -> &mut Self {
+        self.actions.options.suggestion_author = author.to_string();
+        self
+    }
+
+    /// Sets the content allowed in the editor
+    pub fn with_schema(&mut self, schema: Schema) -> &mut Self {
+        self.actions.options.schema = Some(schema);
+        self.actions_schema = false;
+        self
+    }
+
+    /// Only allows the content that the actions of the editor create, see
+    /// `Schema::from_actions`
+    pub fn with_actions_schema(&mut self) -> &mut Self {
+        self.actions_schema = true;
+        self
+    }
+
+    /// Inserts the actions to suggest changes and review them
+    pub fn with_suggestion_actions(&mut self) -> &mut Self {
+        self.with_suggestion_mode()
+            .with_accept_change()
+            .with_reject_change()
+            .with_accept_all_changes()
+            .with_reject_all_changes()
+            .with_final_view()
+    }
+
     pub fn with_bold(&mut self) -> &mut Self {
         self.add_action(Action {
             title: "Bold".to_string(),
@@ -110,6 +395,8 @@ impl ActionsBuilder {
                     .expect("")
                     .query_command_state("bold")
             }),
+            group: None,
+            schema: action_schema(|schema| schema.allow_marks(&["b", "strong"])),
         })
     }
 
@@ -129,6 +416,8 @@ impl ActionsBuilder {
                     .expect("")
                     .query_command_state("italic")
             }),
+            group: None,
+            schema: action_schema(|schema| schema.allow_marks(&["em", "i"])),
         })
     }
 
@@ -148,6 +437,8 @@ impl ActionsBuilder {
                     .expect("")
                     .query_command_state("underline")
             }),
+            group: None,
+            schema: action_schema(|schema| schema.allow_marks(&["u"])),
         })
     }
 
@@ -167,6 +458,72 @@ impl ActionsBuilder {
                     .expect("")
                     .query_command_state("strikeThrough")
             }),
+            group: None,
+            schema: action_schema(|schema| schema.allow_marks(&["s", "strike"])),
+        })
+    }
+
+    pub fn with_subscript(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Subscript".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, <span>"x"<sub>"2"</sub></span>}
+                }
+                .into_view(cx)
+            },
+            compute: |_| toggle_subscript(),
+            state: Some(|_| {
+                document()
+                    .dyn_ref::<HtmlDocument>()
+                    .expect("")
+                    .query_command_state("subscript")
+            }),
+            group: None,
+            schema: action_schema(|schema| schema.allow_marks(&["sub"])),
+        })
+    }
+
+    pub fn with_superscript(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Superscript".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, <span>"x"<sup>"2"</sup></span>}
+                }
+                .into_view(cx)
+            },
+            compute: |_| toggle_superscript(),
+            state: Some(|_| {
+                document()
+                    .dyn_ref::<HtmlDocument>()
+                    .expect("")
+                    .query_command_state("superscript")
+            }),
+            group: None,
+            schema: action_schema(|schema| schema.allow_marks(&["sup"])),
+        })
+    }
+
+    pub fn with_clear_formatting(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Clear Formatting".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, <span>"T"<sub>"×"</sub></span>}
+                }
+                .into_view(cx)
+            },
+            compute: |data| {
+                clear_formatting(
+                    &content_id(&data.menu_key),
+                    &data.options.paragraph_separator,
+                )
+                .map(|_| true)
+            },
+            state: None,
+            group: None,
+            schema: None,
         })
     }
 
@@ -179,8 +536,40 @@ impl ActionsBuilder {
                 }
                 .into_view(cx)
             },
-            compute: |_| exec("formatBlock", "<pre>"),
-            state: None,
+            compute: |data| toggle_inline_code(&content_id(&data.menu_key)).map(|_| true),
+            state: Some(|data| Ok(is_inline_code(&content_id(&data.menu_key)))),
+            group: None,
+            schema: action_schema(|schema| schema.allow_marks(&["code"])),
+        })
+    }
+
+    pub fn with_code_block(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Code Block".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, <b>"{ }"</b>}
+                }
+                .into_view(cx)
+            },
+            compute: |data| {
+                code_block_menu(
+                    &action_button_id(&data.menu_key, "Code Block"),
+                    &content_id(&data.menu_key),
+                    &data.options.code_languages,
+                    data.options.highlighter.clone(),
+                    &data.options.paragraph_separator,
+                );
+                Ok(true)
+            },
+            state: Some(|data| Ok(is_code_block(&content_id(&data.menu_key)))),
+            group: None,
+            schema: action_schema(|schema| {
+                schema
+                    .allow_blocks(&["pre"])
+                    .allow_marks(&["code"])
+                    .allow_attributes("code", &["class"])
+            }),
         })
     }
 
@@ -193,8 +582,10 @@ impl ActionsBuilder {
                 }
                 .into_view(cx)
             },
-            compute: |_| exec("formatBlock", "<h1>"),
-            state: None,
+            compute: |data| toggle_block(&data, "h1"),
+            state: Some(|_| is_block("h1")),
+            group: Some("heading".to_string()),
+            schema: action_schema(|schema| schema.allow_blocks(&["h1"])),
         })
     }
 
@@ -207,8 +598,10 @@ impl ActionsBuilder {
                 }
                 .into_view(cx)
             },
-            compute: |_| exec("formatBlock", "<h2>"),
-            state: None,
+            compute: |data| toggle_block(&data, "h2"),
+            state: Some(|_| is_block("h2")),
+            group: Some("heading".to_string()),
+            schema: action_schema(|schema| schema.allow_blocks(&["h2"])),
         })
     }
 
@@ -221,8 +614,10 @@ impl ActionsBuilder {
                 }
                 .into_view(cx)
             },
-            compute: |_| exec("formatBlock", "<h3>"),
-            state: None,
+            compute: |data| toggle_block(&data, "h3"),
+            state: Some(|_| is_block("h3")),
+            group: Some("heading".to_string()),
+            schema: action_schema(|schema| schema.allow_blocks(&["h3"])),
         })
     }
 
@@ -235,8 +630,10 @@ impl ActionsBuilder {
                 }
                 .into_view(cx)
             },
-            compute: |_| exec("formatBlock", "<h4>"),
-            state: None,
+            compute: |data| toggle_block(&data, "h4"),
+            state: Some(|_| is_block("h4")),
+            group: Some("heading".to_string()),
+            schema: action_schema(|schema| schema.allow_blocks(&["h4"])),
         })
     }
 
@@ -249,8 +646,10 @@ impl ActionsBuilder {
                 }
                 .into_view(cx)
             },
-            compute: |_| exec("formatBlock", "<h5>"),
-            state: None,
+            compute: |data| toggle_block(&data, "h5"),
+            state: Some(|_| is_block("h5")),
+            group: Some("heading".to_string()),
+            schema: action_schema(|schema| schema.allow_blocks(&["h5"])),
         })
     }
 
@@ -263,8 +662,10 @@ impl ActionsBuilder {
                 }
                 .into_view(cx)
             },
-            compute: |_| exec("formatBlock", "<h6>"),
-            state: None,
+            compute: |data| toggle_block(&data, "h6"),
+            state: Some(|_| is_block("h6")),
+            group: Some("heading".to_string()),
+            schema: action_schema(|schema| schema.allow_blocks(&["h6"])),
         })
     }
 
@@ -279,6 +680,8 @@ impl ActionsBuilder {
             },
             compute: |_| exec("insertHorizontalRule", ""),
             state: None,
+            group: None,
+            schema: action_schema(|schema| schema.allow_blocks(&["hr"])),
         })
     }
 
@@ -292,7 +695,15 @@ impl ActionsBuilder {
                 .into_view(cx)
             },
             compute: |_| exec("insertOrderedList", ""),
-            state: None,
+            state: Some(|data| {
+                Ok(current_list_kind(&content_id(&data.menu_key)) == Some(ListKind::Ordered))
+            }),
+            group: None,
+            schema: action_schema(|schema| {
+                schema
+                    .allow_blocks(&["li", "ol"])
+                    .allow_attributes("ol", &["start"])
+            }),
         })
     }
 
@@ -306,7 +717,72 @@ impl ActionsBuilder {
                 .into_view(cx)
             },
             compute: |_| exec("insertUnorderedList", ""),
+            state: Some(|data| {
+                Ok(current_list_kind(&content_id(&data.menu_key)) == Some(ListKind::Unordered))
+            }),
+            group: None,
+            schema: action_schema(|schema| schema.allow_blocks(&["li", "ul"])),
+        })
+    }
+
+    pub fn with_checklist(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Checklist".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, "☑"}
+                }
+                .into_view(cx)
+            },
+            compute: |data| toggle_checklist(&content_id(&data.menu_key)).map(|_| true),
+            state: Some(|data| {
+                Ok(current_list_kind(&content_id(&data.menu_key)) == Some(ListKind::Checklist))
+            }),
+            group: None,
+            schema: action_schema(|schema| {
+                schema
+                    .allow_blocks(&["li", "ul"])
+                    .allow_marks(&["input"])
+                    .allow_attributes("ul", &["class"])
+                    .allow_attributes("input", &["checked", "class", "type"])
+            }),
+        })
+    }
+
+    pub fn with_indent(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Indent".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, "⇥"}
+                }
+                .into_view(cx)
+            },
+            compute: |data| indent_list_item(&content_id(&data.menu_key)),
+            state: None,
+            group: None,
+            schema: action_schema(|schema| {
+                schema
+                    .allow_blocks(&["li", "ol", "ul"])
+                    .allow_attributes("ol", &["start"])
+                    .allow_attributes("ul", &["class"])
+            }),
+        })
+    }
+
+    pub fn with_outdent(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Outdent".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, "⇤"}
+                }
+                .into_view(cx)
+            },
+            compute: |data| outdent_list_item(&content_id(&data.menu_key)),
             state: None,
+            group: None,
+            schema: None,
         })
     }
 
@@ -327,6 +803,12 @@ impl ActionsBuilder {
                 }
             },
             state: None,
+            group: None,
+            schema: action_schema(|schema| {
+                schema
+                    .allow_marks(&["a"])
+                    .allow_attributes("a", &["href", "title"])
+            }),
         })
     }
 
@@ -347,6 +829,49 @@ impl ActionsBuilder {
                 }
             },
             state: None,
+            group: None,
+            schema: action_schema(|schema| {
+                schema
+                    .allow_blocks(&["figcaption", "figure"])
+                    .allow_marks(&["img"])
+                    .allow_attributes("img", &["alt", "src", "style", "width", ALIGN_ATTRIBUTE])
+                    .allow_attributes("figure", &["class", "style", ALIGN_ATTRIBUTE])
+            }),
+        })
+    }
+
+    pub fn with_embed(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Embed".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, "🎬"}
+                }
+                .into_view(cx)
+            },
+            compute: |data| {
+                let url = window().prompt_with_message("Enter the URL to embed:");
+                let Ok(Some(url)) = url else {
+                    return Ok(false);
+                };
+                let inserted = insert_embed(
+                    &data.options.embed_providers,
+                    &url,
+                    &data.options.paragraph_separator,
+                )?;
+                if !inserted {
+                    window().alert_with_message("This URL can't be embedded")?;
+                }
+                Ok(inserted)
+            },
+            state: None,
+            group: None,
+            schema: action_schema(|schema| {
+                schema
+                    .allow_marks(&["iframe"])
+                    .allow_attributes("div", &["class", "contenteditable"])
+                    .allow_attributes("iframe", &["allowfullscreen", "sandbox", "src", "style"])
+            }),
         })
     }
 
@@ -355,10 +880,119 @@ impl ActionsBuilder {
             title: "Text color".to_string(),
             icon: |cx: Scope| { view!{cx, <svg  width="16px" height="16px" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 512 512"><path d="M512 256c0 .9 0 1.8 0 2.7c-.4 36.5-33.6 61.3-70.1 61.3H344c-26.5 0-48 21.5-48 48c0 3.4 .4 6.7 1 9.9c2.1 10.2 6.5 20 10.8 29.9c6.1 13.8 12.1 27.5 12.1 42c0 31.8-21.6 60.7-53.4 62c-3.5 .1-7 .2-10.6 .2C114.6 512 0 397.4 0 256S114.6 0 256 0S512 114.6 512 256zM128 288a32 32 0 1 0 -64 0 32 32 0 1 0 64 0zm0-96a32 32 0 1 0 0-64 32 32 0 1 0 0 64zM288 96a32 32 0 1 0 -64 0 32 32 0 1 0 64 0zm96 96a32 32 0 1 0 0-64 32 32 0 1 0 0 64z"/></svg>}}.into_view(cx),
             compute: |data| {
-                color_picker_menu(&format!("{}-Textcolor-rte-btn", data.menu_key), 128., 144.);
+                color_picker_menu(
+                    &action_button_id(&data.menu_key, "Text color"),
+                    &data.options.text_colors,
+                    ColorTarget::Foreground,
+                );
                 Ok(true)
             },
-            state: None
+            state: Some(|data| {
+                show_current_color(
+                    &action_button_id(&data.menu_key, "Text color"),
+                    ColorTarget::Foreground,
+                )?;
+                Ok(false)
+            }),
+            group: None,
+            schema: action_schema(|schema| schema
+                    .allow_marks(&["font", "span"])
+                    .allow_attributes("font", &["color"])
+                    .allow_attributes("span", &["style"])),
+        })
+    }
+
+    pub fn with_highlight_color(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Highlight color".to_string(),
+            icon: |cx: Scope| { view!{cx, <svg width="16px" height="16px" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 576 512"><path d="M315 315l158.4-215L444.1 70.6 229 229 315 315zm-187 5l0 0V248.3c0-15.3 7.2-29.6 19.5-38.6L420.6 8.4C428 2.9 437 0 446.2 0c11.4 0 22.4 4.5 30.5 12.6l54.8 54.8c8.1 8.1 12.6 19 12.6 30.5c0 9.2-2.9 18.2-8.4 25.6L334.4 396.5c-9 12.3-23.4 19.5-38.6 19.5H224l-25.4 25.4c-12.5 12.5-32.8 12.5-45.3 0l-50.7-50.7c-12.5-12.5-12.5-32.8 0-45.3L128 320zM7 466.3l63-63 70.6 70.6-31 31c-4.5 4.5-10.6 7-17 7H24c-13.3 0-24-10.7-24-24v-4.7c0-6.4 2.5-12.5 7-17z"/></svg>}}.into_view(cx),
+            compute: |data| {
+                color_picker_menu(
+                    &action_button_id(&data.menu_key, "Highlight color"),
+                    &data.options.highlight_colors,
+                    ColorTarget::Background,
+                );
+                Ok(true)
+            },
+            state: Some(|data| {
+                show_current_color(
+                    &action_button_id(&data.menu_key, "Highlight color"),
+                    ColorTarget::Background,
+                )?;
+                Ok(false)
+            }),
+            group: None,
+            schema: action_schema(|schema| schema
+                    .allow_marks(&["span"])
+                    .allow_attributes("span", &["style"])),
+        })
+    }
+
+    pub fn with_font_family(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Font family".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, "Default"}
+                }
+                .into_view(cx)
+            },
+            compute: |data| {
+                font_menu(
+                    &action_button_id(&data.menu_key, "Font family"),
+                    &content_id(&data.menu_key),
+                    &data.options.font_families,
+                );
+                Ok(true)
+            },
+            state: Some(|data| {
+                show_current_font(
+                    &action_button_id(&data.menu_key, "Font family"),
+                    &content_id(&data.menu_key),
+                    &data.options.font_families,
+                )?;
+                Ok(false)
+            }),
+            group: None,
+            schema: action_schema(|schema| {
+                schema
+                    .allow_marks(&["span"])
+                    .allow_attributes("span", &["class"])
+            }),
+        })
+    }
+
+    pub fn with_font_size(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Font size".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, "Default"}
+                }
+                .into_view(cx)
+            },
+            compute: |data| {
+                font_menu(
+                    &action_button_id(&data.menu_key, "Font size"),
+                    &content_id(&data.menu_key),
+                    &data.options.font_sizes,
+                );
+                Ok(true)
+            },
+            state: Some(|data| {
+                show_current_font(
+                    &action_button_id(&data.menu_key, "Font size"),
+                    &content_id(&data.menu_key),
+                    &data.options.font_sizes,
+                )?;
+                Ok(false)
+            }),
+            group: None,
+            schema: action_schema(|schema| {
+                schema
+                    .allow_marks(&["span"])
+                    .allow_attributes("span", &["class"])
+            }),
         })
     }
 
@@ -373,6 +1007,8 @@ impl ActionsBuilder {
             },
             compute: |_| exec("formatBlock", "<p>"),
             state: None,
+            group: None,
+            schema: action_schema(|schema| schema.allow_blocks(&["p"])),
         })
     }
 
@@ -385,8 +1021,10 @@ impl ActionsBuilder {
                 }
                 .into_view(cx)
             },
-            compute: |_| exec("formatBlock", "<blockQuote>"),
+            compute: |_| exec("formatBlock", "<blockquote>"),
             state: None,
+            group: None,
+            schema: action_schema(|schema| schema.allow_blocks(&["blockquote"])),
         })
     }
 
@@ -394,19 +1032,15 @@ impl ActionsBuilder {
         self.add_action(Action {
             title: "Justify Center".to_string(),
             icon: |cx: Scope| { view!{cx, <svg height="16px" width="16px" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 448 512"><path d="M352 64c0-17.7-14.3-32-32-32H128c-17.7 0-32 14.3-32 32s14.3 32 32 32H320c17.7 0 32-14.3 32-32zm96 128c0-17.7-14.3-32-32-32H32c-17.7 0-32 14.3-32 32s14.3 32 32 32H416c17.7 0 32-14.3 32-32zM0 448c0 17.7 14.3 32 32 32H416c17.7 0 32-14.3 32-32s-14.3-32-32-32H32c-17.7 0-32 14.3-32 32zM352 320c0-17.7-14.3-32-32-32H128c-17.7 0-32 14.3-32 32s14.3 32 32 32H320c17.7 0 32-14.3 32-32z"/></svg>}}.into_view(cx),
-            compute: |_| exec("justifyCenter", ""),
-            state: Some(|data| {
-                let document = document();
-                let res = document
+            compute: |_| toggle_justify("justifyCenter"),
+            state: Some(|_| {
+                document()
                     .dyn_ref::<HtmlDocument>()
                     .expect("")
-                    .query_command_state("justifyCenter");
-                if let Ok(true) = res {
-                    unchecked_remove_class_from_el(&document, &format!("{}-JustifyLeft-rte-btn", data.menu_key), &data.selected_class);
-                    unchecked_remove_class_from_el(&document, &format!("{}-JustifyRight-rte-btn", data.menu_key), &data.selected_class);
-                }
-                res
-            })
+                    .query_command_state("justifyCenter")
+            }),
+            group: Some("alignment".to_string()),
+            schema: justify_schema(),
         })
     }
 
@@ -414,19 +1048,15 @@ impl ActionsBuilder {
         self.add_action(Action {
             title: "Justify Left".to_string(),
             icon: |cx: Scope| { view!{cx, <svg height="16px" width="16px" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 448 512"><path d="M288 64c0 17.7-14.3 32-32 32H32C14.3 96 0 81.7 0 64S14.3 32 32 32H256c17.7 0 32 14.3 32 32zm0 256c0 17.7-14.3 32-32 32H32c-17.7 0-32-14.3-32-32s14.3-32 32-32H256c17.7 0 32 14.3 32 32zM0 192c0-17.7 14.3-32 32-32H416c17.7 0 32 14.3 32 32s-14.3 32-32 32H32c-17.7 0-32-14.3-32-32zM448 448c0 17.7-14.3 32-32 32H32c-17.7 0-32-14.3-32-32s14.3-32 32-32H416c17.7 0 32 14.3 32 32z"/></svg>}}.into_view(cx),
-            compute: |_| exec("justifyLeft", ""),
-            state: Some(|data| {
-                let document = document();
-                let res = document
+            compute: |_| toggle_justify("justifyLeft"),
+            state: Some(|_| {
+                document()
                     .dyn_ref::<HtmlDocument>()
                     .expect("")
-                    .query_command_state("justifyLeft");
-                if let Ok(true) = res {
-                    unchecked_remove_class_from_el(&document, &format!("{}-JustifyRight-rte-btn", data.menu_key), &data.selected_class);
-                    unchecked_remove_class_from_el(&document, &format!("{}-JustifyCenter-rte-btn", data.menu_key), &data.selected_class);
-                }
-                res
-            })
+                    .query_command_state("justifyLeft")
+            }),
+            group: Some("alignment".to_string()),
+            schema: justify_schema(),
         })
     }
 
@@ -434,19 +1064,128 @@ impl ActionsBuilder {
         self.add_action(Action {
             title: "Justify Right".to_string(),
             icon: |cx: Scope| { view!{cx, <svg height="16px" width="16px" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 448 512"><path d="M448 64c0 17.7-14.3 32-32 32H192c-17.7 0-32-14.3-32-32s14.3-32 32-32H416c17.7 0 32 14.3 32 32zm0 256c0 17.7-14.3 32-32 32H192c-17.7 0-32-14.3-32-32s14.3-32 32-32H416c17.7 0 32 14.3 32 32zM0 192c0-17.7 14.3-32 32-32H416c17.7 0 32 14.3 32 32s-14.3 32-32 32H32c-17.7 0-32-14.3-32-32zM448 448c0 17.7-14.3 32-32 32H32c-17.7 0-32-14.3-32-32s14.3-32 32-32H416c17.7 0 32 14.3 32 32z"/></svg>}}.into_view(cx),
-            compute: |_| exec("justifyRight", ""),
-            state: Some(|data| {
-                let document = document();
-                let res = document
+            compute: |_| toggle_justify("justifyRight"),
+            state: Some(|_| {
+                document()
+                    .dyn_ref::<HtmlDocument>()
+                    .expect("")
+                    .query_command_state("justifyRight")
+            }),
+            group: Some("alignment".to_string()),
+            schema: justify_schema(),
+        })
+    }
+
+    pub fn with_justify_full(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Justify Full".to_string(),
+            icon: |cx: Scope| { view!{cx, <svg height="16px" width="16px" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 448 512"><path d="M448 64c0-17.7-14.3-32-32-32H32C14.3 32 0 46.3 0 64S14.3 96 32 96H416c17.7 0 32-14.3 32-32zm0 256c0-17.7-14.3-32-32-32H32c-17.7 0-32 14.3-32 32s14.3 32 32 32H416c17.7 0 32-14.3 32-32zM0 192c0 17.7 14.3 32 32 32H416c17.7 0 32-14.3 32-32s-14.3-32-32-32H32c-17.7 0-32 14.3-32 32zM448 448c0-17.7-14.3-32-32-32H32c-17.7 0-32 14.3-32 32s14.3 32 32 32H416c17.7 0 32-14.3 32-32z"/></svg>}}.into_view(cx),
+            compute: |_| toggle_justify("justifyFull"),
+            state: Some(|_| {
+                document()
                     .dyn_ref::<HtmlDocument>()
                     .expect("")
-                    .query_command_state("justifyRight");
-                if let Ok(true) = res {
-                    unchecked_remove_class_from_el(&document, &format!("{}-JustifyLeft-rte-btn", data.menu_key), &data.selected_class);
-                    unchecked_remove_class_from_el(&document, &format!("{}-JustifyCenter-rte-btn", data.menu_key), &data.selected_class);
+                    .query_command_state("justifyFull")
+            }),
+            group: Some("alignment".to_string()),
+            schema: justify_schema(),
+        })
+    }
+
+    pub fn with_suggestion_mode(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Suggest".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, "✍"}
+                }
+                .into_view(cx)
+            },
+            compute: |data| Ok(toggle_suggestion_mode(&content_id(&data.menu_key))),
+            state: Some(|data| Ok(is_suggestion_mode(&content_id(&data.menu_key)))),
+            group: None,
+            schema: suggestion_schema(),
+        })
+    }
+
+    pub fn with_accept_change(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Accept Change".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, "✓"}
+                }
+                .into_view(cx)
+            },
+            compute: |data| accept_change(&content_id(&data.menu_key)),
+            state: None,
+            group: None,
+            schema: suggestion_schema(),
+        })
+    }
+
+    pub fn with_reject_change(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Reject Change".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, "✗"}
+                }
+                .into_view(cx)
+            },
+            compute: |data| reject_change(&content_id(&data.menu_key)),
+            state: None,
+            group: None,
+            schema: suggestion_schema(),
+        })
+    }
+
+    pub fn with_accept_all_changes(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Accept All Changes".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, <span>"✓"<sub>"all"</sub></span>}
                 }
-                res
-            })
+                .into_view(cx)
+            },
+            compute: |data| accept_all_changes(&content_id(&data.menu_key)),
+            state: None,
+            group: None,
+            schema: suggestion_schema(),
+        })
+    }
+
+    pub fn with_reject_all_changes(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Reject All Changes".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, <span>"✗"<sub>"all"</sub></span>}
+                }
+                .into_view(cx)
+            },
+            compute: |data| reject_all_changes(&content_id(&data.menu_key)),
+            state: None,
+            group: None,
+            schema: suggestion_schema(),
+        })
+    }
+
+    /// Toggles between the marked up content and the content with every change accepted
+    pub fn with_final_view(&mut self) -> &mut Self {
+        self.add_action(Action {
+            title: "Final View".to_string(),
+            icon: |cx: Scope| {
+                {
+                    view! {cx, "👁"}
+                }
+                .into_view(cx)
+            },
+            compute: |data| toggle_final_view(&content_id(&data.menu_key)),
+            state: Some(|data| Ok(is_final_view(&content_id(&data.menu_key)))),
+            group: None,
+            schema: suggestion_schema(),
         })
     }
 }
@@ -1,8 +1,26 @@
-use crate::action::{Action as PapelitoAction, ActionExtraData};
+use crate::action::{Action as PapelitoAction, ActionExtraData, ActionOptions};
+use crate::util::{content_id, dispatch_event};
 use leptos::*;
 use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
 use web_sys::{Event, MouseEvent};
 
+/// Returns the id of the button rendered for the action with the given title
+pub fn action_button_id(editor_key: &str, action_title: &str) -> String {
+    format!(
+        "{}-{}-rte-btn",
+        editor_key.replace(" ", ""),
+        action_title.replace(" ", "")
+    )
+}
+
+/// Event dispatched on the content div to make every action button update its state
+pub const REFRESH_STATE_EVENT: &str = "papelito-refresh-state";
+
+/// Returns the value of the `data-papelito-group` attribute of the buttons of a group
+fn action_group_id(editor_key: &str, group: &str) -> String {
+    format!("{}-{}", editor_key.replace(" ", ""), group.replace(" ", ""))
+}
+
 #[component]
 pub fn ActionButton(
     cx: Scope,
@@ -10,42 +28,39 @@ pub fn ActionButton(
     editor_key: String,
     content_ref: NodeRef<leptos::html::Div>,
     selected_class: String,
+    #[prop(optional)] options: ActionOptions,
     #[prop(optional)] class: String,
 ) -> impl IntoView {
-    let unique_btn_id = format!(
-        "{}-{}-rte-btn",
-        editor_key.replace(" ", ""),
-        action.title.replace(" ", "")
-    );
-
-    let unique_btn_id_clone = unique_btn_id.clone();
+    let unique_btn_id = action_button_id(&editor_key, &action.title);
 
     let class_clone = selected_class.clone();
     let key_clone = editor_key.clone();
+    let options_clone = options.clone();
     let on_click_btn = move |_: MouseEvent| {
         let action_data = ActionExtraData {
             menu_key: key_clone.clone(),
             selected_class: class_clone.clone(),
+            options: options_clone.clone(),
         };
         let _ = (action.compute)(action_data);
-        if let Some(state) = action.state {
-            let metadata = DataHandleBtnState {
-                button_id: unique_btn_id_clone.clone(),
-                selected_class: class_clone.clone(),
-                key: key_clone.clone(),
-                state,
-            };
-            handle_btn_state(metadata);
-        }
+        // Updates this button and the other buttons of its group
+        dispatch_event(&content_id(&key_clone), REFRESH_STATE_EVENT);
     };
 
+    let group_id = action
+        .group
+        .as_ref()
+        .map(|group| action_group_id(&editor_key, group));
     if let Some(state) = action.state {
         let btn_id = unique_btn_id.clone();
+        let group_id = group_id.clone();
         content_ref.on_load(cx, move |c| {
             let metadata = DataHandleBtnState {
                 button_id: btn_id.clone(),
                 selected_class: selected_class.clone(),
                 key: editor_key.clone(),
+                options: options.clone(),
+                group_id: group_id.clone(),
                 state,
             };
 
@@ -64,13 +79,19 @@ pub fn ActionButton(
             content_el
                 .add_event_listener_with_callback("mouseup", event_handler.as_ref().unchecked_ref())
                 .expect("couldn't add event listener");
+            content_el
+                .add_event_listener_with_callback(
+                    REFRESH_STATE_EVENT,
+                    event_handler.as_ref().unchecked_ref(),
+                )
+                .expect("couldn't add event listener");
 
             event_handler.forget();
         });
     }
 
     view! {cx,
-        <button title=action.title class=class on:click=on_click_btn id=unique_btn_id>
+        <button title=action.title class=class on:click=on_click_btn id=unique_btn_id data-papelito-group=group_id>
             {(action.icon)(cx)}
         </button>
     }
@@ -89,11 +110,26 @@ fn handle_btn_state(data: DataHandleBtnState) {
     let state_data = ActionExtraData {
         menu_key: key,
         selected_class,
+        options: data.options.clone(),
     };
 
     match (data.state)(state_data) {
         Ok(true) => {
             button_el.class_list().add_1(&data.selected_class).unwrap();
+            if let Some(group_id) = &data.group_id {
+                let group_buttons = document()
+                    .query_selector_all(&format!("[data-papelito-group=\"{group_id}\"]"))
+                    .unwrap();
+                for i in 0..group_buttons.length() {
+                    let other = group_buttons
+                        .item(i)
+                        .unwrap()
+                        .unchecked_into::<web_sys::Element>();
+                    if !other.is_same_node(Some(button_el.unchecked_ref::<web_sys::Node>())) {
+                        other.class_list().remove_1(&data.selected_class).unwrap();
+                    }
+                }
+            }
         }
         _ => {
             button_el
@@ -109,5 +145,7 @@ struct DataHandleBtnState {
     button_id: String,
     selected_class: String,
     key: String,
+    options: ActionOptions,
+    group_id: Option<String>,
     state: fn(ActionExtraData) -> Result<bool, JsValue>,
 }
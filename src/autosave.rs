@@ -0,0 +1,143 @@
+use leptos_dom::window;
+use std::fmt;
+use std::rc::Rc;
+use wasm_bindgen::JsValue;
+
+/// Where the drafts are persisted
+pub trait DraftStorage {
+    fn load(&self, key: &str) -> Option<String>;
+    fn save(&self, key: &str, value: &str);
+    fn remove(&self, key: &str);
+}
+
+/// Persists the drafts in the `localStorage` of the browser
+pub struct LocalStorage;
+
+impl LocalStorage {
+    fn storage() -> Option<web_sys::Storage> {
+        window().local_storage().ok().flatten()
+    }
+}
+
+impl DraftStorage for LocalStorage {
+    fn load(&self, key: &str) -> Option<String> {
+        Self::storage()?.get_item(key).ok().flatten()
+    }
+
+    fn save(&self, key: &str, value: &str) {
+        if let Some(storage) = Self::storage() {
+            // Fails when the storage is full, the draft is just not saved then
+            let _ = storage.set_item(key, value);
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        if let Some(storage) = Self::storage() {
+            let _ = storage.remove_item(key);
+        }
+    }
+}
+
+/// Content saved by the autosave
+#[derive(Clone, Debug, PartialEq)]
+pub struct Draft {
+    pub content: String,
+    /// Milliseconds since the Unix epoch
+    pub saved_at: f64,
+}
+
+impl Draft {
+    /// Stored as the timestamp followed by a new line and the content
+    fn to_stored(&self) -> String {
+        format!("{}\n{}", self.saved_at, self.content)
+    }
+
+    fn from_stored(value: &str) -> Option<Self> {
+        let (saved_at, content) = value.split_once('\n')?;
+        Some(Self {
+            content: content.to_string(),
+            saved_at: saved_at.parse().ok()?,
+        })
+    }
+
+    /// Returns the date of the draft formatted for the locale of the user
+    pub fn saved_at_label(&self) -> String {
+        js_sys::Date::new(&JsValue::from_f64(self.saved_at))
+            .to_locale_string("default", &JsValue::UNDEFINED)
+            .into()
+    }
+}
+
+/// Saves the edits of an editor as a draft, keyed by the editor key
+#[derive(Clone)]
+pub struct Autosave {
+    storage: Rc<dyn DraftStorage>,
+    /// When the initial content of the editor was saved, in milliseconds since the Unix
+    /// epoch. Drafts older than it aren't offered
+    saved_at: Option<f64>,
+}
+
+impl fmt::Debug for Autosave {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Autosave")
+            .field("saved_at", &self.saved_at)
+            .finish()
+    }
+}
+
+impl Default for Autosave {
+    fn default() -> Self {
+        Self::new(LocalStorage)
+    }
+}
+
+impl Autosave {
+    pub fn new(storage: impl DraftStorage + 'static) -> Self {
+        Self {
+            storage: Rc::new(storage),
+            saved_at: None,
+        }
+    }
+
+    /// Sets when the initial content of the editor was saved, in milliseconds since the
+    /// Unix epoch
+    pub fn with_saved_at(mut self, saved_at: f64) -> Self {
+        self.saved_at = Some(saved_at);
+        self
+    }
+
+    fn storage_key(editor_key: &str) -> String {
+        format!("papelito-draft-{editor_key}")
+    }
+
+    pub fn load_draft(&self, editor_key: &str) -> Option<Draft> {
+        let value = self.storage.load(&Self::storage_key(editor_key))?;
+        Draft::from_stored(&value)
+    }
+
+    pub fn save_draft(&self, editor_key: &str, content: &str) {
+        let draft = Draft {
+            content: content.to_string(),
+            saved_at: js_sys::Date::now(),
+        };
+        self.storage
+            .save(&Self::storage_key(editor_key), &draft.to_stored());
+    }
+
+    /// Removes the draft of the editor, call it once the content was saved
+    pub fn clear_draft(&self, editor_key: &str) {
+        self.storage.remove(&Self::storage_key(editor_key));
+    }
+
+    /// Returns the draft of the editor if it differs from the initial content and is newer
+    /// than it
+    pub fn newer_draft(&self, editor_key: &str, initial_content: &str) -> Option<Draft> {
+        self.load_draft(editor_key).filter(|draft| {
+            draft.content != initial_content
+                && self
+                    .saved_at
+                    .map(|saved_at| draft.saved_at > saved_at)
+                    .unwrap_or(true)
+        })
+    }
+}
@@ -0,0 +1,346 @@
+use crate::highlight::{
+    highlight_code_element, set_code_language, CodeLanguage, SharedHighlighter,
+};
+use crate::util::{
+    dispatch_input_event, dropdown_menu, exec, isolate_from_ancestor, mark_selection,
+    restore_selection, save_selection, selection_element, set_caret_after, unwrap_element,
+    DropdownItem,
+};
+use leptos_dom::document;
+use std::rc::Rc;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Element, KeyboardEvent, Range, Text};
+
+/// Text inserted when pressing Tab inside a code block
+const CODE_BLOCK_INDENT: &str = "    ";
+/// Width in pixels of the code block dropdown menu
+const CODE_BLOCK_MENU_WIDTH: f64 = 160.;
+
+/// Returns the inline `<code>` element at the caret, code blocks are ignored
+fn inline_code_at_caret(content_id: &str) -> Option<Element> {
+    let el = selection_element(content_id)?;
+    let code = el.closest("code").ok()??;
+    match code.closest("pre").ok()? {
+        Some(_) => None,
+        None => Some(code),
+    }
+}
+
+/// Returns the `<pre>` element of the code block at the caret
+pub fn code_block_at_caret(content_id: &str) -> Option<Element> {
+    let el = selection_element(content_id)?;
+    let pre = el.closest("pre").ok()??;
+    let root = document().get_element_by_id(content_id)?;
+    match root.contains(Some(&pre)) {
+        true => Some(pre),
+        false => None,
+    }
+}
+
+pub fn is_inline_code(content_id: &str) -> bool {
+    inline_code_at_caret(content_id).is_some()
+}
+
+pub fn is_code_block(content_id: &str) -> bool {
+    code_block_at_caret(content_id).is_some()
+}
+
+/// Wraps the selection in an inline `<code>` element, or removes it if the caret is already
+/// inside one. With a collapsed selection inside inline code the caret is moved after it
+pub fn toggle_inline_code(content_id: &str) -> Result<(), JsValue> {
+    let root = document()
+        .get_element_by_id(content_id)
+        .ok_or("couldn't find the content div")?;
+    if code_block_at_caret(content_id).is_some() {
+        return Ok(());
+    }
+    let Some(range) = save_selection() else {
+        return Ok(());
+    };
+
+    let current_code = inline_code_at_caret(content_id);
+    if range.collapsed() {
+        if let Some(code) = current_code {
+            set_caret_after(&code)?;
+        }
+        return Ok(());
+    }
+
+    let selection_range = Range::new()?;
+    let markers = mark_selection(&root);
+    for (i, marker) in markers.iter().enumerate() {
+        let (first, last) = (marker.first_child(), marker.last_child());
+        match current_code {
+            Some(_) => {
+                if let Some(code) = marker
+                    .parent_element()
+                    .and_then(|p| p.closest("code").ok()?)
+                {
+                    isolate_from_ancestor(marker, &code)?;
+                    unwrap_element(&code);
+                }
+                unwrap_element(marker);
+                if let (Some(first), Some(last)) = (first, last) {
+                    if i == 0 {
+                        selection_range.set_start_before(&first)?;
+                    }
+                    selection_range.set_end_after(&last)?;
+                }
+            }
+            None => {
+                let nested = marker.query_selector_all("code")?;
+                for j in 0..nested.length() {
+                    unwrap_element(nested.item(j).unwrap().unchecked_ref());
+                }
+                let code = document().create_element("code")?;
+                while let Some(child) = marker.first_child() {
+                    code.append_child(&child)?;
+                }
+                marker.replace_with_with_node_1(&code)?;
+                if i == 0 {
+                    selection_range.set_start(&code, 0)?;
+                }
+                selection_range.set_end(&code, code.child_nodes().length())?;
+            }
+        }
+    }
+    if !markers.is_empty() {
+        restore_selection(&selection_range);
+    }
+    dispatch_input_event(content_id);
+    Ok(())
+}
+
+/// Turns the block at the caret into a `<pre><code>` block, or turns the code block at the
+/// caret back into one `separator` block per line
+pub fn toggle_code_block(content_id: &str, separator: &str) -> Result<(), JsValue> {
+    match code_block_at_caret(content_id) {
+        Some(pre) => {
+            let text = pre.text_content().unwrap_or_default();
+            let mut first_block = None;
+            for line in text.trim_end_matches('\n').split('\n') {
+                let block = document().create_element(separator)?;
+                match line.is_empty() {
+                    true => block.set_inner_html("<br>"),
+                    false => block.set_text_content(Some(line)),
+                }
+                pre.parent_node()
+                    .ok_or("the code block has no parent")?
+                    .insert_before(&block, Some(&pre))?;
+                first_block.get_or_insert(block);
+            }
+            pre.remove();
+
+            if let Some(block) = first_block {
+                let range = Range::new()?;
+                range.set_start(&block, 0)?;
+                range.collapse_with_to_start(true);
+                restore_selection(&range);
+            }
+        }
+        None => {
+            exec("formatBlock", "<pre>")?;
+            if let Some(pre) = code_block_at_caret(content_id) {
+                wrap_code_block_content(&pre)?;
+            }
+        }
+    }
+    dispatch_input_event(content_id);
+    Ok(())
+}
+
+/// Toggles a dropdown below the element with the given id listing the code block languages.
+/// Picking a language turns the block at the caret into a code block of that language, when
+/// the caret is already inside a code block the menu also allows removing it
+pub fn code_block_menu(
+    element_id: &str,
+    content_id: &str,
+    languages: &[CodeLanguage],
+    highlighter: SharedHighlighter,
+    separator: &str,
+) {
+    let in_code_block = is_code_block(content_id);
+    let mut items = languages
+        .iter()
+        .map(|language| DropdownItem {
+            label: language.label.clone(),
+            class: None,
+        })
+        .collect::<Vec<_>>();
+    if in_code_block {
+        items.push(DropdownItem {
+            label: "Remove code block".to_string(),
+            class: None,
+        });
+    }
+
+    let content_id = content_id.to_string();
+    let separator = separator.to_string();
+    let languages = languages.to_vec();
+    let on_select = Rc::new(move |index: usize| {
+        let _ = match languages.get(index) {
+            Some(language) => {
+                set_code_block_language(&content_id, &language.name, &highlighter, &separator)
+            }
+            None => toggle_code_block(&content_id, &separator),
+        };
+    });
+    dropdown_menu(element_id, CODE_BLOCK_MENU_WIDTH, items, on_select);
+}
+
+/// Sets the language of the code block at the caret, creating the code block if needed
+pub fn set_code_block_language(
+    content_id: &str,
+    language: &str,
+    highlighter: &SharedHighlighter,
+    separator: &str,
+) -> Result<(), JsValue> {
+    if !is_code_block(content_id) {
+        toggle_code_block(content_id, separator)?;
+    }
+    if let Some(pre) = code_block_at_caret(content_id) {
+        let code = wrap_code_block_content(&pre)?;
+        set_code_language(&code, language);
+        highlight_code_element(&code, highlighter.0.as_ref());
+    }
+    dispatch_input_event(content_id);
+    Ok(())
+}
+
+/// Makes sure all the content of the `<pre>` is plain text inside a single `<code>` element
+pub fn wrap_code_block_content(pre: &Element) -> Result<Element, JsValue> {
+    if let Some(code) = pre.first_element_child() {
+        if code.tag_name().eq_ignore_ascii_case("code") && pre.child_nodes().length() == 1 {
+            return Ok(code);
+        }
+    }
+
+    let brs = pre.query_selector_all("br")?;
+    for i in 0..brs.length() {
+        let br = brs.item(i).unwrap().unchecked_into::<Element>();
+        br.replace_with_with_str_1("\n")?;
+    }
+    let text = pre.text_content().unwrap_or_default();
+    let code = document().create_element("code")?;
+    code.set_text_content(Some(&text));
+    pre.set_text_content(None);
+    pre.append_child(&code)?;
+
+    let range = Range::new()?;
+    range.set_start(&code, code.child_nodes().length())?;
+    range.collapse_with_to_start(true);
+    restore_selection(&range);
+    Ok(code)
+}
+
+/// Handles the keys that behave differently inside code blocks, returns `true` if the event
+/// was handled:
+/// - Tab inserts an indentation instead of moving the focus
+/// - Enter inserts a line break instead of a new block
+/// - Shift+Enter leaves the code block, creating a `separator` block after it
+pub fn handle_code_block_keydown(
+    content_id: &str,
+    e: &KeyboardEvent,
+    separator: &str,
+) -> Result<bool, JsValue> {
+    let Some(pre) = code_block_at_caret(content_id) else {
+        return Ok(false);
+    };
+
+    match (e.key().as_str(), e.shift_key()) {
+        ("Tab", false) => {
+            e.prevent_default();
+            exec("insertText", CODE_BLOCK_INDENT)?;
+        }
+        ("Enter", false) => {
+            e.prevent_default();
+            insert_line_break(&pre)?;
+            dispatch_input_event(content_id);
+        }
+        ("Enter", true) => {
+            e.prevent_default();
+            let block = document().create_element(separator)?;
+            block.set_inner_html("<br>");
+            pre.parent_node()
+                .ok_or("the code block has no parent")?
+                .insert_before(&block, pre.next_sibling().as_ref())?;
+            let range = Range::new()?;
+            range.set_start(&block, 0)?;
+            range.collapse_with_to_start(true);
+            restore_selection(&range);
+            dispatch_input_event(content_id);
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn insert_line_break(pre: &Element) -> Result<(), JsValue> {
+    let Some(range) = save_selection() else {
+        return Ok(());
+    };
+    range.delete_contents()?;
+    let line_break = document().create_text_node("\n");
+    range.insert_node(&line_break)?;
+
+    // A trailing line break isn't rendered, so another one is needed when the caret is at
+    // the end of the block
+    let rest = Range::new()?;
+    rest.set_start_after(&line_break)?;
+    rest.set_end(pre, pre.child_nodes().length())?;
+    let rest_text = rest.clone_contents()?.text_content().unwrap_or_default();
+    if rest_text.is_empty() {
+        line_break.append_data("\n")?;
+        let caret = Range::new()?;
+        caret.set_start(&line_break, 1)?;
+        caret.collapse_with_to_start(true);
+        restore_selection(&caret);
+        return Ok(());
+    }
+
+    set_caret_after(&line_break)
+}
+
+/// Turns `` `text` `` into inline code when the closing backtick is typed, returns `true`
+/// if the rule was applied
+pub fn apply_backtick_rule(content_id: &str) -> Result<bool, JsValue> {
+    let Some(el) = selection_element(content_id) else {
+        return Ok(false);
+    };
+    if el.closest("code, pre")?.is_some() {
+        return Ok(false);
+    }
+    let selection = document().get_selection()?.ok_or("no selection")?;
+    let Some(text) = selection
+        .anchor_node()
+        .and_then(|node| node.dyn_into::<Text>().ok())
+    else {
+        return Ok(false);
+    };
+
+    // DOM offsets are in UTF-16 code units
+    let caret = selection.anchor_offset() as usize;
+    let data = text.data().encode_utf16().collect::<Vec<u16>>();
+    let backtick = '`' as u16;
+    if caret < 3 || caret > data.len() || data[caret - 1] != backtick {
+        return Ok(false);
+    }
+    let Some(start) = data[..caret - 1].iter().rposition(|c| *c == backtick) else {
+        return Ok(false);
+    };
+    if start + 1 == caret - 1 {
+        return Ok(false);
+    }
+
+    let code_text = String::from_utf16_lossy(&data[start + 1..caret - 1]);
+    let after = text.split_text(caret as u32)?;
+    text.delete_data(start as u32, (caret - start) as u32)?;
+
+    let code = document().create_element("code")?;
+    code.set_text_content(Some(&code_text));
+    text.parent_node()
+        .ok_or("the text has no parent")?
+        .insert_before(&code, Some(&after))?;
+    set_caret_after(&code)?;
+    Ok(true)
+}
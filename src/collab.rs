@@ -0,0 +1,623 @@
+use crate::html::decode_entities;
+use crate::util::{selection_text_offsets, set_selection_text_offsets, text_units, TextChange};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use web_sys::Element;
+
+/// Site of the characters of the initial content, which every peer creates with the same ids
+const INITIAL_SITE: u64 = 0;
+
+/// Unique id of a character of the document, ordered by its Lamport timestamp and then by
+/// its site
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CharId {
+    pub counter: u64,
+    pub site: u64,
+}
+
+impl Ord for CharId {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.counter, self.site).cmp(&(other.counter, other.site))
+    }
+}
+
+impl PartialOrd for CharId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// An edit of the HTML of the content
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operation {
+    /// Inserts a character after another one, or at the start if `after` is `None`. The
+    /// characters of the document are the characters of the text and whole tags, comments
+    /// and character references
+    Insert {
+        id: CharId,
+        after: Option<CharId>,
+        value: String,
+    },
+    Delete {
+        id: CharId,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct CharEntry {
+    id: CharId,
+    value: String,
+    deleted: bool,
+}
+
+/// Returns the length of the tag at the start of the HTML, the `>` of quoted attribute
+/// values doesn't end it
+fn tag_len(html: &str) -> usize {
+    let mut quoted = false;
+    for (i, c) in html.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '>' if !quoted => return i + 1,
+            _ => {}
+        }
+    }
+    html.len()
+}
+
+/// Splits HTML into the characters of a document: whole tags, comments and character
+/// references, and single characters of text
+fn html_chars(html: &str) -> Vec<&str> {
+    let mut chars = vec![];
+    let mut rest = html;
+    while let Some(c) = rest.chars().next() {
+        let is_tag = c == '<'
+            && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!');
+        let len = if rest.starts_with("<!--") {
+            rest.find("-->").map(|end| end + 3).unwrap_or(rest.len())
+        } else if is_tag {
+            tag_len(rest)
+        } else if c == '&' {
+            rest.find(';')
+                .filter(|end| {
+                    *end <= 10
+                        && rest[1..*end]
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '#')
+                })
+                .map(|end| end + 1)
+                .unwrap_or(1)
+        } else {
+            c.len_utf8()
+        };
+        chars.push(&rest[..len]);
+        rest = &rest[len..];
+    }
+    chars
+}
+
+/// Returns the length of a character of a document in the text of the content, in UTF-16
+/// code units. Tags and comments have no text
+fn text_len(value: &str) -> u32 {
+    match value.len() > 1 && value.starts_with('<') {
+        true => 0,
+        false => decode_entities(value).encode_utf16().count() as u32,
+    }
+}
+
+/// A replicated growable array of the characters of the HTML of the content.
+/// Peers converge to the same HTML regardless of the order in which they receive the
+/// operations, as long as they all start from the same initial content.
+///
+/// Tags are never split, so the merged HTML only has tags written by a peer. Concurrent
+/// changes of the same element can still leave its tags misnested, e.g. when a peer makes
+/// a word bold while another one makes it italic, the browser parses such HTML like any
+/// other and the content sent by the next edit is well formed again
+#[derive(Clone, Debug)]
+pub struct CollabDocument {
+    site: u64,
+    clock: u64,
+    chars: Vec<CharEntry>,
+    /// Operations that reference characters that weren't received yet
+    pending: Vec<Operation>,
+}
+
+impl CollabDocument {
+    /// `site` identifies this peer, it must be unique and different from 0
+    pub fn new(site: u64, initial: &str) -> Self {
+        let chars: Vec<CharEntry> = html_chars(initial)
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| CharEntry {
+                id: CharId {
+                    counter: i as u64 + 1,
+                    site: INITIAL_SITE,
+                },
+                value: value.to_string(),
+                deleted: false,
+            })
+            .collect();
+        Self {
+            site,
+            clock: chars.len() as u64,
+            chars,
+            pending: vec![],
+        }
+    }
+
+    pub fn site(&self) -> u64 {
+        self.site
+    }
+
+    pub fn text(&self) -> String {
+        self.chars
+            .iter()
+            .filter(|c| !c.deleted)
+            .map(|c| c.value.as_str())
+            .collect()
+    }
+
+    /// Returns the text of the content in UTF-16 code units, the unit of the text offsets
+    pub fn text_units(&self) -> Vec<u16> {
+        self.chars
+            .iter()
+            .filter(|c| !c.deleted && text_len(&c.value) > 0)
+            .flat_map(|c| decode_entities(&c.value).encode_utf16().collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// Returns the position at an offset of the text of the content. It is the character
+    /// before the offset, or `None` at the start, so the position stays next to the same text
+    /// when the document changes
+    pub fn position_at(&self, offset: u32) -> Option<CharId> {
+        let mut len = 0;
+        let mut position = None;
+        for c in self.chars.iter().filter(|c| !c.deleted) {
+            if len >= offset {
+                break;
+            }
+            len += text_len(&c.value);
+            position = Some(c.id);
+        }
+        position
+    }
+
+    /// Returns the offset in the text of the content of a position returned by
+    /// `position_at`, `None` if its character wasn't received yet. Deleted characters are
+    /// kept, so the position of a deleted one is where it was
+    pub fn offset_of(&self, position: Option<CharId>) -> Option<u32> {
+        let Some(id) = position else {
+            return Some(0);
+        };
+        let mut len = 0;
+        for c in &self.chars {
+            if !c.deleted {
+                len += text_len(&c.value);
+            }
+            if c.id == id {
+                return Some(len);
+            }
+        }
+        None
+    }
+
+    fn index_of(&self, id: CharId) -> Option<usize> {
+        self.chars.iter().position(|c| c.id == id)
+    }
+
+    fn next_id(&mut self) -> CharId {
+        self.clock += 1;
+        CharId {
+            counter: self.clock,
+            site: self.site,
+        }
+    }
+
+    /// Returns the operations that turn the current text into `new_text`, and applies them
+    pub fn local_edit(&mut self, new_text: &str) -> Vec<Operation> {
+        let visible: Vec<usize> = (0..self.chars.len())
+            .filter(|i| !self.chars[*i].deleted)
+            .collect();
+        let old: Vec<&str> = visible
+            .iter()
+            .map(|i| self.chars[*i].value.as_str())
+            .collect();
+        let new = html_chars(new_text);
+
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let mut ops = vec![];
+        for i in &visible[prefix..old.len() - suffix] {
+            ops.push(Operation::Delete {
+                id: self.chars[*i].id,
+            });
+        }
+        let mut after = match prefix {
+            0 => None,
+            _ => Some(self.chars[visible[prefix - 1]].id),
+        };
+        for value in &new[prefix..new.len() - suffix] {
+            let id = self.next_id();
+            ops.push(Operation::Insert {
+                id,
+                after,
+                value: value.to_string(),
+            });
+            after = Some(id);
+        }
+
+        for op in &ops {
+            self.integrate(op);
+        }
+        ops
+    }
+
+    /// Applies operations received from other peers, returns `true` if the text changed
+    pub fn apply_remote(&mut self, ops: Vec<Operation>) -> bool {
+        let before = self.text();
+        self.pending.extend(ops);
+        // Integrates the pending operations until none of them can be integrated
+        loop {
+            let pending = std::mem::take(&mut self.pending);
+            let count = pending.len();
+            for op in pending {
+                if !self.integrate(&op) {
+                    self.pending.push(op);
+                }
+            }
+            if self.pending.is_empty() || self.pending.len() == count {
+                break;
+            }
+        }
+        self.text() != before
+    }
+
+    /// Integrates an operation, returns `false` if it references an unknown character
+    fn integrate(&mut self, op: &Operation) -> bool {
+        match op {
+            Operation::Insert { id, after, value } => {
+                self.clock = self.clock.max(id.counter);
+                if self.index_of(*id).is_some() {
+                    return true;
+                }
+                let mut index = match after {
+                    Some(after) => match self.index_of(*after) {
+                        Some(index) => index + 1,
+                        None => return false,
+                    },
+                    None => 0,
+                };
+                // Characters inserted concurrently after the same one are ordered by id
+                while index < self.chars.len() && self.chars[index].id > *id {
+                    index += 1;
+                }
+                self.chars.insert(
+                    index,
+                    CharEntry {
+                        id: *id,
+                        value: value.clone(),
+                        deleted: false,
+                    },
+                );
+                true
+            }
+            Operation::Delete { id } => match self.index_of(*id) {
+                Some(index) => {
+                    self.chars[index].deleted = true;
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransportError(pub String);
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+pub type LocalBoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// Sends the local operations to the other peers and receives theirs
+pub trait Transport {
+    fn send(&self, ops: Vec<Operation>) -> LocalBoxFuture<'_, Result<(), TransportError>>;
+    /// Waits for the next operations of the other peers, `None` once the transport is closed
+    fn receive(&self) -> LocalBoxFuture<'_, Option<Vec<Operation>>>;
+}
+
+#[derive(Default)]
+struct Channel {
+    queue: VecDeque<Vec<Operation>>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+/// A transport connected to another one in the same process, used for tests and demos
+pub struct LoopbackTransport {
+    incoming: Rc<RefCell<Channel>>,
+    outgoing: Rc<RefCell<Channel>>,
+}
+
+impl LoopbackTransport {
+    /// Returns two transports connected to each other
+    pub fn pair() -> (Self, Self) {
+        let a = Rc::new(RefCell::new(Channel::default()));
+        let b = Rc::new(RefCell::new(Channel::default()));
+        (
+            Self {
+                incoming: a.clone(),
+                outgoing: b.clone(),
+            },
+            Self {
+                incoming: b,
+                outgoing: a,
+            },
+        )
+    }
+}
+
+impl Drop for LoopbackTransport {
+    fn drop(&mut self) {
+        let mut outgoing = self.outgoing.borrow_mut();
+        outgoing.closed = true;
+        if let Some(waker) = outgoing.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Transport for LoopbackTransport {
+    fn send(&self, ops: Vec<Operation>) -> LocalBoxFuture<'_, Result<(), TransportError>> {
+        let result = {
+            let mut outgoing = self.outgoing.borrow_mut();
+            match outgoing.closed {
+                true => Err(TransportError(
+                    "the other transport was dropped".to_string(),
+                )),
+                false => {
+                    outgoing.queue.push_back(ops);
+                    if let Some(waker) = outgoing.waker.take() {
+                        waker.wake();
+                    }
+                    Ok(())
+                }
+            }
+        };
+        Box::pin(async move { result })
+    }
+
+    fn receive(&self) -> LocalBoxFuture<'_, Option<Vec<Operation>>> {
+        Box::pin(Receive {
+            channel: self.incoming.clone(),
+        })
+    }
+}
+
+struct Receive {
+    channel: Rc<RefCell<Channel>>,
+}
+
+impl Future for Receive {
+    type Output = Option<Vec<Operation>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut channel = self.channel.borrow_mut();
+        if let Some(ops) = channel.queue.pop_front() {
+            return Poll::Ready(Some(ops));
+        }
+        if channel.closed {
+            return Poll::Ready(None);
+        }
+        channel.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Connects an editor to other peers
+#[derive(Clone)]
+pub struct Collaboration {
+    pub(crate) site: u64,
+    pub(crate) transport: Rc<dyn Transport>,
+    pub(crate) document: Rc<RefCell<Option<CollabDocument>>>,
+}
+
+impl fmt::Debug for Collaboration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Collaboration")
+            .field("site", &self.site)
+            .finish()
+    }
+}
+
+impl Collaboration {
+    /// `site` identifies this peer, it must be unique and different from 0.
+    /// Every peer must start from the same content
+    pub fn new(site: u64, transport: impl Transport + 'static) -> Self {
+        Self {
+            site,
+            transport: Rc::new(transport),
+            document: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Creates the document from the initial content
+    pub(crate) fn start(&self, initial: &str) {
+        *self.document.borrow_mut() = Some(CollabDocument::new(self.site, initial));
+    }
+
+    /// Records an edit of the content and sends its operations to the other peers
+    pub(crate) fn local_edit(&self, html: &str) {
+        let ops = match self.document.borrow_mut().as_mut() {
+            Some(document) => document.local_edit(html),
+            None => return,
+        };
+        if ops.is_empty() {
+            return;
+        }
+        let transport = self.transport.clone();
+        leptos::spawn_local(async move {
+            if let Err(error) = transport.send(ops).await {
+                leptos::log!("couldn't send the collaboration operations: {error}");
+            }
+        });
+    }
+
+    /// Receives the operations of the other peers until the transport is closed, calling
+    /// `on_change` with the merged HTML whenever they change it
+    pub(crate) fn listen(&self, on_change: impl Fn(String) + 'static) {
+        let collaboration = self.clone();
+        leptos::spawn_local(async move {
+            while let Some(ops) = collaboration.transport.receive().await {
+                let html = {
+                    let mut document = collaboration.document.borrow_mut();
+                    match document.as_mut() {
+                        Some(document) => document.apply_remote(ops).then(|| document.text()),
+                        None => None,
+                    }
+                };
+                if let Some(html) = html {
+                    on_change(html);
+                }
+            }
+        });
+    }
+}
+
+/// Runs `f`, which changes the content of the root, keeping the selection over the same
+/// text. Text inserted or removed before the selection moves it
+pub fn with_preserved_text_selection(root: &Element, f: impl FnOnce()) {
+    let offsets = selection_text_offsets(root);
+    let old_text = text_units(root);
+    f();
+    let Some((anchor, focus)) = offsets else {
+        return;
+    };
+    let change = TextChange::between(&old_text, &text_units(root));
+    let _ = set_selection_text_offsets(root, change.map(anchor), change.map(focus));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converges_when_operations_arrive_in_different_orders() {
+        let initial = "<p>hello</p>";
+        let mut a = CollabDocument::new(1, initial);
+        let mut b = CollabDocument::new(2, initial);
+        let a1 = a.local_edit("<p>hello world</p>");
+        let a2 = a.local_edit("<p>hello word</p>");
+        let b1 = b.local_edit("<p>oh, hello!</p>");
+
+        // The second edit of `a` reaches `b` before the first one, it waits for it
+        assert!(!b.apply_remote(a2.clone()));
+        assert!(b.apply_remote(a1.clone()));
+        a.apply_remote(b1.clone());
+        assert_eq!(a.text(), b.text());
+        assert_eq!(a.text(), "<p>oh, hello! word</p>");
+
+        // A third peer receiving everything at once converges too
+        let mut c = CollabDocument::new(3, initial);
+        c.apply_remote([b1, a2, a1].concat());
+        assert_eq!(c.text(), a.text());
+    }
+
+    #[test]
+    fn orders_concurrent_insertions_at_the_same_place() {
+        let mut a = CollabDocument::new(1, "ac");
+        let mut b = CollabDocument::new(2, "ac");
+        let from_a = a.local_edit("abc");
+        let from_b = b.local_edit("axc");
+        a.apply_remote(from_b);
+        b.apply_remote(from_a);
+        assert_eq!(a.text(), b.text());
+        assert_eq!(a.text().len(), 4);
+    }
+
+    #[test]
+    fn deletions_of_the_same_text_converge() {
+        let mut a = CollabDocument::new(1, "<p>one two</p>");
+        let mut b = CollabDocument::new(2, "<p>one two</p>");
+        let from_a = a.local_edit("<p>one</p>");
+        let from_b = b.local_edit("<p>two</p>");
+        a.apply_remote(from_b);
+        b.apply_remote(from_a);
+        assert_eq!(a.text(), "<p></p>");
+        assert_eq!(b.text(), "<p></p>");
+    }
+
+    #[test]
+    fn keeps_tags_whole_when_the_same_element_is_edited() {
+        let initial = "<p><b>word</b></p>";
+        let mut a = CollabDocument::new(1, initial);
+        let mut b = CollabDocument::new(2, initial);
+        let from_a = a.local_edit("<p><a href=\"https://a.example\">word</a></p>");
+        let from_b = b.local_edit("<p><i class=\"x > y\">word</i></p>");
+        a.apply_remote(from_b);
+        b.apply_remote(from_a);
+        assert_eq!(a.text(), b.text());
+        // Both elements are kept whole, misnested, instead of mixing their characters
+        let text = a.text();
+        for tag in [
+            "<a href=\"https://a.example\">",
+            "</a>",
+            "<i class=\"x > y\">",
+            "</i>",
+        ] {
+            assert!(text.contains(tag), "{tag} isn't in {text}");
+        }
+        assert!(!text.contains("<b>") && !text.contains("</b>"));
+    }
+
+    #[test]
+    fn positions_stay_next_to_the_same_text() {
+        let initial = "<p>one <b>two</b> &amp; three</p>";
+        let mut a = CollabDocument::new(1, initial);
+        let mut b = CollabDocument::new(2, initial);
+        assert_eq!(a.text_units().len(), "one two & three".len());
+        // Before "three", the tags and the reference don't count as text
+        let position = a.position_at(10);
+        assert_eq!(a.offset_of(position), Some(10));
+        assert_eq!(a.position_at(0), None);
+
+        let from_b = b.local_edit("<p>zero one <b>two</b> &amp; three</p>");
+        a.local_edit("<p>one <b>two</b> &amp; new three</p>");
+        a.apply_remote(from_b);
+        assert_eq!(a.offset_of(position), Some(15));
+        assert_eq!(String::from_utf16_lossy(&a.text_units()[15..]), "new three");
+        // The position of a character that wasn't received isn't known
+        let unknown = CharId {
+            counter: 100,
+            site: 9,
+        };
+        assert_eq!(a.offset_of(Some(unknown)), None);
+    }
+
+    #[test]
+    fn splits_html_into_tags_references_and_characters() {
+        assert_eq!(
+            html_chars("<p class=\"a>b\">x&amp;é<!-- c --></p>"),
+            vec!["<p class=\"a>b\">", "x", "&amp;", "é", "<!-- c -->", "</p>"]
+        );
+        assert_eq!(
+            html_chars("a < b & c"),
+            vec!["a", " ", "<", " ", "b", " ", "&", " ", "c"]
+        );
+    }
+}
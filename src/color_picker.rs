@@ -0,0 +1,417 @@
+use crate::util::{
+    add_context_menu_to_el, dispatch_input_event, exec, remove_context_menu, restore_selection,
+    save_selection, unwrap_element, ContextMenuPosition,
+};
+use lazy_static::lazy_static;
+use leptos_dom::document;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{Event, HtmlDivElement, HtmlElement, HtmlInputElement, KeyboardEvent, Range};
+
+lazy_static! {
+    /// Recently used colors, keyed by the id of the element that opened the picker
+    static ref RECENT_COLORS: Mutex<HashMap<String, Vec<String>>> = Mutex::new(HashMap::new());
+}
+
+/// Size in pixels of each color swatch, including its border
+const ITEM_SIZE: f64 = 18.;
+/// Height in pixels of the custom color input row
+const INPUT_ROW_HEIGHT: f64 = 24.;
+/// Height in pixels of the "remove color" row
+const REMOVE_ROW_HEIGHT: f64 = 20.;
+
+/// Color used to mark the selection when removing a color, it is never left in the document
+const SENTINEL_COLOR: &str = "#010203";
+const SENTINEL_COLOR_RGB: &str = "rgb(1, 2, 3)";
+
+static DEFAULT_COLORS: [&str; 56] = [
+    "#000000", "#44B8FF", "#1E92F7", "#0074D9", "#005DC2", "#00369B", "#b3d5f4", "#444444",
+    "#C3FFFF", "#9DF9FF", "#7FDBFF", "#68C4E8", "#419DC1", "#d9f4ff", "#666666", "#72FF84",
+    "#4CEA5E", "#2ECC40", "#17B529", "#008E02", "#c0f0c6", "#888888", "#FFFF44", "#FFFA1E",
+    "#FFDC00", "#E8C500", "#C19E00", "#fff5b3", "#aaaaaa", "#FFC95F", "#FFA339", "#FF851B",
+    "#E86E04", "#C14700", "#ffdbbb", "#cccccc", "#FF857A", "#FF5F54", "#FF4136", "#E82A1F",
+    "#C10300", "#ffc6c3", "#eeeeee", "#FF56FF", "#FF30DC", "#F012BE", "#D900A7", "#B20080",
+    "#fbb8ec", "#ffffff", "#F551FF", "#CF2BE7", "#B10DC9", "#9A00B2", "#9A00B2", "#e8b6ef",
+];
+
+static DEFAULT_HIGHLIGHT_COLORS: [&str; 14] = [
+    "#fff59d", "#ffe082", "#ffcc80", "#ffab91", "#f48fb1", "#ce93d8", "#b39ddb", "#90caf9",
+    "#81d4fa", "#80deea", "#80cbc4", "#a5d6a7", "#c5e1a5", "#e0e0e0",
+];
+
+/// The colors shown by a color picker menu
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorPalette {
+    pub colors: Vec<String>,
+    /// Amount of swatches per row
+    pub columns: usize,
+}
+
+impl ColorPalette {
+    pub fn new(colors: Vec<String>) -> Self {
+        Self {
+            colors,
+            ..Default::default()
+        }
+    }
+
+    /// The default palette used for highlighting text
+    pub fn highlight() -> Self {
+        Self::new(
+            DEFAULT_HIGHLIGHT_COLORS
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+        )
+    }
+
+    pub fn with_columns(mut self, columns: usize) -> Self {
+        self.columns = columns.max(1);
+        self
+    }
+
+    fn rows(&self) -> usize {
+        (self.colors.len() + self.columns - 1) / self.columns
+    }
+}
+
+impl Default for ColorPalette {
+    fn default() -> Self {
+        Self {
+            colors: DEFAULT_COLORS.iter().map(|c| c.to_string()).collect(),
+            columns: 7,
+        }
+    }
+}
+
+/// Which color of the selected text a color picker changes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorTarget {
+    /// The text color, applied with `foreColor`
+    Foreground,
+    /// The highlight behind the text, applied with `hiliteColor`
+    Background,
+}
+
+impl ColorTarget {
+    fn command(&self) -> &'static str {
+        match self {
+            ColorTarget::Foreground => "foreColor",
+            ColorTarget::Background => "hiliteColor",
+        }
+    }
+
+    /// `hiliteColor` can't be queried on every browser, `backColor` returns the same value
+    fn query_command(&self) -> &'static str {
+        match self {
+            ColorTarget::Foreground => "foreColor",
+            ColorTarget::Background => "backColor",
+        }
+    }
+
+    fn css_property(&self) -> &'static str {
+        match self {
+            ColorTarget::Foreground => "color",
+            ColorTarget::Background => "background-color",
+        }
+    }
+
+    fn remove_label(&self) -> &'static str {
+        match self {
+            ColorTarget::Foreground => "Remove color",
+            ColorTarget::Background => "Remove highlight",
+        }
+    }
+}
+
+/// Parses a hex (`#rgb`, `#rrggbb`) or `rgb(r, g, b)` color into the `#rrggbb` format
+pub fn parse_color(input: &str) -> Option<String> {
+    let input = input.trim().to_lowercase();
+    if let Some(hex) = input.strip_prefix('#') {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        return match hex.len() {
+            3 => Some(format!(
+                "#{}",
+                hex.chars().flat_map(|c| [c, c]).collect::<String>()
+            )),
+            6 => Some(format!("#{hex}")),
+            _ => None,
+        };
+    }
+
+    let inner = input
+        .strip_prefix("rgb(")
+        .or_else(|| input.strip_prefix("rgba("))?
+        .strip_suffix(')')?;
+    let channels = inner
+        .split(',')
+        .map(|c| c.trim().parse::<u8>().ok())
+        .collect::<Option<Vec<u8>>>()?;
+    match channels.as_slice() {
+        [r, g, b] => Some(format!("#{r:02x}{g:02x}{b:02x}")),
+        _ => None,
+    }
+}
+
+/// Returns the color of the current selection in the `#rrggbb` format
+pub fn current_color(target: ColorTarget) -> Option<String> {
+    let value = document()
+        .dyn_ref::<web_sys::HtmlDocument>()
+        .expect("couldn't get the HtmlDocument")
+        .query_command_value(target.query_command())
+        .ok()?;
+    parse_color(&value)
+}
+
+/// Shows the color of the current selection as an underline on the element with the given id
+pub fn show_current_color(element_id: &str, target: ColorTarget) -> Result<(), JsValue> {
+    let Some(el) = document().get_element_by_id(element_id) else {
+        return Ok(());
+    };
+    let el = el.dyn_into::<HtmlElement>().unwrap();
+    match current_color(target) {
+        Some(color) => el
+            .style()
+            .set_property("box-shadow", &format!("inset 0 -3px 0 {color}")),
+        None => el.style().remove_property("box-shadow").map(|_| ()),
+    }
+}
+
+/// Toggles a color picker menu on top of the element with the given id
+pub fn color_picker_menu(element_id: &str, palette: &ColorPalette, target: ColorTarget) {
+    if remove_context_menu(element_id) {
+        return;
+    }
+    let selection = save_selection();
+
+    let width = palette.columns as f64 * ITEM_SIZE;
+    let height = (palette.rows() + 1) as f64 * ITEM_SIZE + INPUT_ROW_HEIGHT + REMOVE_ROW_HEIGHT;
+    let menu = add_context_menu_to_el(element_id, width, height, ContextMenuPosition::TopCenter);
+    style_color_picker_menu(&menu).unwrap();
+
+    let recent_colors = RECENT_COLORS
+        .lock()
+        .unwrap()
+        .get(element_id)
+        .cloned()
+        .unwrap_or_default();
+    let recent_row = create_row(&menu);
+    for color in recent_colors.iter().take(palette.columns) {
+        add_color_item(&recent_row, element_id, color, target, &selection);
+    }
+
+    let palette_container = create_row(&menu);
+    for color in &palette.colors {
+        add_color_item(&palette_container, element_id, color, target, &selection);
+    }
+
+    add_custom_color_input(&menu, element_id, target, &selection);
+    add_remove_color_item(&menu, element_id, target, &selection);
+}
+
+fn create_row(menu: &HtmlDivElement) -> HtmlElement {
+    let row = document().create_element("div").unwrap();
+    let row = row.dyn_into::<HtmlElement>().unwrap();
+    style_color_picker_row(&row).unwrap();
+    menu.append_child(&row).unwrap();
+    row
+}
+
+fn add_color_item(
+    container: &HtmlElement,
+    element_id: &str,
+    color: &str,
+    target: ColorTarget,
+    selection: &Option<Range>,
+) {
+    let el = document().create_element("a").unwrap();
+    let el = el.dyn_ref::<HtmlElement>().unwrap();
+    el.set_title(color);
+    style_color_picker_item(el, color).unwrap();
+
+    let element_id = element_id.to_string();
+    let color = color.to_string();
+    let selection = selection.clone();
+    let on_click_item: Closure<dyn Fn(Event)> = Closure::new(move |_: Event| {
+        apply_color(&element_id, &color, target, &selection);
+    });
+    el.add_event_listener_with_callback("click", on_click_item.as_ref().unchecked_ref())
+        .unwrap();
+    on_click_item.forget();
+    container.append_child(el).unwrap();
+}
+
+fn add_custom_color_input(
+    menu: &HtmlDivElement,
+    element_id: &str,
+    target: ColorTarget,
+    selection: &Option<Range>,
+) {
+    let input = document().create_element("input").unwrap();
+    let input = input.dyn_into::<HtmlInputElement>().unwrap();
+    input.set_type("text");
+    input.set_placeholder("#rrggbb or rgb(r, g, b)");
+    style_color_picker_input(&input).unwrap();
+
+    let element_id = element_id.to_string();
+    let selection = selection.clone();
+    let on_keydown: Closure<dyn Fn(KeyboardEvent)> = Closure::new(move |e: KeyboardEvent| {
+        if e.key() != "Enter" {
+            return;
+        }
+        e.prevent_default();
+        let input = e.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        match parse_color(&input.value()) {
+            Some(color) => apply_color(&element_id, &color, target, &selection),
+            None => {
+                let _ = input.style().set_property("border-color", "red");
+            }
+        }
+    });
+    input
+        .add_event_listener_with_callback("keydown", on_keydown.as_ref().unchecked_ref())
+        .unwrap();
+    on_keydown.forget();
+    menu.append_child(&input).unwrap();
+}
+
+fn add_remove_color_item(
+    menu: &HtmlDivElement,
+    element_id: &str,
+    target: ColorTarget,
+    selection: &Option<Range>,
+) {
+    let el = document().create_element("a").unwrap();
+    let el = el.dyn_ref::<HtmlElement>().unwrap();
+    el.set_inner_text(target.remove_label());
+    style_color_picker_remove_item(el).unwrap();
+
+    let element_id = element_id.to_string();
+    let selection = selection.clone();
+    let on_click_item: Closure<dyn Fn(Event)> = Closure::new(move |_: Event| {
+        if let Some(range) = &selection {
+            restore_selection(range);
+        }
+        remove_color_from_selection(target);
+        remove_context_menu(&element_id);
+    });
+    el.add_event_listener_with_callback("click", on_click_item.as_ref().unchecked_ref())
+        .unwrap();
+    on_click_item.forget();
+    menu.append_child(el).unwrap();
+}
+
+fn apply_color(element_id: &str, color: &str, target: ColorTarget, selection: &Option<Range>) {
+    if let Some(range) = selection {
+        restore_selection(range);
+    }
+    let _ = exec(target.command(), color);
+    push_recent_color(element_id, color);
+    remove_context_menu(element_id);
+}
+
+fn push_recent_color(element_id: &str, color: &str) {
+    let mut recent_colors = RECENT_COLORS.lock().unwrap();
+    let colors = recent_colors.entry(element_id.to_string()).or_default();
+    colors.retain(|c| !c.eq_ignore_ascii_case(color));
+    colors.insert(0, color.to_string());
+    colors.truncate(16);
+}
+
+/// Removes the color from the selected text.
+///
+/// The selection is first colored with a sentinel color so the browser splits the
+/// formatting elements at the selection boundaries, then the sentinel is stripped.
+pub fn remove_color_from_selection(target: ColorTarget) {
+    let Some(range) = save_selection() else {
+        return;
+    };
+    if range.collapsed() {
+        return;
+    }
+    let _ = exec(target.command(), SENTINEL_COLOR);
+
+    let Ok(root) = range.common_ancestor_container() else {
+        return;
+    };
+    let root = match root.dyn_into::<web_sys::Element>() {
+        Ok(el) => el,
+        Err(node) => match node.parent_element() {
+            Some(el) => el,
+            None => return,
+        },
+    };
+    let root = root.parent_element().unwrap_or(root);
+
+    let property = target.css_property();
+    let marked = root
+        .query_selector_all(&format!("font[{property}], [style*=\"{property}\"]"))
+        .unwrap();
+    for i in 0..marked.length() {
+        let el = marked.item(i).unwrap().dyn_into::<HtmlElement>().unwrap();
+        let is_sentinel = el
+            .get_attribute(property)
+            .map(|c| c.eq_ignore_ascii_case(SENTINEL_COLOR))
+            .unwrap_or(false)
+            || el.style().get_property_value(property).unwrap() == SENTINEL_COLOR_RGB;
+        if !is_sentinel {
+            continue;
+        }
+
+        let _ = el.remove_attribute(property);
+        let _ = el.style().remove_property(property);
+        if el.style().length() == 0 {
+            let _ = el.remove_attribute("style");
+        }
+        if !el.has_attributes() {
+            unwrap_element(&el);
+        }
+    }
+
+    if let Ok(Some(content)) = root.closest("[contenteditable]") {
+        dispatch_input_event(&content.id());
+    }
+}
+
+fn style_color_picker_menu(el: &HtmlDivElement) -> Result<(), JsValue> {
+    el.style().set_property("border", "1px solid black")?;
+    el.style().set_property("background-color", "white")?;
+    el.style().set_property("display", "flex")?;
+    el.style().set_property("flex-direction", "column")
+}
+
+fn style_color_picker_row(el: &HtmlElement) -> Result<(), JsValue> {
+    el.style().set_property("width", "100%")?;
+    el.style()
+        .set_property("min-height", &format!("{ITEM_SIZE}px"))?;
+    el.style().set_property("display", "flex")?;
+    el.style().set_property("flex-wrap", "wrap")
+}
+
+fn style_color_picker_item(el: &HtmlElement, color: &str) -> Result<(), JsValue> {
+    el.style().set_property("width", "14px")?;
+    el.style().set_property("height", "14px")?;
+    el.style().set_property("background-color", color)?;
+    el.style().set_property("border", "2px solid #ffffff")?;
+    el.style().set_property("user-select", "none")
+}
+
+fn style_color_picker_input(el: &HtmlInputElement) -> Result<(), JsValue> {
+    el.style().set_property("box-sizing", "border-box")?;
+    el.style().set_property("width", "100%")?;
+    el.style()
+        .set_property("height", &format!("{INPUT_ROW_HEIGHT}px"))?;
+    el.style().set_property("border", "1px solid #cccccc")
+}
+
+fn style_color_picker_remove_item(el: &HtmlElement) -> Result<(), JsValue> {
+    el.style()
+        .set_property("height", &format!("{REMOVE_ROW_HEIGHT}px"))?;
+    el.style().set_property("font-size", "12px")?;
+    el.style().set_property("text-align", "center")?;
+    el.style().set_property("cursor", "pointer")?;
+    el.style().set_property("user-select", "none")
+}
@@ -0,0 +1,263 @@
+use crate::util::{
+    add_style, dispatch_input_event, insert_typed_text, restore_selection, save_selection,
+    split_range_text, unwrap_element,
+};
+use leptos_dom::document;
+use std::rc::Rc;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Element, InputEvent, MouseEvent, Node, Range, Text};
+
+/// Class of the markers that anchor the comments to the text
+pub const COMMENT_CLASS: &str = "papelito-comment";
+/// Attribute of the markers with the id of their comment thread
+pub const COMMENT_ATTRIBUTE: &str = "data-comment-id";
+const COMMENT_STYLE_ID: &str = "papelito-comment-style";
+
+/// Receives the ids of the comment threads of a clicked marker, the innermost first
+pub type CommentCallback = Rc<dyn Fn(Vec<String>)>;
+
+/// The text a comment thread is anchored to
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommentAnchor {
+    pub id: String,
+    pub text: String,
+}
+
+/// Highlights the commented text
+pub fn add_comment_style() -> Result<(), JsValue> {
+    add_style(
+        COMMENT_STYLE_ID,
+        &format!(
+            ".{COMMENT_CLASS} {{ background-color: rgba(255, 212, 0, 0.35); \
+             border-bottom: 2px solid rgb(255, 180, 0); cursor: pointer; }}"
+        ),
+    )
+}
+
+fn markers(root: &Element) -> Vec<Element> {
+    let markers = root
+        .query_selector_all(&format!(".{COMMENT_CLASS}"))
+        .unwrap();
+    (0..markers.length())
+        .filter_map(|i| markers.item(i))
+        .filter_map(|node| node.dyn_into::<Element>().ok())
+        .collect()
+}
+
+fn comment_id(marker: &Element) -> Option<String> {
+    marker.get_attribute(COMMENT_ATTRIBUTE)
+}
+
+fn is_marker_of(node: &Node, id: &str) -> bool {
+    node.dyn_ref::<Element>()
+        .map(|el| el.class_list().contains(COMMENT_CLASS) && comment_id(el).as_deref() == Some(id))
+        .unwrap_or(false)
+}
+
+/// Anchors a comment thread to the selected text, the text of each node is wrapped in a
+/// marker with the id of the thread. Returns `false` if nothing is selected
+pub fn add_comment(content_id: &str, comment_id: &str) -> Result<bool, JsValue> {
+    let root = document()
+        .get_element_by_id(content_id)
+        .ok_or("the content doesn't exist")?;
+    let Some(range) = save_selection() else {
+        return Ok(false);
+    };
+    if range.collapsed() || !root.contains(Some(&range.common_ancestor_container()?)) {
+        return Ok(false);
+    }
+
+    let mut wrapped = false;
+    for text in split_range_text(&range)? {
+        let node: Node = text.into();
+        let parent = node.parent_node().ok_or("the text has no parent")?;
+        match node.previous_sibling() {
+            Some(previous) if is_marker_of(&previous, comment_id) => {
+                previous.append_child(&node)?;
+            }
+            _ => {
+                let marker = document().create_element("span")?;
+                marker.set_class_name(COMMENT_CLASS);
+                marker.set_attribute(COMMENT_ATTRIBUTE, comment_id)?;
+                parent.insert_before(&marker, Some(&node))?;
+                marker.append_child(&node)?;
+            }
+        }
+        wrapped = true;
+    }
+    if wrapped {
+        dispatch_input_event(content_id);
+    }
+    Ok(wrapped)
+}
+
+/// Removes the markers of a comment thread, keeping their text
+pub fn remove_comment(content_id: &str, comment_id: &str) {
+    let Some(root) = document().get_element_by_id(content_id) else {
+        return;
+    };
+    let mut removed = false;
+    for marker in markers(&root) {
+        if is_marker_of(&marker, comment_id) {
+            unwrap_element(&marker);
+            removed = true;
+        }
+    }
+    if removed {
+        root.normalize();
+        dispatch_input_event(content_id);
+    }
+}
+
+/// Returns the comment threads anchored in the content, in the order of their first marker.
+/// Threads whose text was entirely deleted aren't returned
+pub fn comment_anchors(content_id: &str) -> Vec<CommentAnchor> {
+    let Some(root) = document().get_element_by_id(content_id) else {
+        return vec![];
+    };
+    let mut anchors: Vec<CommentAnchor> = vec![];
+    for marker in markers(&root) {
+        let Some(id) = comment_id(&marker) else {
+            continue;
+        };
+        let text = marker.text_content().unwrap_or_default();
+        match anchors.iter_mut().find(|anchor| anchor.id == id) {
+            Some(anchor) => anchor.text.push_str(&text),
+            None => anchors.push(CommentAnchor { id, text }),
+        }
+    }
+    anchors
+}
+
+/// Selects the text a comment thread is anchored to and scrolls to it
+pub fn select_comment(content_id: &str, comment_id: &str) -> Result<bool, JsValue> {
+    let root = document()
+        .get_element_by_id(content_id)
+        .ok_or("the content doesn't exist")?;
+    let thread: Vec<Element> = markers(&root)
+        .into_iter()
+        .filter(|marker| is_marker_of(marker, comment_id))
+        .collect();
+    let (Some(first), Some(last)) = (thread.first(), thread.last()) else {
+        return Ok(false);
+    };
+    let range = Range::new()?;
+    range.set_start_before(first)?;
+    range.set_end_after(last)?;
+    restore_selection(&range);
+    first.scroll_into_view();
+    Ok(true)
+}
+
+/// Returns the ids of the comment threads of the clicked marker, the innermost first
+pub fn clicked_comments(content_id: &str, e: &MouseEvent) -> Vec<String> {
+    let Some(root) = document().get_element_by_id(content_id) else {
+        return vec![];
+    };
+    let mut el = e.target().and_then(|t| t.dyn_into::<Element>().ok());
+    let mut ids = vec![];
+    while let Some(current) = el {
+        if current == root {
+            break;
+        }
+        if current.class_list().contains(COMMENT_CLASS) {
+            if let Some(id) = comment_id(&current) {
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+        el = current.parent_element();
+    }
+    ids
+}
+
+/// Removes the markers whose text was deleted, so a thread whose text is retyped isn't
+/// anchored to it again
+pub fn remove_empty_comment_markers(root: &Element) {
+    for marker in markers(root) {
+        if marker.text_content().unwrap_or_default().is_empty() {
+            marker.remove();
+        }
+    }
+}
+
+/// Keeps the text typed at the edges of a marker out of it, the browser would otherwise
+/// add it to the commented text. Returns `true` if the event was handled
+pub fn handle_comment_beforeinput(content_id: &str, e: &InputEvent) -> bool {
+    if e.input_type() != "insertText" || e.is_composing() {
+        return false;
+    }
+    let (Some(root), Some(text), Some(range)) = (
+        document().get_element_by_id(content_id),
+        e.data(),
+        save_selection(),
+    ) else {
+        return false;
+    };
+    if !range.collapsed() {
+        return false;
+    }
+    let (Ok(node), Ok(offset)) = (range.start_container(), range.start_offset()) else {
+        return false;
+    };
+    let Some(text_node) = node.dyn_ref::<Text>() else {
+        return false;
+    };
+    let Some(marker) = node
+        .parent_element()
+        .filter(|parent| parent.class_list().contains(COMMENT_CLASS))
+        .filter(|parent| root.contains(Some(parent)))
+    else {
+        return false;
+    };
+
+    let at_start = offset == 0 && node.previous_sibling().is_none();
+    let at_end = offset == text_node.length() && node.next_sibling().is_none();
+    let result = match (at_start, at_end) {
+        (true, _) => insert_text_outside(&marker, &text, false),
+        (_, true) => insert_text_outside(&marker, &text, true),
+        _ => return false,
+    };
+    if result.is_ok() {
+        e.prevent_default();
+        dispatch_input_event(content_id);
+    }
+    result.is_ok()
+}
+
+/// Inserts the text right before or after the marker and places the caret after it
+fn insert_text_outside(marker: &Element, text: &str, after: bool) -> Result<(), JsValue> {
+    let parent = marker.parent_node().ok_or("the marker has no parent")?;
+    let sibling = match after {
+        true => marker.next_sibling(),
+        false => marker.previous_sibling(),
+    };
+    let range = Range::new()?;
+    match sibling
+        .as_ref()
+        .and_then(|sibling| sibling.dyn_ref::<Text>())
+    {
+        Some(sibling) => {
+            let offset = match after {
+                true => 0,
+                false => sibling.length(),
+            };
+            let end = insert_typed_text(sibling, offset, text)?;
+            range.set_start(sibling, end)?;
+        }
+        None => {
+            let text_node = document().create_text_node("");
+            let reference = match after {
+                true => marker.next_sibling(),
+                false => Some(marker.clone().into()),
+            };
+            parent.insert_before(&text_node, reference.as_ref())?;
+            let end = insert_typed_text(&text_node, 0, text)?;
+            range.set_start(&text_node, end)?;
+        }
+    }
+    range.collapse_with_to_start(true);
+    restore_selection(&range);
+    Ok(())
+}
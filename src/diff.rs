@@ -0,0 +1,258 @@
+use crate::html::{parse_html, to_html, HtmlElement, HtmlNode};
+use crate::transaction::{flatten, matching_pairs, rebuild, text_block, Inline, Item, Mark};
+
+/// Class of the `<ins>` elements that mark the inserted content
+pub const DIFF_INSERTED_CLASS: &str = "papelito-diff-ins";
+/// Class of the `<del>` elements that mark the deleted content
+pub const DIFF_DELETED_CLASS: &str = "papelito-diff-del";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Change {
+    Inserted,
+    Deleted,
+}
+
+impl Change {
+    fn mark(self) -> Mark {
+        let (tag, class) = match self {
+            Change::Inserted => ("ins", DIFF_INSERTED_CLASS),
+            Change::Deleted => ("del", DIFF_DELETED_CLASS),
+        };
+        Mark {
+            tag: tag.to_string(),
+            attributes: vec![("class".to_string(), class.to_string())],
+        }
+    }
+
+    fn element(self) -> HtmlElement {
+        let mark = self.mark();
+        HtmlElement {
+            tag: mark.tag,
+            attributes: mark.attributes,
+            children: vec![],
+        }
+    }
+}
+
+/// Marks the whole node as changed. The blocks are kept and their content is marked, so
+/// the marks are valid inside lists and tables
+fn mark_node(node: &HtmlNode, change: Change) -> HtmlNode {
+    match node {
+        HtmlNode::Element(el) if el.is_block() && !el.is_void() => HtmlNode::Element(HtmlElement {
+            children: mark_nodes(&el.children, change),
+            ..el.clone()
+        }),
+        node => {
+            let mut wrapper = change.element();
+            wrapper.children.push(node.clone());
+            HtmlNode::Element(wrapper)
+        }
+    }
+}
+
+/// Marks the nodes as changed, joining the adjacent inline nodes in the same mark
+fn mark_nodes(nodes: &[HtmlNode], change: Change) -> Vec<HtmlNode> {
+    let mut marked: Vec<HtmlNode> = vec![];
+    for node in nodes {
+        let is_inline = !matches!(node, HtmlNode::Element(el) if el.is_block());
+        if let (true, Some(HtmlNode::Element(previous))) = (is_inline, marked.last_mut()) {
+            let mark = change.mark();
+            if previous.tag == mark.tag && previous.attributes == mark.attributes {
+                previous.children.push(node.clone());
+                continue;
+            }
+        }
+        marked.push(mark_node(node, change));
+    }
+    marked
+}
+
+/// Splits the inline content into words, runs of whitespace and single other characters
+fn tokens(inlines: &[Inline]) -> Vec<&[Inline]> {
+    fn word_kind(inline: &Inline) -> Option<bool> {
+        match inline.item {
+            Item::Char(c) if c.is_alphanumeric() => Some(true),
+            Item::Char(c) if c.is_whitespace() => Some(false),
+            _ => None,
+        }
+    }
+
+    let mut tokens = vec![];
+    let mut start = 0;
+    while start < inlines.len() {
+        let kind = word_kind(&inlines[start]);
+        let mut end = start + 1;
+        if kind.is_some() {
+            while end < inlines.len()
+                && word_kind(&inlines[end]) == kind
+                && inlines[end].marks == inlines[start].marks
+            {
+                end += 1;
+            }
+        }
+        tokens.push(&inlines[start..end]);
+        start = end;
+    }
+    tokens
+}
+
+/// Compares inline content word by word, a word whose formatting changed is shown as
+/// deleted and inserted
+fn diff_inlines(old: &[Inline], new: &[Inline]) -> Vec<HtmlNode> {
+    let old_tokens = tokens(old);
+    let new_tokens = tokens(new);
+    let mut merged: Vec<Inline> = vec![];
+    fn push_changed(merged: &mut Vec<Inline>, tokens: &[&[Inline]], change: Change) {
+        for inline in tokens.iter().flat_map(|token| token.iter()) {
+            let mut marks = vec![change.mark()];
+            marks.extend(inline.marks.iter().cloned());
+            merged.push(Inline {
+                item: inline.item.clone(),
+                marks,
+            });
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut pairs = matching_pairs(&old_tokens, &new_tokens);
+    pairs.push((old_tokens.len(), new_tokens.len()));
+    for (next_i, next_j) in pairs {
+        push_changed(&mut merged, &old_tokens[i..next_i], Change::Deleted);
+        push_changed(&mut merged, &new_tokens[j..next_j], Change::Inserted);
+        if let Some(token) = new_tokens.get(next_j) {
+            merged.extend_from_slice(token);
+        }
+        i = next_i + 1;
+        j = next_j + 1;
+    }
+    rebuild(&merged)
+}
+
+/// Compares two blocks that take the same place in their documents
+fn diff_block(old: &HtmlNode, new: &HtmlNode) -> Vec<HtmlNode> {
+    let replaced = || {
+        vec![
+            mark_node(old, Change::Deleted),
+            mark_node(new, Change::Inserted),
+        ]
+    };
+    match (old, new) {
+        (HtmlNode::Text(_), HtmlNode::Text(_)) => {
+            let (mut old_inlines, mut new_inlines) = (vec![], vec![]);
+            flatten(std::slice::from_ref(old), &mut vec![], &mut old_inlines);
+            flatten(std::slice::from_ref(new), &mut vec![], &mut new_inlines);
+            diff_inlines(&old_inlines, &new_inlines)
+        }
+        (HtmlNode::Element(old_el), HtmlNode::Element(new_el))
+            if old_el.tag == new_el.tag
+                && old_el.attributes == new_el.attributes
+                && old_el.is_block()
+                && !old_el.is_void() =>
+        {
+            let children = match (text_block(old), text_block(new)) {
+                (Some(old_inlines), Some(new_inlines)) => diff_inlines(&old_inlines, &new_inlines),
+                // Blocks with nested blocks, such as lists, are compared block by block
+                _ => diff_nodes(&old_el.children, &new_el.children),
+            };
+            vec![HtmlNode::Element(HtmlElement {
+                children,
+                ..new_el.clone()
+            })]
+        }
+        _ => replaced(),
+    }
+}
+
+fn diff_nodes(old: &[HtmlNode], new: &[HtmlNode]) -> Vec<HtmlNode> {
+    let old_html: Vec<String> = old.iter().map(HtmlNode::to_html).collect();
+    let new_html: Vec<String> = new.iter().map(HtmlNode::to_html).collect();
+    let mut merged = vec![];
+    let (mut i, mut j) = (0, 0);
+    let mut pairs = matching_pairs(&old_html, &new_html);
+    pairs.push((old.len(), new.len()));
+    for (next_i, next_j) in pairs {
+        // The blocks in between changed, they are compared in pairs and the rest was
+        // deleted or inserted
+        while i < next_i && j < next_j {
+            merged.extend(diff_block(&old[i], &new[j]));
+            i += 1;
+            j += 1;
+        }
+        merged.extend(old[i..next_i].iter().map(|n| mark_node(n, Change::Deleted)));
+        merged.extend(
+            new[j..next_j]
+                .iter()
+                .map(|n| mark_node(n, Change::Inserted)),
+        );
+        if let Some(node) = new.get(next_j) {
+            merged.push(node.clone());
+        }
+        i = next_i + 1;
+        j = next_j + 1;
+    }
+    merged
+}
+
+/// Compares two contents of an editor and returns the new one with the deleted content
+/// in `<del>` and the inserted content in `<ins>`. Blocks are matched first, and the text
+/// of the blocks that changed is compared word by word
+pub fn diff_html(old_html: &str, new_html: &str) -> String {
+    to_html(&diff_nodes(&parse_html(old_html), &parse_html(new_html)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INS: &str = "<ins class=\"papelito-diff-ins\">";
+    const DEL: &str = "<del class=\"papelito-diff-del\">";
+
+    #[test]
+    fn equal_contents_have_no_changes() {
+        let html = "<p>Hello <b>world</b></p><ul><li>one</li></ul>";
+        assert_eq!(diff_html(html, html), html);
+    }
+
+    #[test]
+    fn compares_text_word_by_word() {
+        assert_eq!(
+            diff_html("<p>Hello old world</p>", "<p>Hello new world!</p>"),
+            format!("<p>Hello {DEL}old</del>{INS}new</ins> world{INS}!</ins></p>")
+        );
+    }
+
+    #[test]
+    fn shows_reformatted_words_as_replaced() {
+        assert_eq!(
+            diff_html("<p>Hello world</p>", "<p>Hello <b>world</b></p>"),
+            format!("<p>Hello {DEL}world</del>{INS}<b>world</b></ins></p>")
+        );
+    }
+
+    #[test]
+    fn marks_the_content_of_inserted_and_deleted_blocks() {
+        assert_eq!(
+            diff_html("<p>one</p><p>two</p>", "<p>two</p><ul><li>three</li></ul>"),
+            format!("<p>{DEL}one</del></p><p>two</p><ul><li>{INS}three</ins></li></ul>")
+        );
+    }
+
+    #[test]
+    fn compares_nested_blocks_one_by_one() {
+        assert_eq!(
+            diff_html(
+                "<ul><li>one</li><li>two</li></ul>",
+                "<ul><li>one</li><li>2</li></ul>"
+            ),
+            format!("<ul><li>one</li><li>{DEL}two</del>{INS}2</ins></li></ul>")
+        );
+    }
+
+    #[test]
+    fn replaces_blocks_whose_type_changed() {
+        assert_eq!(
+            diff_html("<p>title</p>", "<h1>title</h1>"),
+            format!("<p>{DEL}title</del></p><h1>{INS}title</ins></h1>")
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use crate::diff::{diff_html, DIFF_DELETED_CLASS, DIFF_INSERTED_CLASS};
+use crate::embed::{default_embed_providers, sanitize_embeds, EmbedProvider};
+use crate::util::add_style;
+use leptos::html::Div;
+use leptos::*;
+
+const DIFF_STYLE_ID: &str = "papelito-diff-style";
+
+/// Shows the changes between two contents of an editor, such as two stored versions
+#[component]
+pub fn PapelitoDiffView(
+    cx: Scope,
+    #[prop(into)] old: MaybeSignal<String>,
+    #[prop(into)] new: MaybeSignal<String>,
+    #[prop(optional)] class: String,
+    /// The embeds of other providers are removed, like in the editor. The default providers
+    /// are used if `None`, pass the ones of the editor's `ActionOptions` otherwise
+    #[prop(optional)]
+    embed_providers: Option<Vec<EmbedProvider>>,
+) -> impl IntoView {
+    let diff_ref = create_node_ref::<Div>(cx);
+    let embed_providers = embed_providers.unwrap_or_else(default_embed_providers);
+    let diff = create_memo(cx, move |_| {
+        sanitize_embeds(&diff_html(&old.get(), &new.get()), &embed_providers)
+    });
+
+    create_effect(cx, move |_| {
+        let diff = diff.get();
+        if let Some(el) = diff_ref.get() {
+            el.inner_html(diff);
+        }
+    });
+
+    diff_ref.on_load(cx, move |el| {
+        let _ = add_style(
+            DIFF_STYLE_ID,
+            &format!(
+                ".{DIFF_INSERTED_CLASS} {{ background-color: #d4f7dc; text-decoration: none; }} \
+                 .{DIFF_DELETED_CLASS} {{ background-color: #fbdcdc; }}"
+            ),
+        );
+        el.inner_html(diff.get_untracked());
+    });
+
+    view! { cx,
+        <div class=class ref=diff_ref></div>
+    }
+}
@@ -0,0 +1,464 @@
+use crate::html::{parse_html, to_html, HtmlElement, HtmlNode};
+use crate::transaction::{flatten, rebuild, Inline, Item, Mark};
+#[cfg(feature = "serde")]
+use std::fmt;
+
+/// Version of the format of the documents written by this version of the crate
+pub const DOCUMENT_VERSION: u32 = 1;
+
+/// The content of an editor as a tree of blocks and marked text, which can be stored
+/// instead of its HTML. The HTML written by the editor, which is normalized, converts to a
+/// document and back without changes
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Document {
+    /// Version of the format, older documents are migrated when they are loaded
+    pub version: u32,
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum Node {
+    /// A block, or an inline element with blocks inside
+    Element {
+        tag: String,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Vec::is_empty")
+        )]
+        attributes: Vec<(String, String)>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Vec::is_empty")
+        )]
+        children: Vec<Node>,
+    },
+    /// Text with the marks that format it, from the outermost
+    Text {
+        text: String,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Vec::is_empty")
+        )]
+        marks: Vec<Mark>,
+    },
+    /// An inline element without text, such as an image or a line break
+    Atom {
+        tag: String,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Vec::is_empty")
+        )]
+        attributes: Vec<(String, String)>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Vec::is_empty")
+        )]
+        marks: Vec<Mark>,
+    },
+    Comment {
+        text: String,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Vec::is_empty")
+        )]
+        marks: Vec<Mark>,
+    },
+}
+
+/// Converts flattened inline content to nodes, joining the characters with the same marks
+fn inline_nodes(inlines: Vec<Inline>) -> Vec<Node> {
+    let mut nodes: Vec<Node> = vec![];
+    for inline in inlines {
+        let node = match inline.item {
+            Item::Char(c) => {
+                if let Some(Node::Text { text, marks }) = nodes.last_mut() {
+                    if *marks == inline.marks {
+                        text.push(c);
+                        continue;
+                    }
+                }
+                Node::Text {
+                    text: c.to_string(),
+                    marks: inline.marks,
+                }
+            }
+            Item::Object(HtmlNode::Element(el)) => Node::Atom {
+                tag: el.tag,
+                attributes: el.attributes,
+                marks: inline.marks,
+            },
+            Item::Object(HtmlNode::Comment(text)) => Node::Comment {
+                text,
+                marks: inline.marks,
+            },
+            Item::Object(HtmlNode::Text(text)) => Node::Text {
+                text,
+                marks: inline.marks,
+            },
+        };
+        nodes.push(node);
+    }
+    nodes
+}
+
+fn element_node(el: &HtmlElement) -> Node {
+    Node::Element {
+        tag: el.tag.clone(),
+        attributes: el.attributes.clone(),
+        children: nodes_from_html(&el.children),
+    }
+}
+
+fn nodes_from_html(html_nodes: &[HtmlNode]) -> Vec<Node> {
+    let mut nodes = vec![];
+    let mut start = 0;
+    while start < html_nodes.len() {
+        if let HtmlNode::Element(el) = &html_nodes[start] {
+            if el.is_block() {
+                nodes.push(element_node(el));
+                start += 1;
+                continue;
+            }
+        }
+        let end = html_nodes[start..]
+            .iter()
+            .position(|node| matches!(node, HtmlNode::Element(el) if el.is_block()))
+            .map(|end| start + end)
+            .unwrap_or(html_nodes.len());
+        let mut inlines = vec![];
+        if flatten(&html_nodes[start..end], &mut vec![], &mut inlines) {
+            nodes.extend(inline_nodes(inlines));
+        } else {
+            // An inline element has blocks inside, the elements are kept as they are
+            for node in &html_nodes[start..end] {
+                let mut inlines = vec![];
+                let flattened = flatten(std::slice::from_ref(node), &mut vec![], &mut inlines);
+                match node {
+                    HtmlNode::Element(el) if !flattened => nodes.push(element_node(el)),
+                    _ => nodes.extend(inline_nodes(inlines)),
+                }
+            }
+        }
+        start = end;
+    }
+    nodes
+}
+
+fn nodes_to_html(nodes: &[Node]) -> Vec<HtmlNode> {
+    let mut html_nodes = vec![];
+    let mut inlines = vec![];
+    for node in nodes {
+        match node {
+            Node::Element {
+                tag,
+                attributes,
+                children,
+            } => {
+                html_nodes.extend(rebuild(&inlines));
+                inlines.clear();
+                html_nodes.push(HtmlNode::Element(HtmlElement {
+                    tag: tag.clone(),
+                    attributes: attributes.clone(),
+                    children: nodes_to_html(children),
+                }));
+            }
+            Node::Text { text, marks } => inlines.extend(text.chars().map(|c| Inline {
+                item: Item::Char(c),
+                marks: marks.clone(),
+            })),
+            Node::Atom {
+                tag,
+                attributes,
+                marks,
+            } => inlines.push(Inline {
+                item: Item::Object(HtmlNode::Element(HtmlElement {
+                    tag: tag.clone(),
+                    attributes: attributes.clone(),
+                    children: vec![],
+                })),
+                marks: marks.clone(),
+            }),
+            Node::Comment { text, marks } => inlines.push(Inline {
+                item: Item::Object(HtmlNode::Comment(text.clone())),
+                marks: marks.clone(),
+            }),
+        }
+    }
+    html_nodes.extend(rebuild(&inlines));
+    html_nodes
+}
+
+impl Document {
+    pub fn from_html(html: &str) -> Self {
+        Self {
+            version: DOCUMENT_VERSION,
+            nodes: nodes_from_html(&parse_html(html)),
+        }
+    }
+
+    pub fn to_html(&self) -> String {
+        to_html(&nodes_to_html(&self.nodes))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Document {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("couldn't serialize the document")
+    }
+
+    /// Loads a document stored in any version of the format, see `Migrations`
+    pub fn from_json(json: &str) -> Result<Self, DocumentError> {
+        Migrations::new().load(json)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum DocumentError {
+    /// The JSON isn't a document
+    Json(String),
+    /// The document is newer than the target version of the migrations, or there is no
+    /// migration from its version
+    UnsupportedVersion(u32),
+    Migration {
+        version: u32,
+        message: String,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocumentError::Json(message) => write!(f, "invalid document: {message}"),
+            DocumentError::UnsupportedVersion(version) => {
+                write!(f, "unsupported document version {version}")
+            }
+            DocumentError::Migration { version, message } => {
+                write!(
+                    f,
+                    "couldn't migrate the document from version {version}: {message}"
+                )
+            }
+        }
+    }
+}
+
+/// Upgrades the JSON of a document from the version it is registered for to the next one
+#[cfg(feature = "serde")]
+pub type Migration = fn(serde_json::Value) -> Result<serde_json::Value, String>;
+
+/// The migrations applied to stored documents when they are loaded, until they reach
+/// `DOCUMENT_VERSION` or the target version.
+/// An app can change the documents it stores, e.g. its custom elements, by keeping its own
+/// versions after `DOCUMENT_VERSION`: it sets the version of the documents it stores, and
+/// registers the migrations to its current version with a target version
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug)]
+pub struct Migrations {
+    migrations: Vec<(u32, Migration)>,
+    target: u32,
+}
+
+#[cfg(feature = "serde")]
+impl Migrations {
+    /// The migrations between the versions of the format of this crate
+    pub fn new() -> Self {
+        // Each change of the format adds the migration from the previous version here
+        Self {
+            migrations: vec![],
+            target: DOCUMENT_VERSION,
+        }
+    }
+
+    /// Migrates the documents to `version` instead of `DOCUMENT_VERSION`, lower versions
+    /// are ignored
+    pub fn with_target_version(&mut self, version: u32) -> &mut Self {
+        self.target = version.max(DOCUMENT_VERSION);
+        self
+    }
+
+    /// Registers the migration of the documents of `version` to `version + 1`, replacing
+    /// the one of the crate if there is one
+    pub fn with_migration(&mut self, version: u32, migration: Migration) -> &mut Self {
+        self.migrations.retain(|(v, _)| *v != version);
+        self.migrations.push((version, migration));
+        self
+    }
+
+    /// Upgrades the JSON of a document to the target version
+    pub fn migrate(
+        &self,
+        mut value: serde_json::Value,
+    ) -> Result<serde_json::Value, DocumentError> {
+        loop {
+            let version = value
+                .get("version")
+                .and_then(|version| version.as_u64())
+                .ok_or_else(|| DocumentError::Json("the version is missing".to_string()))?;
+            let version = u32::try_from(version).map_err(|_| {
+                DocumentError::Json(format!("the version {version} is out of range"))
+            })?;
+            if version == self.target {
+                return Ok(value);
+            }
+            let migration = self
+                .migrations
+                .iter()
+                .find(|(v, _)| *v == version && version < self.target)
+                .map(|(_, migration)| migration)
+                .ok_or(DocumentError::UnsupportedVersion(version))?;
+            value = migration(value)
+                .map_err(|message| DocumentError::Migration { version, message })?;
+            if let Some(object) = value.as_object_mut() {
+                object.insert("version".to_string(), (version + 1).into());
+            }
+        }
+    }
+
+    /// Parses and migrates a stored document
+    pub fn load(&self, json: &str) -> Result<Document, DocumentError> {
+        let value = serde_json::from_str(json).map_err(|e| DocumentError::Json(e.to_string()))?;
+        serde_json::from_value(self.migrate(value)?).map_err(|e| DocumentError::Json(e.to_string()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Default for Migrations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_html_to_a_document_and_back() {
+        let html = "<p>Hello <strong>bold <em>both</em></strong><br>\
+                    <img src=\"a.png\"><!-- note --></p>\
+                    <ul><li>one</li><li><p>two</p></li></ul><hr>";
+        let document = Document::from_html(html);
+        assert_eq!(document.version, DOCUMENT_VERSION);
+        assert_eq!(document.to_html(), html);
+    }
+
+    #[test]
+    fn joins_text_with_the_same_marks() {
+        let strong = Mark {
+            tag: "strong".to_string(),
+            attributes: vec![],
+        };
+        assert_eq!(
+            Document::from_html("<p>a<strong>b</strong><strong>c</strong></p>").nodes,
+            vec![Node::Element {
+                tag: "p".to_string(),
+                attributes: vec![],
+                children: vec![
+                    Node::Text {
+                        text: "a".to_string(),
+                        marks: vec![],
+                    },
+                    Node::Text {
+                        text: "bc".to_string(),
+                        marks: vec![strong],
+                    },
+                ],
+            }]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    mod migrations {
+        use super::*;
+        use serde_json::{json, Value};
+
+        fn rename_divs(mut value: Value) -> Result<Value, String> {
+            for node in value["nodes"].as_array_mut().ok_or("no nodes")? {
+                if node["tag"] == "div" {
+                    node["tag"] = "p".into();
+                }
+            }
+            Ok(value)
+        }
+
+        fn fail(_: Value) -> Result<Value, String> {
+            Err("broken".to_string())
+        }
+
+        fn stored(version: u64) -> String {
+            json!({
+                "version": version,
+                "nodes": [{ "type": "element", "tag": "div" }],
+            })
+            .to_string()
+        }
+
+        #[test]
+        fn round_trips_through_json() {
+            let document = Document::from_html("<p>a <a href=\"u\">link</a></p>");
+            assert_eq!(Document::from_json(&document.to_json()), Ok(document));
+        }
+
+        #[test]
+        fn migrates_to_the_target_version() {
+            let target = DOCUMENT_VERSION + 1;
+            let document = Migrations::new()
+                .with_migration(DOCUMENT_VERSION, rename_divs)
+                .with_target_version(target)
+                .load(&stored(DOCUMENT_VERSION.into()))
+                .unwrap();
+            assert_eq!(document.version, target);
+            assert_eq!(document.to_html(), "<p></p>");
+
+            // Without a target version the migrations of the app aren't applied
+            let document = Migrations::new()
+                .with_migration(DOCUMENT_VERSION, rename_divs)
+                .load(&stored(DOCUMENT_VERSION.into()))
+                .unwrap();
+            assert_eq!(document.to_html(), "<div></div>");
+        }
+
+        #[test]
+        fn rejects_unknown_versions() {
+            let migrations = Migrations::new();
+            let newer = u64::from(DOCUMENT_VERSION) + 1;
+            assert_eq!(
+                migrations.load(&stored(newer)),
+                Err(DocumentError::UnsupportedVersion(DOCUMENT_VERSION + 1))
+            );
+            let out_of_range = u64::from(u32::MAX) + u64::from(DOCUMENT_VERSION) + 1;
+            assert!(matches!(
+                migrations.load(&stored(out_of_range)),
+                Err(DocumentError::Json(_))
+            ));
+            assert!(matches!(
+                migrations.load("{\"nodes\": []}"),
+                Err(DocumentError::Json(_))
+            ));
+        }
+
+        #[test]
+        fn reports_failed_migrations() {
+            let error = Migrations::new()
+                .with_migration(DOCUMENT_VERSION, fail)
+                .with_target_version(DOCUMENT_VERSION + 1)
+                .load(&stored(DOCUMENT_VERSION.into()));
+            assert_eq!(
+                error,
+                Err(DocumentError::Migration {
+                    version: DOCUMENT_VERSION,
+                    message: "broken".to_string(),
+                })
+            );
+        }
+    }
+}
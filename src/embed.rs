@@ -0,0 +1,327 @@
+use crate::highlight::escape_html;
+use crate::util::exec;
+use leptos_dom::document;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Element, HtmlTemplateElement};
+
+/// Class of the block that wraps an embed
+pub const EMBED_CLASS: &str = "papelito-embed";
+
+/// Elements that load other documents or plugins, they are never allowed in the content
+const FRAME_ELEMENTS: &str = "frame, frameset, object, embed, applet";
+
+/// Attributes kept on the allowed iframes, the others, such as `srcdoc` or event handlers,
+/// could run other content than the one of the provider
+const IFRAME_ATTRIBUTES: [&str; 6] = [
+    "allowfullscreen",
+    "height",
+    "src",
+    "style",
+    "title",
+    "width",
+];
+
+/// What the embedded content of the providers can do, set on every iframe
+pub const IFRAME_SANDBOX: &str = "allow-scripts allow-same-origin allow-popups allow-presentation";
+
+/// A source of embeds, used both to turn URLs into embeds and to decide which iframes are
+/// allowed in the content
+#[derive(Clone, Debug, PartialEq)]
+pub enum EmbedProvider {
+    YouTube,
+    Vimeo,
+    /// Any `https` URL whose host is in the list. Hosts starting with `*.` also match
+    /// their subdomains
+    Iframe(Vec<String>),
+}
+
+pub fn default_embed_providers() -> Vec<EmbedProvider> {
+    vec![EmbedProvider::YouTube, EmbedProvider::Vimeo]
+}
+
+struct ParsedUrl {
+    scheme: String,
+    host: String,
+    path: String,
+    query: String,
+}
+
+/// Parses an `http` or `https` URL. URLs that browsers could read with another host, such as
+/// the ones with credentials or backslashes, are rejected
+fn parse_url(url: &str) -> Option<ParsedUrl> {
+    let url = url.trim();
+    if url.contains('\\') || url.chars().any(char::is_control) {
+        return None;
+    }
+    let (scheme, rest) = url.split_once("://")?;
+    let scheme = scheme.to_lowercase();
+    if scheme != "http" && scheme != "https" {
+        return None;
+    }
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let authority = &rest[..authority_end];
+    let (host, port) = authority.split_once(':').unwrap_or((authority, ""));
+    let host = host.to_lowercase();
+    let is_host = !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    if !is_host || !port.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let rest = &rest[authority_end..];
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    Some(ParsedUrl {
+        scheme,
+        host,
+        path: path.to_string(),
+        query: query.to_string(),
+    })
+}
+
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+fn is_youtube_id(id: &str) -> bool {
+    id.len() == 11
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn host_matches(host: &str, allowed: &str) -> bool {
+    let allowed = allowed.to_lowercase();
+    match allowed.strip_prefix("*.") {
+        Some(domain) => host == domain || host.ends_with(&format!(".{domain}")),
+        None => host == allowed,
+    }
+}
+
+impl EmbedProvider {
+    /// Returns the URL of the iframe that embeds the given URL, if this provider supports it
+    pub fn embed_url(&self, url: &str) -> Option<String> {
+        let parsed = parse_url(url)?;
+        match self {
+            EmbedProvider::YouTube => {
+                let id = match parsed.host.as_str() {
+                    "youtu.be" => parsed.path.trim_start_matches('/').to_string(),
+                    "youtube.com" | "www.youtube.com" | "m.youtube.com" => {
+                        match parsed.path.as_str() {
+                            "/watch" => query_param(&parsed.query, "v")?.to_string(),
+                            path => path
+                                .strip_prefix("/embed/")
+                                .or_else(|| path.strip_prefix("/shorts/"))?
+                                .to_string(),
+                        }
+                    }
+                    _ => return None,
+                };
+                is_youtube_id(&id).then(|| format!("https://www.youtube.com/embed/{id}"))
+            }
+            EmbedProvider::Vimeo => {
+                if parsed.host != "vimeo.com" && parsed.host != "www.vimeo.com" {
+                    return None;
+                }
+                let id = parsed.path.trim_start_matches('/');
+                (!id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+                    .then(|| format!("https://player.vimeo.com/video/{id}"))
+            }
+            EmbedProvider::Iframe(_) => self.allows_src(url).then(|| url.trim().to_string()),
+        }
+    }
+
+    /// Returns `true` if an iframe with the given `src` belongs to this provider
+    pub fn allows_src(&self, src: &str) -> bool {
+        let Some(parsed) = parse_url(src) else {
+            return false;
+        };
+        if parsed.scheme != "https" {
+            return false;
+        }
+        match self {
+            EmbedProvider::YouTube => {
+                matches!(
+                    parsed.host.as_str(),
+                    "www.youtube.com" | "youtube.com" | "www.youtube-nocookie.com"
+                ) && parsed.path.starts_with("/embed/")
+            }
+            EmbedProvider::Vimeo => {
+                parsed.host == "player.vimeo.com" && parsed.path.starts_with("/video/")
+            }
+            EmbedProvider::Iframe(hosts) => hosts
+                .iter()
+                .any(|allowed| host_matches(&parsed.host, allowed)),
+        }
+    }
+}
+
+/// Returns the URL of the iframe that embeds the given URL using the first provider that
+/// supports it
+pub fn embed_url(providers: &[EmbedProvider], url: &str) -> Option<String> {
+    providers
+        .iter()
+        .find_map(|provider| provider.embed_url(url))
+}
+
+/// Returns the HTML of a non editable, responsive embed block
+pub fn embed_html(src: &str) -> String {
+    format!(
+        "<div class=\"{EMBED_CLASS}\" contenteditable=\"false\">\
+            <iframe src=\"{}\" style=\"width: 100%; aspect-ratio: 16 / 9; border: 0;\" \
+                sandbox=\"{IFRAME_SANDBOX}\" allowfullscreen></iframe>\
+        </div>",
+        escape_html(src)
+    )
+}
+
+/// Inserts an embed of the given URL at the caret, followed by an empty `separator` block.
+/// Returns `false` if no provider supports the URL
+pub fn insert_embed(
+    providers: &[EmbedProvider],
+    url: &str,
+    separator: &str,
+) -> Result<bool, JsValue> {
+    let Some(src) = embed_url(providers, url) else {
+        return Ok(false);
+    };
+    exec(
+        "insertHTML",
+        &format!("{}<{separator}><br></{separator}>", embed_html(&src)),
+    )
+}
+
+/// Returns the attributes that an allowed iframe keeps, with the sandbox of the embeds
+fn iframe_attributes(attributes: &[(String, String)]) -> Vec<(String, String)> {
+    let mut kept: Vec<(String, String)> = attributes
+        .iter()
+        .filter(|(name, _)| IFRAME_ATTRIBUTES.contains(&name.to_lowercase().as_str()))
+        .cloned()
+        .collect();
+    kept.push(("sandbox".to_string(), IFRAME_SANDBOX.to_string()));
+    kept
+}
+
+/// Removes the iframes that aren't allowed by any of the providers from the HTML, and the
+/// frames, objects and plugins, which never are. The allowed iframes only keep the
+/// attributes of an embed and are sandboxed. The HTML is parsed inside a `<template>`, so
+/// nothing is loaded
+pub fn sanitize_embeds(html: &str, providers: &[EmbedProvider]) -> String {
+    let template = document()
+        .create_element("template")
+        .unwrap()
+        .unchecked_into::<HtmlTemplateElement>();
+    template.set_inner_html(html);
+
+    let frames = template
+        .content()
+        .query_selector_all(FRAME_ELEMENTS)
+        .unwrap();
+    for i in 0..frames.length() {
+        frames.item(i).unwrap().unchecked_into::<Element>().remove();
+    }
+
+    let iframes = template.content().query_selector_all("iframe").unwrap();
+    for i in 0..iframes.length() {
+        let iframe = iframes.item(i).unwrap().unchecked_into::<Element>();
+        let src = iframe.get_attribute("src").unwrap_or_default();
+        if providers.iter().any(|provider| provider.allows_src(&src)) {
+            let attributes: Vec<(String, String)> = iframe
+                .get_attribute_names()
+                .iter()
+                .filter_map(|name| name.as_string())
+                .map(|name| {
+                    let value = iframe.get_attribute(&name).unwrap_or_default();
+                    (name, value)
+                })
+                .collect();
+            for (name, _) in &attributes {
+                let _ = iframe.remove_attribute(name);
+            }
+            for (name, value) in iframe_attributes(&attributes) {
+                let _ = iframe.set_attribute(&name, &value);
+            }
+            continue;
+        }
+        match iframe.closest(&format!(".{EMBED_CLASS}")) {
+            Ok(Some(embed)) => embed.remove(),
+            _ => iframe.remove(),
+        }
+    }
+
+    let embeds = template
+        .content()
+        .query_selector_all(&format!(".{EMBED_CLASS}"))
+        .unwrap();
+    for i in 0..embeds.length() {
+        let embed = embeds.item(i).unwrap().unchecked_into::<Element>();
+        let _ = embed.set_attribute("contenteditable", "false");
+    }
+    template.inner_html()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embeds_the_urls_of_the_providers() {
+        let providers = default_embed_providers();
+        assert_eq!(
+            embed_url(
+                &providers,
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=1"
+            )
+            .as_deref(),
+            Some("https://www.youtube.com/embed/dQw4w9WgXcQ")
+        );
+        assert_eq!(
+            embed_url(&providers, "https://vimeo.com/76979871").as_deref(),
+            Some("https://player.vimeo.com/video/76979871")
+        );
+        assert_eq!(embed_url(&providers, "https://example.com/video"), None);
+    }
+
+    #[test]
+    fn rejects_sources_with_another_host() {
+        let youtube = EmbedProvider::YouTube;
+        assert!(youtube.allows_src("https://www.youtube.com/embed/dQw4w9WgXcQ"));
+        assert!(youtube.allows_src("https://www.youtube.com:443/embed/dQw4w9WgXcQ"));
+        assert!(!youtube.allows_src("https://evil.example\\@www.youtube.com/embed/x"));
+        assert!(!youtube.allows_src("https://evil.example@www.youtube.com/embed/x"));
+        assert!(!youtube.allows_src("https://www.youtube.com\\@evil.example/embed/x"));
+        assert!(!youtube.allows_src("https://www.you\ttube.com/embed/x"));
+        assert!(!youtube.allows_src("http://www.youtube.com/embed/x"));
+
+        let iframe = EmbedProvider::Iframe(vec!["*.example.com".to_string()]);
+        assert!(iframe.allows_src("https://maps.example.com/embed"));
+        assert!(!iframe.allows_src("https://evilexample.com/embed"));
+        assert!(!iframe.allows_src("https://evil.test%2F.example.com/embed"));
+    }
+
+    #[test]
+    fn keeps_only_the_attributes_of_an_embed() {
+        let attribute = |name: &str, value: &str| (name.to_string(), value.to_string());
+        assert_eq!(
+            iframe_attributes(&[
+                attribute("src", "https://www.youtube.com/embed/x"),
+                attribute("srcdoc", "<script>alert(1)</script>"),
+                attribute("onload", "alert(1)"),
+                attribute("allow", "camera; microphone"),
+                attribute("sandbox", ""),
+                attribute("allowfullscreen", ""),
+            ]),
+            vec![
+                attribute("src", "https://www.youtube.com/embed/x"),
+                attribute("allowfullscreen", ""),
+                attribute("sandbox", IFRAME_SANDBOX),
+            ]
+        );
+    }
+}
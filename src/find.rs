@@ -0,0 +1,877 @@
+use crate::util::{
+    add_context_menu_at, context_menu_id, exec, remove_context_menu, restore_selection,
+    save_selection,
+};
+use lazy_static::lazy_static;
+use leptos_dom::{document, window};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{
+    Element, Event, HtmlDivElement, HtmlElement, HtmlInputElement, KeyboardEvent, Node, Range,
+};
+
+const PANEL_WIDTH: f64 = 320.;
+const PANEL_ROW_HEIGHT: f64 = 28.;
+
+/// Elements whose text is never joined with the text around them when searching
+const BLOCK_SELECTOR: &str = "p, div, h1, h2, h3, h4, h5, h6, li, pre, blockquote, figcaption, \
+                              td, th";
+
+/// Search options and the match that is currently selected
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FindState {
+    pub query: String,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    pub regex: bool,
+    pub current: usize,
+    /// Whether the panel shows the replace row
+    pub with_replace: bool,
+}
+
+lazy_static! {
+    static ref FIND_STATES: Mutex<HashMap<String, FindState>> = Mutex::new(HashMap::new());
+}
+
+fn find_state(content_id: &str) -> Option<FindState> {
+    FIND_STATES.lock().unwrap().get(content_id).cloned()
+}
+
+fn update_find_state(content_id: &str, f: impl FnOnce(&mut FindState)) {
+    if let Some(state) = FIND_STATES.lock().unwrap().get_mut(content_id) {
+        f(state);
+    }
+}
+
+/// Escapes the characters that have a special meaning in a regular expression
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\^$.|?*+()[]{}/".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl FindState {
+    fn pattern(&self) -> String {
+        let pattern = match self.regex {
+            true => self.query.clone(),
+            false => escape_regex(&self.query),
+        };
+        match self.whole_word {
+            true => format!("\\b(?:{pattern})\\b"),
+            false => pattern,
+        }
+    }
+
+    fn flags(&self, global: bool) -> String {
+        let mut flags = String::new();
+        if global {
+            flags.push('g');
+        }
+        if !self.case_sensitive {
+            flags.push('i');
+        }
+        flags
+    }
+
+    /// Builds the regular expression of the search, fails if the query is an invalid regex
+    fn build_regex(&self, global: bool) -> Result<js_sys::RegExp, JsValue> {
+        let constructor = js_sys::Reflect::get(&js_sys::global(), &"RegExp".into())?
+            .dyn_into::<js_sys::Function>()?;
+        let args = js_sys::Array::of2(&self.pattern().into(), &self.flags(global).into());
+        Ok(js_sys::Reflect::construct(&constructor, &args)?.unchecked_into())
+    }
+
+    /// Returns the text that replaces a match, `$1` and such are expanded from its groups in
+    /// regex mode
+    fn replacement_for(&self, found: &FindMatch, replacement: &str) -> String {
+        match self.regex {
+            true => expand_replacement(replacement, &found.groups),
+            false => replacement.to_string(),
+        }
+    }
+}
+
+/// Expands `$$`, `$&` and `$1` to `$99` in the replacement as `String.replace` does, from
+/// the groups of the match, the first one being the whole match
+fn expand_replacement(replacement: &str, groups: &[Option<String>]) -> String {
+    let chars: Vec<char> = replacement.chars().collect();
+    let group = |n: usize| groups.get(n).cloned().flatten().unwrap_or_default();
+    let digit = |i: usize| {
+        chars
+            .get(i)
+            .and_then(|c| c.to_digit(10))
+            .map(|d| d as usize)
+    };
+    let mut expanded = String::with_capacity(replacement.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != '$' {
+            expanded.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        match chars.get(i + 1) {
+            Some('$') => {
+                expanded.push('$');
+                i += 2;
+            }
+            Some('&') => {
+                expanded.push_str(&group(0));
+                i += 2;
+            }
+            _ => {
+                let is_group = |n: &usize| *n > 0 && *n < groups.len();
+                let one = digit(i + 1).filter(is_group);
+                let two = digit(i + 1)
+                    .zip(digit(i + 2))
+                    .map(|(tens, units)| tens * 10 + units)
+                    .filter(is_group);
+                match (two, one) {
+                    (Some(n), _) => {
+                        expanded.push_str(&group(n));
+                        i += 3;
+                    }
+                    (None, Some(n)) => {
+                        expanded.push_str(&group(n));
+                        i += 2;
+                    }
+                    (None, None) => {
+                        expanded.push('$');
+                        i += 1;
+                    }
+                }
+            }
+        }
+    }
+    expanded
+}
+
+/// The searchable text of the content, with the text node and offset of each UTF-16 unit.
+/// Blocks are separated by a `\n` that doesn't belong to any node
+pub(crate) struct SearchText {
+    pub text: String,
+    pub positions: Vec<Option<(Node, u32)>>,
+}
+
+impl SearchText {
+    /// Returns the range of the text between the given UTF-16 offsets, `None` if it
+    /// crosses blocks
+    pub fn range(&self, start: usize, end: usize) -> Option<Range> {
+        let positions = self.positions.get(start..end).filter(|p| !p.is_empty())?;
+        if positions.iter().any(|position| position.is_none()) {
+            return None;
+        }
+        let (start_node, start_offset) = positions[0].clone()?;
+        let (end_node, end_offset) = positions[positions.len() - 1].clone()?;
+        let range = Range::new().ok()?;
+        range.set_start(&start_node, start_offset).ok()?;
+        range.set_end(&end_node, end_offset + 1).ok()?;
+        Some(range)
+    }
+}
+
+pub(crate) fn collect_search_text(root: &Element) -> SearchText {
+    let mut search = SearchText {
+        text: String::new(),
+        positions: vec![],
+    };
+    let mut last_block: Option<Element> = None;
+    collect_text_nodes(root, root, &mut last_block, &mut search);
+    search
+}
+
+fn collect_text_nodes(
+    root: &Element,
+    node: &Node,
+    last_block: &mut Option<Element>,
+    search: &mut SearchText,
+) {
+    let children = node.child_nodes();
+    for i in 0..children.length() {
+        let Some(child) = children.item(i) else {
+            continue;
+        };
+        if let Some(el) = child.dyn_ref::<Element>() {
+            // Embeds and checkboxes aren't text the user can edit
+            if el.get_attribute("contenteditable").as_deref() == Some("false")
+                || el.tag_name().eq_ignore_ascii_case("input")
+            {
+                continue;
+            }
+            collect_text_nodes(root, &child, last_block, search);
+        } else if let Some(text) = child.dyn_ref::<web_sys::Text>() {
+            let block = child
+                .parent_element()
+                .and_then(|parent| parent.closest(BLOCK_SELECTOR).ok().flatten())
+                .filter(|block| root.contains(Some(block)));
+            if block != *last_block {
+                search.text.push('\n');
+                search.positions.push(None);
+                *last_block = block;
+            }
+            let data = text.data();
+            search.text.push_str(&data);
+            for (offset, _) in data.encode_utf16().enumerate() {
+                search.positions.push(Some((child.clone(), offset as u32)));
+            }
+        }
+    }
+}
+
+/// A match of the search, with the text of its groups
+struct FindMatch {
+    range: Range,
+    /// The whole match followed by the capture groups, `None` for the ones that didn't
+    /// take part in the match
+    groups: Vec<Option<String>>,
+}
+
+/// Returns every match of the search inside the root
+fn find_matches(root: &Element, state: &FindState) -> Vec<FindMatch> {
+    if state.query.is_empty() {
+        return vec![];
+    }
+    let Ok(regex) = state.build_regex(true) else {
+        return vec![];
+    };
+    let search = collect_search_text(root);
+
+    let mut matches = vec![];
+    while let Some(result) = regex.exec(&search.text) {
+        let index = js_sys::Reflect::get(&result, &"index".into())
+            .ok()
+            .and_then(|index| index.as_f64())
+            .unwrap_or_default() as usize;
+        let matched: String = result.get(0).as_string().unwrap_or_default();
+        let len = matched.encode_utf16().count();
+        if len == 0 {
+            regex.set_last_index(regex.last_index() + 1);
+            continue;
+        }
+        // Matches that cross blocks are ignored
+        if let Some(range) = search.range(index, index + len) {
+            let groups = (0..result.length())
+                .map(|i| result.get(i).as_string())
+                .collect();
+            matches.push(FindMatch { range, groups });
+        }
+    }
+    matches
+}
+
+/// Returns the key used to register the find panel of the editor
+fn panel_key(content_id: &str) -> String {
+    format!("{content_id}-find")
+}
+
+fn highlights_id(content_id: &str) -> String {
+    format!("{content_id}-find-highlights")
+}
+
+fn count_id(content_id: &str) -> String {
+    format!("{content_id}-find-count")
+}
+
+fn find_input_id(content_id: &str) -> String {
+    format!("{content_id}-find-input")
+}
+
+fn replace_input_id(content_id: &str) -> String {
+    format!("{content_id}-replace-input")
+}
+
+/// Opens the find panel on Ctrl+F, or the find and replace panel on Ctrl+H.
+/// Returns `true` if the event was handled
+pub fn handle_find_keydown(content_id: &str, e: &KeyboardEvent) -> bool {
+    if !(e.ctrl_key() || e.meta_key()) {
+        return false;
+    }
+    let with_replace = match e.key().to_lowercase().as_str() {
+        "f" => false,
+        "h" => true,
+        _ => return false,
+    };
+    e.prevent_default();
+    open_find_panel(content_id, with_replace);
+    true
+}
+
+/// Opens the find panel of the editor, the selected text is used as the query
+pub fn open_find_panel(content_id: &str, with_replace: bool) {
+    let selected = save_selection()
+        .map(|range| range_text(&range))
+        .filter(|text| !text.is_empty() && !text.contains('\n'));
+
+    let mut state = find_state(content_id).unwrap_or_default();
+    if let Some(selected) = selected {
+        state.query = selected;
+        state.current = 0;
+    }
+    state.with_replace = with_replace;
+    FIND_STATES
+        .lock()
+        .unwrap()
+        .insert(content_id.to_string(), state.clone());
+
+    remove_context_menu(&panel_key(content_id));
+    let Some(content) = document().get_element_by_id(content_id) else {
+        return;
+    };
+    let rect = content.get_bounding_client_rect();
+    let rows = if with_replace { 3. } else { 2. };
+    let panel = add_context_menu_at(
+        &panel_key(content_id),
+        rect.top(),
+        rect.right() - PANEL_WIDTH,
+        PANEL_WIDTH,
+        PANEL_ROW_HEIGHT * rows,
+        vec![content_id.to_string()],
+    );
+    style_panel(&panel).unwrap();
+
+    add_find_row(&panel, content_id, &state);
+    add_options_row(&panel, content_id, &state);
+    if with_replace {
+        add_replace_row(&panel, content_id);
+    }
+    refresh_find(content_id);
+
+    let focused = match with_replace && !state.query.is_empty() {
+        true => replace_input_id(content_id),
+        false => find_input_id(content_id),
+    };
+    if let Some(input) = document()
+        .get_element_by_id(&focused)
+        .and_then(|el| el.dyn_into::<HtmlInputElement>().ok())
+    {
+        let _ = input.focus();
+        input.select();
+    }
+}
+
+/// Closes the find panel of the editor and removes its highlights
+pub fn close_find_panel(content_id: &str) {
+    remove_context_menu(&panel_key(content_id));
+    if let Some(highlights) = document().get_element_by_id(&highlights_id(content_id)) {
+        highlights.remove();
+    }
+    FIND_STATES.lock().unwrap().remove(content_id);
+}
+
+/// Returns `true` if the find panel of the editor is open
+pub fn is_find_panel_open(content_id: &str) -> bool {
+    document()
+        .get_element_by_id(&context_menu_id(&panel_key(content_id)))
+        .is_some()
+}
+
+/// Highlights the matches of the search and updates the match count, called whenever the
+/// search or the content changes. The highlights are drawn over the content, so the
+/// content itself isn't modified
+pub fn refresh_find(content_id: &str) {
+    let Some(state) = find_state(content_id) else {
+        return;
+    };
+    if !is_find_panel_open(content_id) {
+        // The panel was closed by a click outside of it
+        close_find_panel(content_id);
+        return;
+    }
+    let Some(root) = document().get_element_by_id(content_id) else {
+        return;
+    };
+    let matches = find_matches(&root, &state);
+    let current = match matches.is_empty() {
+        true => 0,
+        false => state.current.min(matches.len() - 1),
+    };
+    update_find_state(content_id, |state| state.current = current);
+
+    if let Some(count) = document()
+        .get_element_by_id(&count_id(content_id))
+        .and_then(|el| el.dyn_into::<HtmlElement>().ok())
+    {
+        let text = match (matches.is_empty(), state.query.is_empty()) {
+            (_, true) => String::new(),
+            (true, false) => "No results".to_string(),
+            (false, false) => format!("{}/{}", current + 1, matches.len()),
+        };
+        count.set_inner_text(&text);
+    }
+    draw_highlights(content_id, &matches, current);
+}
+
+fn draw_highlights(content_id: &str, matches: &[FindMatch], current: usize) {
+    let container = match document().get_element_by_id(&highlights_id(content_id)) {
+        Some(container) => container,
+        None => {
+            let container = document().create_element("div").unwrap();
+            container.set_id(&highlights_id(content_id));
+            document().body().unwrap().append_child(&container).unwrap();
+            container
+        }
+    };
+    container.set_inner_html("");
+
+    let scroll_x = window().scroll_x().unwrap_or_default();
+    let scroll_y = window().scroll_y().unwrap_or_default();
+    for (index, found) in matches.iter().enumerate() {
+        let Some(rects) = found.range.get_client_rects() else {
+            continue;
+        };
+        for i in 0..rects.length() {
+            let Some(rect) = rects.get(i) else {
+                continue;
+            };
+            let el = document()
+                .create_element("div")
+                .unwrap()
+                .unchecked_into::<HtmlElement>();
+            let style = el.style();
+            let _ = style.set_property("position", "absolute");
+            let _ = style.set_property("pointer-events", "none");
+            let _ = style.set_property("top", &format!("{}px", rect.top() + scroll_y));
+            let _ = style.set_property("left", &format!("{}px", rect.left() + scroll_x));
+            let _ = style.set_property("width", &format!("{}px", rect.width()));
+            let _ = style.set_property("height", &format!("{}px", rect.height()));
+            let color = match index == current {
+                true => "rgba(255, 150, 0, 0.5)",
+                false => "rgba(255, 230, 0, 0.4)",
+            };
+            let _ = style.set_property("background-color", color);
+            container.append_child(&el).unwrap();
+        }
+    }
+}
+
+/// Moves to the next match, or to the previous one if `forward` is `false`
+pub fn step_match(content_id: &str, forward: bool) {
+    let (Some(state), Some(root)) = (
+        find_state(content_id),
+        document().get_element_by_id(content_id),
+    ) else {
+        return;
+    };
+    let matches = find_matches(&root, &state);
+    if matches.is_empty() {
+        return;
+    }
+    let current = match forward {
+        true => (state.current + 1) % matches.len(),
+        false => (state.current + matches.len() - 1) % matches.len(),
+    };
+    update_find_state(content_id, |state| state.current = current);
+    scroll_to_range(&matches[current].range);
+    refresh_find(content_id);
+}
+
+fn scroll_to_range(range: &Range) {
+    let rect = range.get_bounding_client_rect();
+    let height = window()
+        .inner_height()
+        .ok()
+        .and_then(|h| h.as_f64())
+        .unwrap_or_default();
+    if rect.top() >= 0. && rect.bottom() <= height {
+        return;
+    }
+    let el = range
+        .start_container()
+        .ok()
+        .and_then(|node| match node.dyn_ref::<Element>() {
+            Some(el) => Some(el.clone()),
+            None => node.parent_element(),
+        });
+    if let Some(el) = el {
+        el.scroll_into_view_with_bool(false);
+    }
+}
+
+pub(crate) fn range_text(range: &Range) -> String {
+    range
+        .clone_contents()
+        .ok()
+        .and_then(|contents| contents.text_content())
+        .unwrap_or_default()
+}
+
+/// Replaces the range with the text as a single undoable edit
+fn replace_range(content_id: &str, range: &Range, text: &str) -> Result<(), JsValue> {
+    let content = document()
+        .get_element_by_id(content_id)
+        .ok_or("couldn't find the content div")?
+        .unchecked_into::<HtmlElement>();
+    content.focus()?;
+    restore_selection(range);
+    match text.is_empty() {
+        true => exec("delete", "")?,
+        false => exec("insertText", text)?,
+    };
+    Ok(())
+}
+
+/// Replaces the current match and moves to the next one
+pub fn replace_current(content_id: &str, replacement: &str) -> Result<(), JsValue> {
+    let (Some(state), Some(root)) = (
+        find_state(content_id),
+        document().get_element_by_id(content_id),
+    ) else {
+        return Ok(());
+    };
+    let matches = find_matches(&root, &state);
+    let Some(found) = matches.get(state.current) else {
+        return Ok(());
+    };
+    replace_range(
+        content_id,
+        &found.range,
+        &state.replacement_for(found, replacement),
+    )?;
+    // The replaced match is gone, so the current index already points to the next one
+    refresh_find(content_id);
+    focus_input(&replace_input_id(content_id));
+    Ok(())
+}
+
+/// Returns the indexes of the children that lead from the root to the node
+fn node_path(root: &Node, node: &Node) -> Vec<u32> {
+    let mut path = vec![];
+    let mut node = node.clone();
+    while !node.is_same_node(Some(root)) {
+        let Some(parent) = node.parent_node() else {
+            break;
+        };
+        let children = parent.child_nodes();
+        let index = (0..children.length())
+            .find(|i| children.item(*i).as_ref() == Some(&node))
+            .unwrap_or_default();
+        path.push(index);
+        node = parent;
+    }
+    path.reverse();
+    path
+}
+
+fn node_at(root: &Node, path: &[u32]) -> Option<Node> {
+    path.iter()
+        .try_fold(root.clone(), |node, index| node.child_nodes().item(*index))
+}
+
+/// Returns the range of the copy of the root that matches the range of the root
+fn copy_range(root: &Node, copy: &Node, range: &Range) -> Option<Range> {
+    let start = node_at(copy, &node_path(root, &range.start_container().ok()?))?;
+    let end = node_at(copy, &node_path(root, &range.end_container().ok()?))?;
+    let copied = Range::new().ok()?;
+    copied.set_start(&start, range.start_offset().ok()?).ok()?;
+    copied.set_end(&end, range.end_offset().ok()?).ok()?;
+    Some(copied)
+}
+
+/// Replaces every match as a single edit, so it's undone at once and the content only
+/// changes once. The matches are replaced in a copy of the content, whose HTML is then
+/// inserted over the whole content, so the embeds, checkboxes and marks around the
+/// matches are kept
+pub fn replace_all(content_id: &str, replacement: &str) -> Result<(), JsValue> {
+    let (Some(state), Some(root)) = (
+        find_state(content_id),
+        document().get_element_by_id(content_id),
+    ) else {
+        return Ok(());
+    };
+    let matches = find_matches(&root, &state);
+    if matches.is_empty() {
+        return Ok(());
+    }
+    let copy = root.clone_node_with_deep(true)?;
+    let ranges: Vec<(Range, String)> = matches
+        .iter()
+        .filter_map(|found| {
+            let range = copy_range(&root, &copy, &found.range)?;
+            Some((range, state.replacement_for(found, replacement)))
+        })
+        .collect();
+    // Replaces from the last match so the ranges of the previous ones stay valid
+    for (range, text) in ranges.iter().rev() {
+        range.delete_contents()?;
+        if !text.is_empty() {
+            range.insert_node(&document().create_text_node(text))?;
+        }
+    }
+
+    let whole = Range::new()?;
+    whole.select_node_contents(&root)?;
+    root.unchecked_ref::<HtmlElement>().focus()?;
+    restore_selection(&whole);
+    exec("insertHTML", &copy.unchecked_into::<Element>().inner_html())?;
+    refresh_find(content_id);
+    focus_input(&replace_input_id(content_id));
+    Ok(())
+}
+
+fn focus_input(id: &str) {
+    if let Some(input) = document()
+        .get_element_by_id(id)
+        .and_then(|el| el.dyn_into::<HtmlInputElement>().ok())
+    {
+        let _ = input.focus();
+    }
+}
+
+fn create_row(panel: &HtmlDivElement) -> HtmlElement {
+    let row = document()
+        .create_element("div")
+        .unwrap()
+        .unchecked_into::<HtmlElement>();
+    style_row(&row).unwrap();
+    panel.append_child(&row).unwrap();
+    row
+}
+
+fn create_input(id: &str, placeholder: &str, value: &str) -> HtmlInputElement {
+    let input = document()
+        .create_element("input")
+        .unwrap()
+        .unchecked_into::<HtmlInputElement>();
+    input.set_id(id);
+    input.set_type("text");
+    input.set_placeholder(placeholder);
+    input.set_value(value);
+    style_input(&input).unwrap();
+    input
+}
+
+fn create_button(label: &str, title: &str, on_click: impl Fn() + 'static) -> HtmlElement {
+    let button = document()
+        .create_element("a")
+        .unwrap()
+        .unchecked_into::<HtmlElement>();
+    button.set_inner_text(label);
+    button.set_title(title);
+    style_button(&button).unwrap();
+    let on_click: Closure<dyn Fn(Event)> = Closure::new(move |_: Event| on_click());
+    button
+        .add_event_listener_with_callback("click", on_click.as_ref().unchecked_ref())
+        .unwrap();
+    on_click.forget();
+    button
+}
+
+fn add_find_row(panel: &HtmlDivElement, content_id: &str, state: &FindState) {
+    let row = create_row(panel);
+    let input = create_input(&find_input_id(content_id), "Find", &state.query);
+
+    let input_content_id = content_id.to_string();
+    let on_input: Closure<dyn Fn(Event)> = Closure::new(move |e: Event| {
+        let query = e
+            .target()
+            .unwrap()
+            .unchecked_into::<HtmlInputElement>()
+            .value();
+        update_find_state(&input_content_id, |state| {
+            state.query = query;
+            state.current = 0;
+        });
+        refresh_find(&input_content_id);
+    });
+    input
+        .add_event_listener_with_callback("input", on_input.as_ref().unchecked_ref())
+        .unwrap();
+    on_input.forget();
+    add_panel_keydown(&input, content_id, |content_id, e| {
+        step_match(content_id, !e.shift_key())
+    });
+    row.append_child(&input).unwrap();
+
+    let count = document()
+        .create_element("span")
+        .unwrap()
+        .unchecked_into::<HtmlElement>();
+    count.set_id(&count_id(content_id));
+    let _ = count.style().set_property("white-space", "nowrap");
+    row.append_child(&count).unwrap();
+
+    let previous_content_id = content_id.to_string();
+    let previous = create_button("↑", "Previous match", move || {
+        step_match(&previous_content_id, false)
+    });
+    row.append_child(&previous).unwrap();
+    let next_content_id = content_id.to_string();
+    let next = create_button("↓", "Next match", move || {
+        step_match(&next_content_id, true)
+    });
+    row.append_child(&next).unwrap();
+    let close_content_id = content_id.to_string();
+    let close = create_button("×", "Close", move || close_find_panel(&close_content_id));
+    row.append_child(&close).unwrap();
+}
+
+/// Closes the panel on Escape and calls `on_enter` on Enter
+fn add_panel_keydown(
+    input: &HtmlInputElement,
+    content_id: &str,
+    on_enter: impl Fn(&str, &KeyboardEvent) + 'static,
+) {
+    let content_id = content_id.to_string();
+    let on_keydown: Closure<dyn Fn(KeyboardEvent)> =
+        Closure::new(move |e: KeyboardEvent| match e.key().as_str() {
+            "Enter" => {
+                e.prevent_default();
+                on_enter(&content_id, &e);
+            }
+            "Escape" => {
+                e.prevent_default();
+                close_find_panel(&content_id);
+                if let Some(content) = document()
+                    .get_element_by_id(&content_id)
+                    .and_then(|el| el.dyn_into::<HtmlElement>().ok())
+                {
+                    let _ = content.focus();
+                }
+            }
+            _ => {}
+        });
+    input
+        .add_event_listener_with_callback("keydown", on_keydown.as_ref().unchecked_ref())
+        .unwrap();
+    on_keydown.forget();
+}
+
+/// Sets one of the search options
+type FindOptionSetter = fn(&mut FindState, bool);
+
+fn add_options_row(panel: &HtmlDivElement, content_id: &str, state: &FindState) {
+    let row = create_row(panel);
+    let options: [(&str, bool, FindOptionSetter); 3] = [
+        ("Match case", state.case_sensitive, |s, v| {
+            s.case_sensitive = v
+        }),
+        ("Whole word", state.whole_word, |s, v| s.whole_word = v),
+        ("Regex", state.regex, |s, v| s.regex = v),
+    ];
+    for (label, checked, set) in options {
+        let label_el = document()
+            .create_element("label")
+            .unwrap()
+            .unchecked_into::<HtmlElement>();
+        let _ = label_el.style().set_property("white-space", "nowrap");
+        let checkbox = document()
+            .create_element("input")
+            .unwrap()
+            .unchecked_into::<HtmlInputElement>();
+        checkbox.set_type("checkbox");
+        checkbox.set_checked(checked);
+
+        let content_id = content_id.to_string();
+        let on_change: Closure<dyn Fn(Event)> = Closure::new(move |e: Event| {
+            let checked = e
+                .target()
+                .unwrap()
+                .unchecked_into::<HtmlInputElement>()
+                .checked();
+            update_find_state(&content_id, |state| {
+                set(state, checked);
+                state.current = 0;
+            });
+            refresh_find(&content_id);
+        });
+        checkbox
+            .add_event_listener_with_callback("change", on_change.as_ref().unchecked_ref())
+            .unwrap();
+        on_change.forget();
+
+        label_el.append_child(&checkbox).unwrap();
+        label_el
+            .append_child(&document().create_text_node(label))
+            .unwrap();
+        row.append_child(&label_el).unwrap();
+    }
+}
+
+fn replace_value(content_id: &str) -> String {
+    document()
+        .get_element_by_id(&replace_input_id(content_id))
+        .and_then(|el| el.dyn_into::<HtmlInputElement>().ok())
+        .map(|input| input.value())
+        .unwrap_or_default()
+}
+
+fn add_replace_row(panel: &HtmlDivElement, content_id: &str) {
+    let row = create_row(panel);
+    let input = create_input(&replace_input_id(content_id), "Replace", "");
+    add_panel_keydown(&input, content_id, |content_id, _| {
+        let _ = replace_current(content_id, &replace_value(content_id));
+    });
+    row.append_child(&input).unwrap();
+
+    let replace_content_id = content_id.to_string();
+    let replace = create_button("Replace", "Replace the current match", move || {
+        let _ = replace_current(&replace_content_id, &replace_value(&replace_content_id));
+    });
+    row.append_child(&replace).unwrap();
+    let all_content_id = content_id.to_string();
+    let all = create_button("All", "Replace all matches", move || {
+        let _ = replace_all(&all_content_id, &replace_value(&all_content_id));
+    });
+    row.append_child(&all).unwrap();
+}
+
+fn style_panel(el: &HtmlDivElement) -> Result<(), JsValue> {
+    el.style().set_property("border", "1px solid black")?;
+    el.style().set_property("background-color", "white")?;
+    el.style().set_property("display", "flex")?;
+    el.style().set_property("flex-direction", "column")?;
+    el.style().set_property("z-index", "1")
+}
+
+fn style_row(el: &HtmlElement) -> Result<(), JsValue> {
+    el.style()
+        .set_property("height", &format!("{PANEL_ROW_HEIGHT}px"))?;
+    el.style().set_property("display", "flex")?;
+    el.style().set_property("align-items", "center")?;
+    el.style().set_property("gap", "4px")?;
+    el.style().set_property("padding", "0 4px")
+}
+
+fn style_input(el: &HtmlInputElement) -> Result<(), JsValue> {
+    el.style().set_property("flex", "1")?;
+    el.style().set_property("min-width", "0")
+}
+
+fn style_button(el: &HtmlElement) -> Result<(), JsValue> {
+    el.style().set_property("padding", "0 4px")?;
+    el.style().set_property("cursor", "pointer")?;
+    el.style().set_property("user-select", "none")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups(groups: &[Option<&str>]) -> Vec<Option<String>> {
+        groups.iter().map(|group| group.map(String::from)).collect()
+    }
+
+    #[test]
+    fn expands_the_groups_of_the_match() {
+        let found = groups(&[Some("foo-bar"), Some("foo"), None, Some("bar")]);
+        assert_eq!(expand_replacement("$3 $1", &found), "bar foo");
+        assert_eq!(expand_replacement("[$&]", &found), "[foo-bar]");
+        assert_eq!(expand_replacement("$2.", &found), ".");
+        assert_eq!(expand_replacement("$$1 $4 $0 $", &found), "$1 $4 $0 $");
+        assert_eq!(expand_replacement("$10", &found), "foo0");
+    }
+
+    #[test]
+    fn prefers_two_digit_groups() {
+        let mut found = vec![Some("match".to_string())];
+        found.extend((1..=12).map(|n| Some(format!("g{n}"))));
+        assert_eq!(expand_replacement("$12 $1", &found), "g12 g1");
+        assert_eq!(expand_replacement("$13", &found), "g13");
+    }
+}
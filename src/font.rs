@@ -0,0 +1,177 @@
+use crate::util::{
+    dispatch_input_event, dropdown_menu, isolate_from_ancestor, mark_selection, remove_class,
+    selection_element, unwrap_element, DropdownItem,
+};
+use leptos_dom::document;
+use std::rc::Rc;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Element, HtmlElement};
+
+/// Width in pixels of the font dropdown menus
+const FONT_MENU_WIDTH: f64 = 140.;
+
+/// A font family or size that can be applied to the selected text.
+/// The text is wrapped in a `<span>` with the given class, so the font itself
+/// must be defined by the stylesheet of the page
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontOption {
+    pub label: String,
+    pub class: String,
+}
+
+impl FontOption {
+    pub fn new(label: &str, class: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            class: class.to_string(),
+        }
+    }
+}
+
+pub fn default_font_families() -> Vec<FontOption> {
+    vec![
+        FontOption::new("Sans Serif", "papelito-font-sans-serif"),
+        FontOption::new("Serif", "papelito-font-serif"),
+        FontOption::new("Monospace", "papelito-font-monospace"),
+    ]
+}
+
+pub fn default_font_sizes() -> Vec<FontOption> {
+    vec![
+        FontOption::new("Small", "papelito-size-small"),
+        FontOption::new("Large", "papelito-size-large"),
+        FontOption::new("Huge", "papelito-size-huge"),
+    ]
+}
+
+/// Toggles a dropdown below the element with the given id listing the font options,
+/// plus a "Default" item that removes them from the selection
+pub fn font_menu(element_id: &str, content_id: &str, options: &[FontOption]) {
+    let mut items = vec![DropdownItem {
+        label: "Default".to_string(),
+        class: None,
+    }];
+    items.extend(options.iter().map(|option| DropdownItem {
+        label: option.label.clone(),
+        class: Some(option.class.clone()),
+    }));
+
+    let element_id_clone = element_id.to_string();
+    let content_id = content_id.to_string();
+    let options = options.to_vec();
+    let on_select = Rc::new(move |index: usize| {
+        let class = match index {
+            0 => None,
+            _ => Some(options[index - 1].class.as_str()),
+        };
+        let _ = apply_font_class(&content_id, class, &options);
+        let _ = show_current_font(&element_id_clone, &content_id, &options);
+    });
+    dropdown_menu(element_id, FONT_MENU_WIDTH, items, on_select);
+}
+
+/// Wraps the selected text of the content div in a `<span>` with the given class, removing
+/// every other class of `group` from it. `None` only removes the classes of `group`
+pub fn apply_font_class(
+    content_id: &str,
+    class: Option<&str>,
+    group: &[FontOption],
+) -> Result<(), JsValue> {
+    let root = document()
+        .get_element_by_id(content_id)
+        .ok_or("couldn't find the content div")?;
+
+    for marker in mark_selection(&root) {
+        while let Some(ancestor) = find_font_ancestor(&marker, &root, group) {
+            isolate_from_ancestor(&marker, &ancestor)?;
+            for option in group {
+                remove_class(&ancestor, &option.class);
+            }
+        }
+
+        let selector = font_selector(group);
+        let descendants = marker.query_selector_all(&selector)?;
+        for i in 0..descendants.length() {
+            let el = descendants.item(i).unwrap().dyn_into::<Element>().unwrap();
+            for option in group {
+                remove_class(&el, &option.class);
+            }
+        }
+
+        match class {
+            Some(class) => {
+                let span = document().create_element("span")?;
+                span.set_class_name(class);
+                while let Some(child) = marker.first_child() {
+                    span.append_child(&child)?;
+                }
+                marker.replace_with_with_node_1(&span)?;
+            }
+            None => unwrap_element(&marker),
+        }
+    }
+    dispatch_input_event(content_id);
+    Ok(())
+}
+
+/// Returns the font option of `group` applied at the caret
+pub fn current_font_option(content_id: &str, group: &[FontOption]) -> Option<FontOption> {
+    let root = document().get_element_by_id(content_id)?;
+    let el = selection_element(content_id)?;
+
+    let ancestor = find_font_ancestor(&el, &root, group).or_else(|| {
+        el.matches(&font_selector(group))
+            .unwrap_or(false)
+            .then(|| el.clone())
+    })?;
+    group
+        .iter()
+        .find(|option| ancestor.class_list().contains(&option.class))
+        .cloned()
+}
+
+/// Shows the label of the font option applied at the caret on the element with the given id
+pub fn show_current_font(
+    element_id: &str,
+    content_id: &str,
+    group: &[FontOption],
+) -> Result<(), JsValue> {
+    let Some(el) = document().get_element_by_id(element_id) else {
+        return Ok(());
+    };
+    let el = el.dyn_into::<HtmlElement>().unwrap();
+    let label = current_font_option(content_id, group)
+        .map(|option| option.label)
+        .unwrap_or_else(|| "Default".to_string());
+    el.set_inner_text(&label);
+    Ok(())
+}
+
+/// Selects the spans with a class of `group`. The classes are matched as attribute values,
+/// so any class name gives a valid selector
+fn font_selector(group: &[FontOption]) -> String {
+    group
+        .iter()
+        .map(|option| {
+            let class = option
+                .class
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\a ");
+            format!("span[class~=\"{class}\"]")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Returns the closest ancestor of `el` inside `root` that has a class of `group`
+fn find_font_ancestor(el: &Element, root: &Element, group: &[FontOption]) -> Option<Element> {
+    if group.is_empty() {
+        return None;
+    }
+    let ancestor = el.parent_element()?.closest(&font_selector(group)).ok()??;
+    match root.contains(Some(&ancestor)) && !ancestor.is_same_node(Some(root)) {
+        true => Some(ancestor),
+        false => None,
+    }
+}
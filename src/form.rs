@@ -0,0 +1,104 @@
+use leptos_dom::{document, window};
+use std::fmt;
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{Element, Event, HtmlTemplateElement};
+
+/// Elements that make the content non empty even without any text
+const NON_TEXT_CONTENT: &str = "img, iframe, hr, input";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationError {
+    /// The editor is required but has no content
+    Empty,
+    /// The text of the content is longer than the maximum length
+    TooLong { length: usize, max_length: usize },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::Empty => f.write_str("This field is required"),
+            ValidationError::TooLong { length, max_length } => write!(
+                f,
+                "The text is too long ({length} characters, the maximum is {max_length})"
+            ),
+        }
+    }
+}
+
+/// Validates the HTML of the content, its length is the number of characters of its text
+pub fn validate_html(
+    html: &str,
+    required: bool,
+    max_length: Option<usize>,
+) -> Option<ValidationError> {
+    let template = document()
+        .create_element("template")
+        .unwrap()
+        .unchecked_into::<HtmlTemplateElement>();
+    template.set_inner_html(html);
+    let content = template.content();
+    let text = content.text_content().unwrap_or_default();
+
+    let is_empty = text.trim().is_empty()
+        && content
+            .query_selector(NON_TEXT_CONTENT)
+            .ok()
+            .flatten()
+            .is_none();
+    if required && is_empty {
+        return Some(ValidationError::Empty);
+    }
+
+    let length = text.chars().count();
+    match max_length {
+        Some(max_length) if length > max_length => {
+            Some(ValidationError::TooLong { length, max_length })
+        }
+        _ => None,
+    }
+}
+
+/// Calls `on_submit` before the form that contains the element with the given id is
+/// submitted, and `on_reset` when it is reset. `on_submit` returns `false` to cancel the
+/// submission.
+/// The submit listener is added to the window in the capture phase, so it runs before the
+/// listeners of the form, such as the one of leptos' `ActionForm`
+pub fn connect_form(
+    element_id: &str,
+    on_submit: impl Fn() -> bool + 'static,
+    on_reset: impl Fn() + 'static,
+) -> Option<Element> {
+    let form = document()
+        .get_element_by_id(element_id)?
+        .closest("form")
+        .ok()??;
+
+    let submit_form = form.clone();
+    let handle_submit: Closure<dyn Fn(Event)> = Closure::new(move |e: Event| {
+        let is_this_form = e
+            .target()
+            .and_then(|t| t.dyn_into::<Element>().ok())
+            .map(|target| target == submit_form)
+            .unwrap_or(false);
+        if is_this_form && !on_submit() {
+            e.prevent_default();
+            e.stop_propagation();
+        }
+    });
+    window()
+        .add_event_listener_with_callback_and_bool(
+            "submit",
+            handle_submit.as_ref().unchecked_ref(),
+            true,
+        )
+        .unwrap();
+    handle_submit.forget();
+
+    let handle_reset: Closure<dyn Fn(Event)> = Closure::new(move |_: Event| on_reset());
+    form.add_event_listener_with_callback("reset", handle_reset.as_ref().unchecked_ref())
+        .unwrap();
+    handle_reset.forget();
+
+    Some(form)
+}
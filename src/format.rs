@@ -0,0 +1,109 @@
+use crate::list::{current_list_kind, toggle_checklist, ListKind};
+use crate::util::{
+    dispatch_input_event, exec, exec_format_block, isolate_from_ancestor, mark_selection,
+    save_selection, unwrap_element,
+};
+use leptos_dom::document;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Element, HtmlDocument};
+
+/// Elements removed by `clear_formatting`, links are kept since they aren't formatting,
+/// and so are the changes suggested by an author and the comment markers, which are
+/// resolved instead
+const INLINE_MARKS: &str = "b, strong, i, em, u, s, strike, del:not([data-author]), \
+                            ins:not([data-author]), sub, sup, code, \
+                            span:not(.papelito-comment), font, mark, small, big, kbd, q, cite, \
+                            dfn, samp, var, tt";
+
+/// Elements kept by `clear_formatting` that the `removeFormat` command of the browsers
+/// removes
+const KEPT_MARKS: &str = "del[data-author], ins[data-author], span.papelito-comment";
+
+fn query_command_state(command: &str) -> Result<bool, JsValue> {
+    document()
+        .dyn_ref::<HtmlDocument>()
+        .expect("couldn't get the HtmlDocument")
+        .query_command_state(command)
+}
+
+/// Toggles subscript, removing superscript first since they can't be combined
+pub fn toggle_subscript() -> Result<bool, JsValue> {
+    if query_command_state("superscript")? {
+        exec("superscript", "")?;
+    }
+    exec("subscript", "")
+}
+
+/// Toggles superscript, removing subscript first since they can't be combined
+pub fn toggle_superscript() -> Result<bool, JsValue> {
+    if query_command_state("subscript")? {
+        exec("subscript", "")?;
+    }
+    exec("superscript", "")
+}
+
+/// Returns `true` if the selection intersects an element of the root matching the selector
+fn selection_has(root: &Element, selector: &str) -> Result<bool, JsValue> {
+    let Some(range) = save_selection() else {
+        return Ok(false);
+    };
+    let elements = root.query_selector_all(selector)?;
+    Ok((0..elements.length()).any(|i| {
+        elements
+            .item(i)
+            .map(|el| range.intersects_node(&el).unwrap_or(false))
+            .unwrap_or(false)
+    }))
+}
+
+/// Returns the closest ancestor of `el` inside the root that is removed by
+/// `clear_formatting`, the elements that are kept in between are skipped
+fn inline_mark_ancestor(el: &Element, root: &Element) -> Option<Element> {
+    let mut ancestor = el.parent_element()?;
+    while !ancestor.is_same_node(Some(root)) {
+        if ancestor.matches(INLINE_MARKS).unwrap_or(false) {
+            return Some(ancestor);
+        }
+        ancestor = ancestor.parent_element()?;
+    }
+    None
+}
+
+/// Removes every inline mark from the selection and turns its blocks into `separator` blocks
+pub fn clear_formatting(content_id: &str, separator: &str) -> Result<(), JsValue> {
+    let root = document()
+        .get_element_by_id(content_id)
+        .ok_or("couldn't find the content div")?;
+
+    match current_list_kind(content_id) {
+        Some(ListKind::Ordered) => {
+            exec("insertOrderedList", "")?;
+        }
+        Some(ListKind::Unordered) => {
+            exec("insertUnorderedList", "")?;
+        }
+        Some(ListKind::Checklist) => toggle_checklist(content_id)?,
+        None => {}
+    }
+    exec_format_block(separator.to_string())?;
+    if !selection_has(&root, KEPT_MARKS)? {
+        exec("removeFormat", "")?;
+    }
+
+    for marker in mark_selection(&root) {
+        while let Some(ancestor) = inline_mark_ancestor(&marker, &root) {
+            isolate_from_ancestor(&marker, &ancestor)?;
+            unwrap_element(&ancestor);
+        }
+
+        let marks = marker.query_selector_all(INLINE_MARKS)?;
+        for i in 0..marks.length() {
+            unwrap_element(&marks.item(i).unwrap().unchecked_into::<Element>());
+        }
+        unwrap_element(&marker);
+    }
+    root.normalize();
+
+    dispatch_input_event(content_id);
+    Ok(())
+}
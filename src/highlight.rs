@@ -0,0 +1,344 @@
+use crate::util::{selection_text_offsets, set_selection_text_offsets};
+use std::fmt;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use web_sys::Element;
+
+/// Prefix of the class that holds the language of a code block, e.g. `language-rust`
+pub const LANGUAGE_CLASS_PREFIX: &str = "language-";
+
+/// A piece of highlighted code, tokens with a class are rendered inside a `<span>` with it
+#[derive(Clone, Debug, PartialEq)]
+pub struct HighlightToken {
+    pub text: String,
+    pub class: Option<String>,
+}
+
+/// Splits the code of a code block in tokens.
+/// The concatenated text of the tokens must be equal to the code
+pub trait Highlighter {
+    fn highlight(&self, language: &str, code: &str) -> Vec<HighlightToken>;
+}
+
+/// A highlighter that can be stored in the editor options
+#[derive(Clone)]
+pub struct SharedHighlighter(pub Rc<dyn Highlighter>);
+
+impl SharedHighlighter {
+    pub fn new(highlighter: impl Highlighter + 'static) -> Self {
+        Self(Rc::new(highlighter))
+    }
+}
+
+impl Default for SharedHighlighter {
+    fn default() -> Self {
+        Self::new(SimpleHighlighter)
+    }
+}
+
+impl fmt::Debug for SharedHighlighter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedHighlighter")
+    }
+}
+
+impl PartialEq for SharedHighlighter {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// A language that can be picked for a code block
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeLanguage {
+    pub label: String,
+    /// Name used in the `language-xxx` class, empty for plain text
+    pub name: String,
+}
+
+impl CodeLanguage {
+    pub fn new(label: &str, name: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            name: name.to_string(),
+        }
+    }
+}
+
+pub fn default_code_languages() -> Vec<CodeLanguage> {
+    vec![
+        CodeLanguage::new("Plain text", ""),
+        CodeLanguage::new("Rust", "rust"),
+        CodeLanguage::new("JavaScript", "javascript"),
+        CodeLanguage::new("Python", "python"),
+    ]
+}
+
+struct LanguageSyntax {
+    keywords: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+}
+
+const RUST_SYNTAX: LanguageSyntax = LanguageSyntax {
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+        "true", "type", "unsafe", "use", "where", "while",
+    ],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"'],
+};
+
+const JAVASCRIPT_SYNTAX: LanguageSyntax = LanguageSyntax {
+    keywords: &[
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "delete",
+        "do",
+        "else",
+        "export",
+        "extends",
+        "false",
+        "finally",
+        "for",
+        "function",
+        "if",
+        "import",
+        "in",
+        "instanceof",
+        "let",
+        "new",
+        "null",
+        "return",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "typeof",
+        "undefined",
+        "var",
+        "void",
+        "while",
+        "yield",
+    ],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\'', '`'],
+};
+
+const PYTHON_SYNTAX: LanguageSyntax = LanguageSyntax {
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+        "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+        "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return", "True",
+        "try", "while", "with", "yield",
+    ],
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: &['"', '\''],
+};
+
+/// A small tokenizer that highlights keywords, strings, comments and numbers
+/// of Rust, JavaScript and Python. Other languages are left as plain text
+pub struct SimpleHighlighter;
+
+impl Highlighter for SimpleHighlighter {
+    fn highlight(&self, language: &str, code: &str) -> Vec<HighlightToken> {
+        let syntax = match language {
+            "rust" | "rs" => &RUST_SYNTAX,
+            "javascript" | "js" | "typescript" | "ts" => &JAVASCRIPT_SYNTAX,
+            "python" | "py" => &PYTHON_SYNTAX,
+            _ => return vec![plain_token(code)],
+        };
+        tokenize(syntax, code)
+    }
+}
+
+fn plain_token(text: &str) -> HighlightToken {
+    HighlightToken {
+        text: text.to_string(),
+        class: None,
+    }
+}
+
+fn tokenize(syntax: &LanguageSyntax, code: &str) -> Vec<HighlightToken> {
+    let mut tokens: Vec<HighlightToken> = vec![];
+    let mut push = |text: &str, class: Option<&str>| {
+        if text.is_empty() {
+            return;
+        }
+        match tokens.last_mut() {
+            Some(last) if class.is_none() && last.class.is_none() => last.text.push_str(text),
+            _ => tokens.push(HighlightToken {
+                text: text.to_string(),
+                class: class.map(|c| format!("papelito-token-{c}")),
+            }),
+        }
+    };
+
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        let len = if let Some(prefix) = syntax.line_comments.iter().find(|p| rest.starts_with(*p)) {
+            let end = rest[prefix.len()..]
+                .find('\n')
+                .map(|i| i + prefix.len())
+                .unwrap_or(rest.len());
+            push(&rest[..end], Some("comment"));
+            end
+        } else if let Some((open, close)) = syntax
+            .block_comment
+            .filter(|(open, _)| rest.starts_with(open))
+        {
+            let end = rest[open.len()..]
+                .find(close)
+                .map(|i| i + open.len() + close.len())
+                .unwrap_or(rest.len());
+            push(&rest[..end], Some("comment"));
+            end
+        } else if syntax.quotes.contains(&c) {
+            let mut end = rest.len();
+            let mut escaped = false;
+            for (i, next) in rest.char_indices().skip(1) {
+                match next {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    _ if next == c => {
+                        end = i + next.len_utf8();
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            push(&rest[..end], Some("string"));
+            end
+        } else if c.is_ascii_digit() {
+            let end = rest
+                .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_' || ch == '.'))
+                .unwrap_or(rest.len());
+            push(&rest[..end], Some("number"));
+            end
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            match syntax.keywords.contains(&word) {
+                true => push(word, Some("keyword")),
+                false => push(word, None),
+            }
+            end
+        } else {
+            push(&rest[..c.len_utf8()], None);
+            c.len_utf8()
+        };
+        rest = &rest[len..];
+    }
+    tokens
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders the tokens as HTML
+pub fn render_tokens(tokens: &[HighlightToken]) -> String {
+    tokens
+        .iter()
+        .map(|token| match &token.class {
+            Some(class) => format!(
+                "<span class=\"{}\">{}</span>",
+                escape_html(class),
+                escape_html(&token.text)
+            ),
+            None => escape_html(&token.text),
+        })
+        .collect()
+}
+
+/// Returns the language of a `<code>` element from its `language-xxx` class
+pub fn code_language(code: &Element) -> String {
+    let class_list = code.class_list();
+    (0..class_list.length())
+        .filter_map(|i| class_list.item(i))
+        .find_map(|class| {
+            class
+                .strip_prefix(LANGUAGE_CLASS_PREFIX)
+                .map(|l| l.to_string())
+        })
+        .unwrap_or_default()
+}
+
+/// Replaces the `language-xxx` class of a `<code>` element, an empty language removes it
+pub fn set_code_language(code: &Element, language: &str) {
+    let current = code_language(code);
+    if !current.is_empty() {
+        let _ = code
+            .class_list()
+            .remove_1(&format!("{LANGUAGE_CLASS_PREFIX}{current}"));
+    }
+    if !language.is_empty() {
+        let _ = code
+            .class_list()
+            .add_1(&format!("{LANGUAGE_CLASS_PREFIX}{language}"));
+    }
+    if code.class_list().length() == 0 {
+        let _ = code.remove_attribute("class");
+    }
+}
+
+/// Highlights the content of a `<code>` element, keeping the caret in place if it is inside it
+pub fn highlight_code_element(code: &Element, highlighter: &dyn Highlighter) {
+    let text = code.text_content().unwrap_or_default();
+    let caret = selection_text_offsets(code).map(|(anchor, _)| anchor);
+    let html = render_tokens(&highlighter.highlight(&code_language(code), &text));
+    if html == code.inner_html() {
+        return;
+    }
+    code.set_inner_html(&html);
+    if let Some(offset) = caret {
+        let _ = set_selection_text_offsets(code, offset, offset);
+    }
+}
+
+/// Highlights every code block inside the element
+pub fn highlight_all(root: &Element, highlighter: &dyn Highlighter) {
+    let blocks = root.query_selector_all("pre > code").unwrap();
+    for i in 0..blocks.length() {
+        let code = blocks.item(i).unwrap().unchecked_into::<Element>();
+        highlight_code_element(&code, highlighter);
+    }
+}
+
+/// Returns the HTML of the element without the highlighting markup of its code blocks
+pub fn html_without_highlighting(root: &Element) -> String {
+    let blocks = root.query_selector_all("pre > code span").unwrap();
+    if blocks.length() == 0 {
+        return root.inner_html();
+    }
+    let copy = root
+        .clone_node_with_deep(true)
+        .unwrap()
+        .unchecked_into::<Element>();
+    let blocks = copy.query_selector_all("pre > code").unwrap();
+    for i in 0..blocks.length() {
+        let code = blocks.item(i).unwrap();
+        let text = code.text_content();
+        code.set_text_content(text.as_deref());
+    }
+    copy.inner_html()
+}
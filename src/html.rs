@@ -0,0 +1,485 @@
+use std::fmt::Write;
+
+/// Elements that never have children or an end tag
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements that start a new line of the content, every other element is inline
+pub const BLOCK_ELEMENTS: [&str; 29] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tbody",
+    "td",
+    "th",
+    "ul",
+];
+
+pub fn is_void_element(tag: &str) -> bool {
+    VOID_ELEMENTS.contains(&tag)
+}
+
+pub fn is_block_element(tag: &str) -> bool {
+    BLOCK_ELEMENTS.contains(&tag) || matches!(tag, "thead" | "tfoot" | "tr")
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HtmlElement {
+    /// Always lowercase
+    pub tag: String,
+    /// In the order of the HTML, names are lowercase
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<HtmlNode>,
+}
+
+impl HtmlElement {
+    pub fn new(tag: &str) -> Self {
+        Self {
+            tag: tag.to_lowercase(),
+            attributes: vec![],
+            children: vec![],
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        match self.attributes.iter_mut().find(|(n, _)| n == name) {
+            Some((_, current)) => *current = value.to_string(),
+            None => self.attributes.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn remove_attribute(&mut self, name: &str) {
+        self.attributes.retain(|(n, _)| n != name);
+    }
+
+    pub fn is_void(&self) -> bool {
+        is_void_element(&self.tag)
+    }
+
+    pub fn is_block(&self) -> bool {
+        is_block_element(&self.tag)
+    }
+
+    /// The HTML of the children
+    pub fn inner_html(&self) -> String {
+        to_html(&self.children)
+    }
+
+    /// The start tag with the attributes
+    pub fn start_tag(&self) -> String {
+        let mut html = format!("<{}", self.tag);
+        for (name, value) in &self.attributes {
+            let _ = write!(html, " {name}=\"{}\"", escape_attribute(value));
+        }
+        html.push('>');
+        html
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HtmlNode {
+    Element(HtmlElement),
+    /// Decoded text
+    Text(String),
+    Comment(String),
+}
+
+impl HtmlNode {
+    pub fn as_element(&self) -> Option<&HtmlElement> {
+        match self {
+            HtmlNode::Element(el) => Some(el),
+            _ => None,
+        }
+    }
+
+    /// The text of the node and of its descendants
+    pub fn text_content(&self) -> String {
+        match self {
+            HtmlNode::Element(el) => el.children.iter().map(HtmlNode::text_content).collect(),
+            HtmlNode::Text(text) => text.clone(),
+            HtmlNode::Comment(_) => String::new(),
+        }
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        write_node(&mut html, self);
+        html
+    }
+}
+
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\u{a0}' => escaped.push_str("&nbsp;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn escape_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\u{a0}' => escaped.push_str("&nbsp;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn write_node(html: &mut String, node: &HtmlNode) {
+    match node {
+        HtmlNode::Element(el) => {
+            html.push_str(&el.start_tag());
+            if el.is_void() {
+                return;
+            }
+            for child in &el.children {
+                write_node(html, child);
+            }
+            let _ = write!(html, "</{}>", el.tag);
+        }
+        HtmlNode::Text(text) => html.push_str(&escape_text(text)),
+        HtmlNode::Comment(comment) => {
+            let _ = write!(html, "<!--{comment}-->");
+        }
+    }
+}
+
+/// Serializes the nodes the same way as the `innerHTML` of the browser
+pub fn to_html(nodes: &[HtmlNode]) -> String {
+    let mut html = String::new();
+    for node in nodes {
+        write_node(&mut html, node);
+    }
+    html
+}
+
+/// Decodes the character references of the text, unknown ones are kept as they are
+pub fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(';').filter(|end| *end <= 10);
+        let c = end.and_then(|end| {
+            let name = &rest[1..end];
+            match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => {
+                    let code = match name.strip_prefix("#x").or(name.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => name.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+                    };
+                    code.and_then(char::from_u32)
+                }
+            }
+        });
+        match (c, end) {
+            (Some(c), Some(end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Parses HTML such as the one of the content without the DOM, so it can be processed
+/// natively, e.g. on a server. Unclosed elements are closed at the end of their parent and
+/// stray end tags are ignored
+pub fn parse_html(html: &str) -> Vec<HtmlNode> {
+    // The elements that are open, with their children so far
+    let mut stack: Vec<HtmlElement> = vec![];
+    let mut roots: Vec<HtmlNode> = vec![];
+
+    fn push(stack: &mut [HtmlElement], roots: &mut Vec<HtmlNode>, node: HtmlNode) {
+        let children = match stack.last_mut() {
+            Some(parent) => &mut parent.children,
+            None => roots,
+        };
+        // Adjacent text is joined, like in the DOM
+        if let (HtmlNode::Text(text), Some(HtmlNode::Text(previous))) = (&node, children.last_mut())
+        {
+            previous.push_str(text);
+            return;
+        }
+        children.push(node);
+    }
+
+    fn close(stack: &mut Vec<HtmlElement>, roots: &mut Vec<HtmlNode>) {
+        if let Some(el) = stack.pop() {
+            push(stack, roots, HtmlNode::Element(el));
+        }
+    }
+
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            push(
+                &mut stack,
+                &mut roots,
+                HtmlNode::Text(decode_entities(rest)),
+            );
+            break;
+        };
+        if start > 0 {
+            push(
+                &mut stack,
+                &mut roots,
+                HtmlNode::Text(decode_entities(&rest[..start])),
+            );
+            rest = &rest[start..];
+        }
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").unwrap_or(comment.len());
+            push(
+                &mut stack,
+                &mut roots,
+                HtmlNode::Comment(comment[..end].to_string()),
+            );
+            rest = comment.get(end + 3..).unwrap_or("");
+            continue;
+        }
+
+        if let Some(end_tag) = rest.strip_prefix("</") {
+            let end = end_tag.find('>').unwrap_or(end_tag.len());
+            let tag = end_tag[..end].trim().to_lowercase();
+            rest = end_tag.get(end + 1..).unwrap_or("");
+            if stack.iter().any(|el| el.tag == tag) {
+                while stack.last().map(|el| el.tag != tag).unwrap_or(false) {
+                    close(&mut stack, &mut roots);
+                }
+                close(&mut stack, &mut roots);
+            }
+            continue;
+        }
+
+        let is_tag = rest[1..]
+            .chars()
+            .next()
+            .map(|c| c.is_ascii_alphabetic())
+            .unwrap_or(false);
+        if !is_tag {
+            push(&mut stack, &mut roots, HtmlNode::Text("<".to_string()));
+            rest = &rest[1..];
+            continue;
+        }
+
+        let (el, self_closing, after) = parse_start_tag(&rest[1..]);
+        rest = after;
+        if el.is_void() || self_closing {
+            push(&mut stack, &mut roots, HtmlNode::Element(el));
+        } else {
+            stack.push(el);
+        }
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots);
+    }
+    roots
+}
+
+/// Parses a start tag without its `<`, returns the element, whether it ends with `/>`, and
+/// the HTML after the tag
+fn parse_start_tag(html: &str) -> (HtmlElement, bool, &str) {
+    let name_end = html
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(html.len());
+    let mut el = HtmlElement::new(&html[..name_end]);
+    let mut rest = &html[name_end..];
+    let mut self_closing = false;
+
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            rest = after;
+            break;
+        }
+        if let Some(after) = rest.strip_prefix('/') {
+            self_closing = after.starts_with('>');
+            rest = after;
+            continue;
+        }
+
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(rest.len())
+            .max(1);
+        let name = rest[..name_end].to_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (raw, after) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let inner = &after[1..];
+                    let end = inner.find(quote).unwrap_or(inner.len());
+                    (&inner[..end], inner.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            value = decode_entities(raw);
+            rest = after;
+        }
+        if el.attribute(&name).is_none() {
+            el.attributes.push((name, value));
+        }
+    }
+    (el, self_closing, rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(tag: &str, children: Vec<HtmlNode>) -> HtmlNode {
+        HtmlNode::Element(HtmlElement {
+            children,
+            ..HtmlElement::new(tag)
+        })
+    }
+
+    fn text(text: &str) -> HtmlNode {
+        HtmlNode::Text(text.to_string())
+    }
+
+    #[test]
+    fn decodes_character_references() {
+        assert_eq!(decode_entities("a &amp; b &lt;&gt;"), "a & b <>");
+        assert_eq!(decode_entities("&#233;&#xE9;&#XE9;&nbsp;"), "ééé\u{a0}");
+        assert_eq!(
+            decode_entities("&unknown; & &#xZZ; &amp"),
+            "&unknown; & &#xZZ; &amp"
+        );
+        assert_eq!(
+            parse_html("<p title=\"&quot;x&quot;\">&lt;b&gt;</p>"),
+            vec![HtmlNode::Element(HtmlElement {
+                attributes: vec![("title".to_string(), "\"x\"".to_string())],
+                children: vec![text("<b>")],
+                ..HtmlElement::new("p")
+            })]
+        );
+    }
+
+    #[test]
+    fn closes_unclosed_elements_at_the_end_of_their_parent() {
+        assert_eq!(
+            parse_html("<div><p><b>bold</div>after"),
+            vec![
+                element(
+                    "div",
+                    vec![element("p", vec![element("b", vec![text("bold")])])]
+                ),
+                text("after"),
+            ]
+        );
+        assert_eq!(
+            parse_html("<p>text"),
+            vec![element("p", vec![text("text")])]
+        );
+    }
+
+    #[test]
+    fn ignores_stray_end_tags() {
+        assert_eq!(
+            parse_html("<p>one</b> two</p></div>"),
+            vec![element("p", vec![text("one two")])]
+        );
+    }
+
+    #[test]
+    fn keeps_comments() {
+        assert_eq!(
+            parse_html("<p>a<!-- note -->b</p><!-- unclosed"),
+            vec![
+                element(
+                    "p",
+                    vec![
+                        text("a"),
+                        HtmlNode::Comment(" note ".to_string()),
+                        text("b")
+                    ]
+                ),
+                HtmlNode::Comment(" unclosed".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_void_elements_and_attributes() {
+        let nodes = parse_html("<P CLASS=a data-x='1 2' hidden>x<BR>y<img src=\"i.png\"/></p>");
+        assert_eq!(
+            to_html(&nodes),
+            "<p class=\"a\" data-x=\"1 2\" hidden=\"\">x<br>y<img src=\"i.png\"></p>"
+        );
+        assert_eq!(nodes[0].text_content(), "xy");
+    }
+
+    #[test]
+    fn serializes_back_to_the_same_html() {
+        let html = "<p>a &amp; b&nbsp;<a href=\"?a=1&amp;b=&quot;2&quot;\">c</a></p><hr>";
+        assert_eq!(to_html(&parse_html(html)), html);
+        assert_eq!(to_html(&parse_html("1 < 2")), "1 &lt; 2");
+    }
+}
@@ -0,0 +1,475 @@
+use crate::util::{
+    add_context_menu_at, context_menu_id, dispatch_input_event, remove_context_menu,
+};
+use leptos_dom::{document, window};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{
+    AddEventListenerOptions, CssStyleDeclaration, Element, Event, HtmlDivElement, HtmlElement,
+    HtmlInputElement, KeyboardEvent, MouseEvent,
+};
+
+/// Class of the `<figure>` that wraps images with a caption
+pub const FIGURE_CLASS: &str = "papelito-figure";
+/// Attribute that holds the alignment of an image, or of its figure
+pub const ALIGN_ATTRIBUTE: &str = "data-papelito-align";
+
+const POPOVER_WIDTH: f64 = 260.;
+const POPOVER_ROW_HEIGHT: f64 = 28.;
+const HANDLE_SIZE: f64 = 12.;
+const MIN_IMAGE_WIDTH: f64 = 16.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageAlignment {
+    Left,
+    Center,
+    Right,
+    FloatLeft,
+    FloatRight,
+}
+
+impl ImageAlignment {
+    pub const ALL: [ImageAlignment; 5] = [
+        ImageAlignment::Left,
+        ImageAlignment::Center,
+        ImageAlignment::Right,
+        ImageAlignment::FloatLeft,
+        ImageAlignment::FloatRight,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImageAlignment::Left => "left",
+            ImageAlignment::Center => "center",
+            ImageAlignment::Right => "right",
+            ImageAlignment::FloatLeft => "float-left",
+            ImageAlignment::FloatRight => "float-right",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            ImageAlignment::Left => "⇤",
+            ImageAlignment::Center => "↔",
+            ImageAlignment::Right => "⇥",
+            ImageAlignment::FloatLeft => "◧",
+            ImageAlignment::FloatRight => "◨",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            ImageAlignment::Left => "Align left",
+            ImageAlignment::Center => "Align center",
+            ImageAlignment::Right => "Align right",
+            ImageAlignment::FloatLeft => "Float left",
+            ImageAlignment::FloatRight => "Float right",
+        }
+    }
+
+    /// Inline style applied to the aligned element, so the alignment works without any CSS
+    fn apply(&self, style: &CssStyleDeclaration) -> Result<(), JsValue> {
+        let (display, float, margin_left, margin_right) = match self {
+            ImageAlignment::Left => ("block", "", "0", "auto"),
+            ImageAlignment::Center => ("block", "", "auto", "auto"),
+            ImageAlignment::Right => ("block", "", "auto", "0"),
+            ImageAlignment::FloatLeft => ("", "left", "0", "1em"),
+            ImageAlignment::FloatRight => ("", "right", "1em", "0"),
+        };
+        set_or_remove_property(style, "display", display)?;
+        set_or_remove_property(style, "float", float)?;
+        style.set_property("margin-left", margin_left)?;
+        style.set_property("margin-right", margin_right)
+    }
+}
+
+fn set_or_remove_property(
+    style: &CssStyleDeclaration,
+    property: &str,
+    value: &str,
+) -> Result<(), JsValue> {
+    match value.is_empty() {
+        true => style.remove_property(property).map(|_| ()),
+        false => style.set_property(property, value),
+    }
+}
+
+/// Returns the figure that wraps the image, if it has a caption
+pub fn image_figure(img: &Element) -> Option<Element> {
+    img.parent_element()
+        .filter(|parent| parent.class_list().contains(FIGURE_CLASS))
+}
+
+/// Returns the element that is aligned for the image, its figure or the image itself
+fn aligned_element(img: &Element) -> Element {
+    image_figure(img).unwrap_or_else(|| img.clone())
+}
+
+/// Returns the alignment of the image
+pub fn image_alignment(img: &Element) -> Option<ImageAlignment> {
+    let name = aligned_element(img).get_attribute(ALIGN_ATTRIBUTE)?;
+    ImageAlignment::ALL
+        .into_iter()
+        .find(|alignment| alignment.name() == name)
+}
+
+/// Aligns the image, or its figure if it has a caption
+pub fn set_image_alignment(img: &Element, alignment: ImageAlignment) -> Result<(), JsValue> {
+    let el = aligned_element(img).unchecked_into::<HtmlElement>();
+    el.set_attribute(ALIGN_ATTRIBUTE, alignment.name())?;
+    alignment.apply(&el.style())
+}
+
+/// Sets the width of the image in pixels, the height follows its aspect ratio.
+/// `None` restores its natural size
+pub fn set_image_width(img: &Element, width: Option<u32>) -> Result<(), JsValue> {
+    img.remove_attribute("height")?;
+    match width {
+        Some(width) => img.set_attribute("width", &width.to_string()),
+        None => img.remove_attribute("width"),
+    }
+}
+
+pub fn set_image_alt(img: &Element, alt: &str) -> Result<(), JsValue> {
+    match alt.is_empty() {
+        true => img.remove_attribute("alt"),
+        false => img.set_attribute("alt", alt),
+    }
+}
+
+/// Wraps the image in a `<figure>` with an empty `<figcaption>`, or removes the figure and
+/// its caption if it already has one. The alignment is moved to the new aligned element
+pub fn toggle_image_caption(img: &Element) -> Result<(), JsValue> {
+    let alignment = image_alignment(img);
+    let old_aligned = aligned_element(img).unchecked_into::<HtmlElement>();
+    old_aligned.remove_attribute(ALIGN_ATTRIBUTE)?;
+    for property in ["display", "float", "margin-left", "margin-right"] {
+        old_aligned.style().remove_property(property)?;
+    }
+    if old_aligned.get_attribute("style").as_deref() == Some("") {
+        old_aligned.remove_attribute("style")?;
+    }
+
+    match image_figure(img) {
+        Some(figure) => {
+            let parent = figure.parent_node().ok_or("the figure has no parent")?;
+            parent.insert_before(img, Some(&figure))?;
+            figure.remove();
+        }
+        None => {
+            let parent = img.parent_node().ok_or("the image has no parent")?;
+            let figure = document().create_element("figure")?;
+            figure.set_class_name(FIGURE_CLASS);
+            let caption = document().create_element("figcaption")?;
+            caption.set_inner_html("<br>");
+            parent.insert_before(&figure, Some(img))?;
+            figure.append_child(img)?;
+            figure.append_child(&caption)?;
+        }
+    }
+
+    match alignment {
+        Some(alignment) => set_image_alignment(img, alignment),
+        None => Ok(()),
+    }
+}
+
+/// Returns the key used to register the image popover of the editor
+fn popover_key(content_id: &str) -> String {
+    format!("{content_id}-image-popover")
+}
+
+/// Returns the key used to register the resize handle of the editor
+fn handle_key(content_id: &str) -> String {
+    format!("{content_id}-image-handle")
+}
+
+/// Closes the image popover and the resize handle of the editor
+pub fn close_image_popover(content_id: &str) {
+    remove_context_menu(&popover_key(content_id));
+    remove_context_menu(&handle_key(content_id));
+}
+
+/// Opens the image popover when an image of the content is clicked, and closes it when
+/// anything else is clicked. Returns `true` if an image was clicked
+pub fn handle_image_click(content_id: &str, e: &MouseEvent) -> bool {
+    let img = e
+        .target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .filter(|el| el.tag_name().eq_ignore_ascii_case("img"));
+    match img {
+        Some(img) => {
+            open_image_popover(content_id, &img);
+            true
+        }
+        None => {
+            close_image_popover(content_id);
+            false
+        }
+    }
+}
+
+/// Shows the resize handle over the bottom right corner of the image and the popover
+/// below it
+pub fn open_image_popover(content_id: &str, img: &Element) {
+    close_image_popover(content_id);
+    let rect = img.get_bounding_client_rect();
+
+    let popover_key = popover_key(content_id);
+    let handle_key = handle_key(content_id);
+    let handle = add_context_menu_at(
+        &handle_key,
+        rect.bottom() - HANDLE_SIZE / 2.,
+        rect.right() - HANDLE_SIZE / 2.,
+        HANDLE_SIZE,
+        HANDLE_SIZE,
+        vec![content_id.to_string(), context_menu_id(&popover_key)],
+    );
+    style_handle(&handle).unwrap();
+    add_resize_listener(&handle, content_id, img);
+
+    let popover = add_context_menu_at(
+        &popover_key,
+        rect.bottom() + HANDLE_SIZE / 2.,
+        rect.left(),
+        POPOVER_WIDTH,
+        POPOVER_ROW_HEIGHT * 4.,
+        vec![content_id.to_string(), context_menu_id(&handle_key)],
+    );
+    style_popover(&popover).unwrap();
+    add_alignment_row(&popover, content_id, img);
+    add_width_row(&popover, content_id, img);
+    add_alt_row(&popover, content_id, img);
+    add_caption_row(&popover, content_id, img);
+}
+
+/// Reopens the popover once the current event is handled, since the click that triggered
+/// the change would otherwise close the new popover
+fn reopen_image_popover(content_id: &str, img: &Element) {
+    let content_id = content_id.to_string();
+    let img = img.clone();
+    let reopen = Closure::once_into_js(move || {
+        open_image_popover(&content_id, &img);
+    });
+    let _ = window().set_timeout_with_callback(reopen.unchecked_ref());
+}
+
+fn add_resize_listener(handle: &HtmlDivElement, content_id: &str, img: &Element) {
+    let content_id = content_id.to_string();
+    let img = img.clone();
+    let on_mousedown: Closure<dyn Fn(MouseEvent)> = Closure::new(move |e: MouseEvent| {
+        e.prevent_default();
+        e.stop_propagation();
+        let left = img.get_bounding_client_rect().left();
+        let handle_id = context_menu_id(&handle_key(&content_id));
+
+        let move_img = img.clone();
+        let on_mousemove: Closure<dyn Fn(MouseEvent)> = Closure::new(move |e: MouseEvent| {
+            let width = (e.client_x() as f64 - left).max(MIN_IMAGE_WIDTH);
+            let _ = set_image_width(&move_img, Some(width.round() as u32));
+            let rect = move_img.get_bounding_client_rect();
+            if let Some(handle) = document()
+                .get_element_by_id(&handle_id)
+                .and_then(|el| el.dyn_into::<HtmlElement>().ok())
+            {
+                let _ = handle
+                    .style()
+                    .set_property("top", &format!("{}px", rect.bottom() - HANDLE_SIZE / 2.));
+                let _ = handle
+                    .style()
+                    .set_property("left", &format!("{}px", rect.right() - HANDLE_SIZE / 2.));
+            }
+        });
+        document()
+            .add_event_listener_with_callback("mousemove", on_mousemove.as_ref().unchecked_ref())
+            .unwrap();
+
+        let up_content_id = content_id.clone();
+        let up_img = img.clone();
+        let on_mousemove = on_mousemove.into_js_value();
+        let on_mouseup = Closure::once_into_js(move |_: MouseEvent| {
+            let _ = document()
+                .remove_event_listener_with_callback("mousemove", on_mousemove.unchecked_ref());
+            dispatch_input_event(&up_content_id);
+            reopen_image_popover(&up_content_id, &up_img);
+        });
+        let mut options = AddEventListenerOptions::new();
+        options.once(true);
+        document()
+            .add_event_listener_with_callback_and_add_event_listener_options(
+                "mouseup",
+                on_mouseup.unchecked_ref(),
+                &options,
+            )
+            .unwrap();
+    });
+    handle
+        .add_event_listener_with_callback("mousedown", on_mousedown.as_ref().unchecked_ref())
+        .unwrap();
+    on_mousedown.forget();
+}
+
+fn create_row(popover: &HtmlDivElement, label: &str) -> HtmlElement {
+    let row = document()
+        .create_element("div")
+        .unwrap()
+        .unchecked_into::<HtmlElement>();
+    style_row(&row).unwrap();
+    if !label.is_empty() {
+        let label_el = document()
+            .create_element("span")
+            .unwrap()
+            .unchecked_into::<HtmlElement>();
+        label_el.set_inner_text(label);
+        row.append_child(&label_el).unwrap();
+    }
+    popover.append_child(&row).unwrap();
+    row
+}
+
+fn add_alignment_row(popover: &HtmlDivElement, content_id: &str, img: &Element) {
+    let row = create_row(popover, "");
+    let current = image_alignment(img);
+    for alignment in ImageAlignment::ALL {
+        let button = document()
+            .create_element("a")
+            .unwrap()
+            .unchecked_into::<HtmlElement>();
+        button.set_inner_text(alignment.label());
+        button.set_title(alignment.title());
+        style_button(&button, current == Some(alignment)).unwrap();
+
+        let content_id = content_id.to_string();
+        let img = img.clone();
+        let on_click: Closure<dyn Fn(Event)> = Closure::new(move |_: Event| {
+            let _ = set_image_alignment(&img, alignment);
+            dispatch_input_event(&content_id);
+            reopen_image_popover(&content_id, &img);
+        });
+        button
+            .add_event_listener_with_callback("click", on_click.as_ref().unchecked_ref())
+            .unwrap();
+        on_click.forget();
+        row.append_child(&button).unwrap();
+    }
+}
+
+/// Adds a row with an input that calls `on_apply` with its value when Enter is pressed
+fn add_input_row(
+    popover: &HtmlDivElement,
+    label: &str,
+    input_type: &str,
+    value: &str,
+    on_apply: impl Fn(String) + 'static,
+) {
+    let row = create_row(popover, label);
+    let input = document()
+        .create_element("input")
+        .unwrap()
+        .unchecked_into::<HtmlInputElement>();
+    input.set_type(input_type);
+    input.set_value(value);
+    style_input(&input).unwrap();
+
+    let on_keydown: Closure<dyn Fn(KeyboardEvent)> = Closure::new(move |e: KeyboardEvent| {
+        if e.key() != "Enter" {
+            return;
+        }
+        e.prevent_default();
+        let input = e
+            .target()
+            .unwrap()
+            .unchecked_into::<HtmlInputElement>()
+            .value();
+        on_apply(input);
+    });
+    input
+        .add_event_listener_with_callback("keydown", on_keydown.as_ref().unchecked_ref())
+        .unwrap();
+    on_keydown.forget();
+    row.append_child(&input).unwrap();
+}
+
+fn add_width_row(popover: &HtmlDivElement, content_id: &str, img: &Element) {
+    let width = img.get_attribute("width").unwrap_or_default();
+    let content_id = content_id.to_string();
+    let img = img.clone();
+    add_input_row(popover, "Width", "number", &width, move |value| {
+        let width = value.trim().parse::<u32>().ok().filter(|w| *w > 0);
+        let _ = set_image_width(&img, width);
+        dispatch_input_event(&content_id);
+        reopen_image_popover(&content_id, &img);
+    });
+}
+
+fn add_alt_row(popover: &HtmlDivElement, content_id: &str, img: &Element) {
+    let alt = img.get_attribute("alt").unwrap_or_default();
+    let content_id = content_id.to_string();
+    let img = img.clone();
+    add_input_row(popover, "Alt text", "text", &alt, move |value| {
+        let _ = set_image_alt(&img, value.trim());
+        dispatch_input_event(&content_id);
+        close_image_popover(&content_id);
+    });
+}
+
+fn add_caption_row(popover: &HtmlDivElement, content_id: &str, img: &Element) {
+    let row = create_row(popover, "Caption");
+    let checkbox = document()
+        .create_element("input")
+        .unwrap()
+        .unchecked_into::<HtmlInputElement>();
+    checkbox.set_type("checkbox");
+    checkbox.set_checked(image_figure(img).is_some());
+
+    let content_id = content_id.to_string();
+    let img = img.clone();
+    let on_change: Closure<dyn Fn(Event)> = Closure::new(move |_: Event| {
+        let _ = toggle_image_caption(&img);
+        dispatch_input_event(&content_id);
+        reopen_image_popover(&content_id, &img);
+    });
+    checkbox
+        .add_event_listener_with_callback("change", on_change.as_ref().unchecked_ref())
+        .unwrap();
+    on_change.forget();
+    row.append_child(&checkbox).unwrap();
+}
+
+fn style_handle(el: &HtmlDivElement) -> Result<(), JsValue> {
+    el.style().set_property("box-sizing", "border-box")?;
+    el.style().set_property("border", "1px solid black")?;
+    el.style().set_property("background-color", "white")?;
+    el.style().set_property("cursor", "nwse-resize")
+}
+
+fn style_popover(el: &HtmlDivElement) -> Result<(), JsValue> {
+    el.style().set_property("border", "1px solid black")?;
+    el.style().set_property("background-color", "white")?;
+    el.style().set_property("display", "flex")?;
+    el.style().set_property("flex-direction", "column")
+}
+
+fn style_row(el: &HtmlElement) -> Result<(), JsValue> {
+    el.style()
+        .set_property("height", &format!("{POPOVER_ROW_HEIGHT}px"))?;
+    el.style().set_property("display", "flex")?;
+    el.style().set_property("align-items", "center")?;
+    el.style().set_property("gap", "4px")?;
+    el.style().set_property("padding", "0 4px")
+}
+
+fn style_button(el: &HtmlElement, selected: bool) -> Result<(), JsValue> {
+    el.style().set_property("width", "24px")?;
+    el.style().set_property("text-align", "center")?;
+    el.style().set_property("cursor", "pointer")?;
+    el.style().set_property("user-select", "none")?;
+    if selected {
+        el.style().set_property("outline", "1px solid black")?;
+    }
+    Ok(())
+}
+
+fn style_input(el: &HtmlInputElement) -> Result<(), JsValue> {
+    el.style().set_property("flex", "1")?;
+    el.style().set_property("min-width", "0")
+}
@@ -1,8 +1,34 @@
-use crate::action::{Action as PapelitoAction, Actions};
+use crate::action::{Action as PapelitoAction, ActionOptions, Actions};
 use crate::action_button::ActionButton;
-use crate::util::exec_format_block;
+use crate::autosave::{Autosave, Draft};
+use crate::code::{
+    apply_backtick_rule, code_block_at_caret, handle_code_block_keydown, toggle_inline_code,
+};
+use crate::collab::{with_preserved_text_selection, Collaboration};
+use crate::comment::{
+    add_comment_style, clicked_comments, handle_comment_beforeinput, remove_empty_comment_markers,
+    CommentCallback,
+};
+use crate::embed::sanitize_embeds;
+use crate::find::{handle_find_keydown, refresh_find};
+use crate::form::{connect_form, validate_html, ValidationError};
+use crate::highlight::{highlight_all, highlight_code_element, html_without_highlighting};
+use crate::image::{close_image_popover, handle_image_click};
+use crate::list::{ensure_checkboxes, handle_list_keydown, sync_checkbox};
+use crate::normalize::normalize_html;
+use crate::presence::{presence_container_id, PresenceOverlay, RemotePresence};
+use crate::schema::{
+    enforce_loaded_content, handle_schema_beforeinput, register_content_rules,
+    unregister_content_rules, StrippedCallback,
+};
+use crate::spellcheck::{handle_spelling_context_menu, refresh_spelling, spelling_container_id};
+use crate::suggestion::handle_suggestion_beforeinput;
+use crate::text::html_to_text;
+use crate::transaction::{steps_between, Transaction, TransactionCallback};
+use crate::update::{ChangeCallback, ContentUpdater, FocusCallback, UpdateMode};
+use crate::util::{content_id, dispatch_input_event, exec_format_block};
 use lazy_static::lazy_static;
-use leptos::ev::{Event, KeyboardEvent};
+use leptos::ev::{Event, FocusEvent, KeyboardEvent, MouseEvent};
 use leptos::*;
 use leptos_dom::{html::Div, is_browser};
 use std::sync::Arc;
@@ -11,6 +37,31 @@ use web_sys::HtmlDocument;
 
 pub mod action;
 pub mod action_button;
+pub mod autosave;
+pub mod code;
+pub mod collab;
+pub mod color_picker;
+pub mod comment;
+pub mod diff;
+pub mod diff_view;
+pub mod document;
+pub mod embed;
+pub mod find;
+pub mod font;
+pub mod form;
+pub mod format;
+pub mod highlight;
+pub mod html;
+pub mod image;
+pub mod list;
+pub mod normalize;
+pub mod presence;
+pub mod schema;
+pub mod spellcheck;
+pub mod suggestion;
+pub mod text;
+pub mod transaction;
+pub mod update;
 pub mod util;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -44,13 +95,17 @@ fn setup() {
             .lock()
             .unwrap()
             .retain(|(menu_id, other_els)| {
-                let menu_el = document().get_element_by_id(menu_id).unwrap();
-                if !target.is_same_node(Some(&menu_el)) {
+                let menu_el = match document().get_element_by_id(menu_id) {
+                    Some(menu_el) => menu_el,
+                    None => return false,
+                };
+                if !menu_el.contains(Some(&target)) {
                     let mut clicked_on_el = false;
                     for el in other_els {
-                        let _el = document().get_element_by_id(el).unwrap();
-                        if target.is_same_node(Some(&_el)) {
-                            clicked_on_el = true;
+                        if let Some(_el) = document().get_element_by_id(el) {
+                            if _el.contains(Some(&target)) {
+                                clicked_on_el = true;
+                            }
                         }
                     }
                     if !clicked_on_el {
@@ -67,6 +122,8 @@ fn setup() {
         .add_event_listener_with_callback("click", handle_click.as_ref().unchecked_ref())
         .unwrap();
     handle_click.forget();
+
+    let _ = add_comment_style();
 }
 
 /// https://developer.mozilla.org/en-US/docs/Web/API/Node/nodeType
@@ -79,8 +136,62 @@ pub fn Papelito(
     key: String,
     content_signal: RwSignal<String>,
     classes: PapelitoClasses,
+    /// Class added to the editor while its content is invalid
+    #[prop(optional)]
+    invalid_class: Option<String>,
     #[prop(optional)] actions: Actions,
     #[prop(optional)] default_paragraph_separator: String,
+    /// Toggles the native spell checking of the browser
+    #[prop(optional)]
+    spellcheck: Option<bool>,
+    /// Name of the hidden input that holds the content, so it is submitted with its form.
+    /// No input is rendered if it is empty
+    #[prop(optional)]
+    name: String,
+    /// Makes an empty editor invalid
+    #[prop(optional)]
+    required: bool,
+    /// Maximum number of characters of the text of the content
+    #[prop(optional)]
+    max_length: Option<usize>,
+    /// Receives the validation error of the content, `None` if it is valid
+    #[prop(optional)]
+    validation_signal: Option<RwSignal<Option<ValidationError>>>,
+    /// When the edits are written to `content_signal`
+    #[prop(optional)]
+    update_mode: UpdateMode,
+    /// Called with the new content whenever the editor writes it to `content_signal`
+    #[prop(optional)]
+    on_change: Option<ChangeCallback>,
+    #[prop(optional)] on_blur: Option<FocusCallback>,
+    #[prop(optional)] on_focus: Option<FocusCallback>,
+    /// Saves the edits as a draft keyed by `key`, and offers to restore the draft on mount
+    /// when it is newer than the content. The draft is cleared when the form of the editor
+    /// is submitted, or by calling `Autosave::clear_draft`
+    #[prop(optional)]
+    autosave: Option<Autosave>,
+    /// Shares the edits with other peers, which must start from the same content
+    #[prop(optional)]
+    collaboration: Option<Collaboration>,
+    /// Carets and selections of the other participants, drawn over the content. Their
+    /// positions are resolved with `collaboration`, the local selection to send to them is
+    /// returned by `presence::local_selection`
+    #[prop(optional)]
+    presence_signal: Option<RwSignal<Vec<RemotePresence>>>,
+    /// Called with the ids of the comment threads of a clicked comment marker
+    #[prop(optional)]
+    on_comment_click: Option<CommentCallback>,
+    /// Called with the steps of every edit written to `content_signal`, which can be
+    /// replayed with `transaction::replay`
+    #[prop(optional)]
+    on_transaction: Option<TransactionCallback>,
+    /// Receives the plain text of the content, see `text::html_to_text`
+    #[prop(optional)]
+    text_signal: Option<RwSignal<String>>,
+    /// Called with what was removed from loaded, pasted or dropped content that didn't fit
+    /// the schema of the actions
+    #[prop(optional)]
+    on_content_stripped: Option<StrippedCallback>,
 ) -> impl IntoView {
     use crate::util::exec;
 
@@ -91,21 +202,104 @@ pub fn Papelito(
 
     let content_ref = create_node_ref::<Div>(cx);
 
+    let validation_error = create_memo(cx, move |_| match is_browser() {
+        true => validate_html(&content_signal.get(), required, max_length),
+        false => None,
+    });
+    let pending_draft = create_rw_signal(cx, None::<Draft>);
+    if let (true, Some(autosave)) = (is_browser(), &autosave) {
+        pending_draft.set(autosave.newer_draft(&key, &initial_value));
+    }
+
+    // Errors are only shown once the content was edited or its form was submitted
+    let touched = create_rw_signal(cx, false);
+    let show_error = move || touched.get() && validation_error.get().is_some();
+    if let Some(validation_signal) = validation_signal {
+        create_effect(cx, move |_| validation_signal.set(validation_error.get()));
+    }
+    if let Some(text_signal) = text_signal {
+        create_effect(cx, move |_| {
+            text_signal.set(content_signal.with(|html| html_to_text(html)))
+        });
+    }
+
     let default_paragraph_separator = match default_paragraph_separator.is_empty() {
         true => Arc::new("div".to_string()),
         false => Arc::new(default_paragraph_separator.clone()),
     };
 
+    let highlighter = actions.options().highlighter.clone();
+    let effect_highlighter = highlighter.clone();
+    let embed_providers = actions.options().embed_providers.clone();
+    let effect_embed_providers = embed_providers.clone();
+    let spell_checker = actions.options().spell_checker.clone();
+    let effect_spell_checker = spell_checker.clone();
+    let effect_content_id = content_id(&key);
+    let schema = actions.options().schema.clone();
+    register_content_rules(
+        &content_id(&key),
+        schema.as_ref(),
+        &default_paragraph_separator,
+        &embed_providers,
+    );
+    let cleanup_content_id = content_id(&key);
+    on_cleanup(cx, move || unregister_content_rules(&cleanup_content_id));
+    let effect_schema = schema.clone();
+    let effect_on_stripped = on_content_stripped.clone();
+    if let (true, Some(collaboration)) = (is_browser(), &collaboration) {
+        // The edits are shared as normalized content, so the document starts from it too
+        let enforced = enforce_loaded_content(
+            schema.as_ref(),
+            &initial_value,
+            &default_paragraph_separator,
+            None,
+        );
+        let initial = enforced.unwrap_or_else(|| initial_value.clone());
+        collaboration.start(&normalize_html(&initial, &default_paragraph_separator));
+    }
+    let presence = PresenceOverlay::new(&content_id(&key), collaboration.clone());
+    if let Some(presence_signal) = presence_signal {
+        let presence = presence.clone();
+        create_effect(cx, move |_| presence.set(presence_signal.get()));
+    }
+    let effect_collaboration = collaboration.clone();
+    let effect_presence = presence.clone();
+    let effect_separator = default_paragraph_separator.clone();
     create_effect(cx, move |_| {
         let content_val = content_signal.get();
         if let Some(content) = content_ref.get() {
-            if content_val
-                != content
-                    .dyn_ref::<web_sys::HtmlElement>()
-                    .unwrap()
-                    .inner_html()
-            {
-                content.inner_html(content_val);
+            let content_el = content.dyn_ref::<web_sys::HtmlElement>().unwrap().clone();
+            // Content that only differs in its markup is the same, e.g. `<b>` and `<strong>`
+            let current =
+                normalize_html(&html_without_highlighting(&content_el), &effect_separator);
+            if normalize_html(&content_val, &effect_separator) != current {
+                let enforced = enforce_loaded_content(
+                    effect_schema.as_ref(),
+                    &content_val,
+                    &effect_separator,
+                    effect_on_stripped.as_ref(),
+                );
+                let html = enforced.clone().unwrap_or(content_val);
+                content.inner_html(sanitize_embeds(&html, &effect_embed_providers));
+                highlight_all(&content_el, effect_highlighter.0.as_ref());
+                if let Some(checker) = &effect_spell_checker {
+                    refresh_spelling(&effect_content_id, checker.0.as_ref());
+                }
+                // Content set from outside of the editor is shared as a local edit, normalized
+                // like the edits made in it
+                if let Some(collaboration) = &effect_collaboration {
+                    let shared =
+                        normalize_html(&html_without_highlighting(&content_el), &effect_separator);
+                    collaboration.local_edit(&shared);
+                }
+                effect_presence.render();
+                // The content without what the schema removed is written back once this
+                // effect has run
+                if enforced.is_some() {
+                    let html = normalize_html(&html, &effect_separator);
+                    let write = Closure::once_into_js(move || content_signal.set(html));
+                    let _ = window().set_timeout_with_callback(write.unchecked_ref());
+                }
             }
         }
     });
@@ -119,13 +313,168 @@ pub fn Papelito(
     }
 
     let _key_clone = key.clone();
+    let load_highlighter = highlighter.clone();
+    let load_spell_checker = spell_checker.clone();
+    let load_content_id = content_id(&key);
+    let reset_value = initial_value.clone();
+    let form_key = key.clone();
+    let form_autosave = autosave.clone();
+    let load_collaboration = collaboration.clone();
+    let load_presence = presence.clone();
+    let suggestion_author = actions.options().suggestion_author.clone();
+    let load_separator = default_paragraph_separator.clone();
     content_ref.on_load(cx, move |content| {
-        let initial_value_clone = initial_value.clone();
-        content.inner_html(initial_value_clone);
+        let content_el = content.dyn_ref::<web_sys::HtmlElement>().unwrap().clone();
+        let enforced = enforce_loaded_content(
+            schema.as_ref(),
+            &initial_value,
+            &load_separator,
+            on_content_stripped.as_ref(),
+        );
+        let initial_value_clone = enforced.clone().unwrap_or_else(|| initial_value.clone());
+        content.inner_html(sanitize_embeds(&initial_value_clone, &embed_providers));
+        if let Some(html) = enforced {
+            content_signal.set(normalize_html(&html, &load_separator));
+        }
+        highlight_all(&content_el, load_highlighter.0.as_ref());
+        if let Some(collaboration) = &load_collaboration {
+            let content_el = content_el.clone();
+            let embed_providers = embed_providers.clone();
+            let highlighter = load_highlighter.clone();
+            let presence = load_presence.clone();
+            collaboration.listen(move |html| {
+                // The content is updated before the signal, so the effect doesn't reset it
+                with_preserved_text_selection(&content_el, || {
+                    content_el.set_inner_html(&sanitize_embeds(&html, &embed_providers));
+                    highlight_all(&content_el, highlighter.0.as_ref());
+                });
+                presence.render();
+                content_signal.set(html);
+            });
+        }
+        // Edits are turned into suggestions, pasted content is stripped of what the schema
+        // doesn't allow, and typing is kept out of the edges of the comments, before the
+        // browser applies them
+        let content_id = load_content_id.clone();
+        let author = suggestion_author.clone();
+        let schema = schema.clone();
+        let separator = load_separator.clone();
+        let providers = embed_providers.clone();
+        let on_stripped = on_content_stripped.clone();
+        let handle_beforeinput: Closure<dyn Fn(web_sys::InputEvent)> =
+            Closure::new(move |e: web_sys::InputEvent| {
+                if handle_suggestion_beforeinput(&content_id, &e, &author) {
+                    return;
+                }
+                if let Some(schema) = &schema {
+                    if let Some(stripped) =
+                        handle_schema_beforeinput(&e, schema, &separator, &providers)
+                    {
+                        if let (false, Some(on_stripped)) = (stripped.is_empty(), &on_stripped) {
+                            on_stripped(stripped);
+                        }
+                        return;
+                    }
+                }
+                handle_comment_beforeinput(&content_id, &e);
+            });
+        content_el
+            .add_event_listener_with_callback(
+                "beforeinput",
+                handle_beforeinput.as_ref().unchecked_ref(),
+            )
+            .unwrap();
+        handle_beforeinput.forget();
+
+        if let Some(checker) = load_spell_checker.clone() {
+            // The content isn't laid out yet, so the underlines are drawn afterwards
+            let content_id = load_content_id.clone();
+            let check = Closure::once_into_js(move || {
+                refresh_spelling(&content_id, checker.0.as_ref());
+            });
+            let _ = window().set_timeout_with_callback(check.unchecked_ref());
+        }
+
+        // The overlays are drawn once the content is laid out
+        if let Some(presence_signal) = presence_signal {
+            let presence = load_presence.clone();
+            let render = Closure::once_into_js(move || presence.set(presence_signal.get()));
+            let _ = window().set_timeout_with_callback(render.unchecked_ref());
+        }
+
+        // The content isn't inside its form until it is mounted
+        let content_id = load_content_id.clone();
+        let reset_value = reset_value.clone();
+        let key = form_key.clone();
+        let autosave = form_autosave.clone();
+        let connect = Closure::once_into_js(move || {
+            connect_form(
+                &content_id,
+                move || {
+                    touched.set(true);
+                    let valid = validation_error.get().is_none();
+                    if let (true, Some(autosave)) = (valid, &autosave) {
+                        autosave.clear_draft(&key);
+                    }
+                    valid
+                },
+                move || {
+                    content_signal.set(reset_value.clone());
+                    touched.set(false);
+                },
+            );
+        });
+        let _ = window().set_timeout_with_callback(connect.unchecked_ref());
+    });
+
+    let commit_content_id = content_id(&key);
+    let commit_key = key.clone();
+    let commit_autosave = autosave.clone();
+    let commit_collaboration = collaboration.clone();
+    let commit_separator = default_paragraph_separator.clone();
+    let updater = ContentUpdater::new(update_mode, move || {
+        let Some(content) = document().get_element_by_id(&commit_content_id) else {
+            return;
+        };
+        let html = normalize_html(&html_without_highlighting(&content), &commit_separator);
+        if let Some(collaboration) = &commit_collaboration {
+            collaboration.local_edit(&html);
+        }
+        if content_signal.with(|v| *v == html) {
+            return;
+        }
+        if let Some(on_transaction) = &on_transaction {
+            let steps = content_signal.with(|old| steps_between(old, &html));
+            on_transaction(Transaction {
+                steps,
+                time: js_sys::Date::now(),
+            });
+        }
+        content_signal.set(html.clone());
+        // The draft offered to be restored isn't overwritten until it is restored or discarded
+        let offers_draft = pending_draft.with(Option::is_some);
+        if let Some(autosave) = commit_autosave.as_ref().filter(|_| !offers_draft) {
+            autosave.save_draft(&commit_key, &html);
+        }
+        if let Some(on_change) = &on_change {
+            on_change(html);
+        }
     });
 
     let separator_clone = default_paragraph_separator.clone();
+    let input_content_id = content_id(&key);
+    let input_updater = updater.clone();
+    let input_spell_checker = spell_checker.clone();
+    let input_presence = presence.clone();
     let on_content_change = move |e: Event| {
+        let mut composing = false;
+        if let Some(input_event) = e.dyn_ref::<web_sys::InputEvent>() {
+            composing = input_event.is_composing();
+            if input_event.data().as_deref() == Some("`") {
+                let _ = apply_backtick_rule(&input_content_id);
+            }
+        }
+
         let first_child = e
             .target()
             .unwrap()
@@ -150,13 +499,52 @@ pub fn Papelito(
             }
         }
 
-        let t = e.target().unwrap();
-        let t = t.dyn_ref::<web_sys::HtmlElement>().unwrap();
-        content_signal.update(|v| *v = t.inner_html());
+        if let Some(t) = e
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::Element>().ok())
+        {
+            let _ = ensure_checkboxes(&t);
+            remove_empty_comment_markers(&t);
+        }
+
+        if !composing {
+            let code = code_block_at_caret(&input_content_id)
+                .and_then(|pre| pre.query_selector("code").ok().flatten());
+            if let Some(code) = code {
+                highlight_code_element(&code, highlighter.0.as_ref());
+            }
+        }
+
+        input_updater.input();
+        touched.set(true);
+        refresh_find(&input_content_id);
+        if let Some(checker) = &input_spell_checker {
+            refresh_spelling(&input_content_id, checker.0.as_ref());
+        }
+        input_presence.render();
     };
 
     let keydown_separator_clone = default_paragraph_separator.clone();
+    let keydown_content_id = content_id(&key);
     let on_content_keydown = move |e: KeyboardEvent| {
+        close_image_popover(&keydown_content_id);
+        if handle_find_keydown(&keydown_content_id, &e) {
+            return;
+        }
+        if (e.ctrl_key() || e.meta_key()) && e.key().eq_ignore_ascii_case("e") {
+            e.prevent_default();
+            let _ = toggle_inline_code(&keydown_content_id);
+            return;
+        }
+        if let Ok(true) =
+            handle_code_block_keydown(&keydown_content_id, &e, &keydown_separator_clone)
+        {
+            return;
+        }
+        if let Ok(true) = handle_list_keydown(&keydown_content_id, &e) {
+            return;
+        }
+
         let doc = document();
         let html_doc = doc.dyn_ref::<HtmlDocument>().expect("");
         if e.key() == "Space"
@@ -167,23 +555,136 @@ pub fn Papelito(
         }
     };
 
+    let change_content_id = content_id(&key);
+    let on_content_change_event = move |e: Event| {
+        let target = e
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::Element>().ok());
+        if let Some(target) = target {
+            if sync_checkbox(&target) {
+                dispatch_input_event(&change_content_id);
+            }
+        }
+    };
+
+    let click_content_id = content_id(&key);
+    let on_content_click = move |e: MouseEvent| {
+        handle_image_click(&click_content_id, &e);
+        refresh_find(&click_content_id);
+        if let Some(on_comment_click) = &on_comment_click {
+            let ids = clicked_comments(&click_content_id, &e);
+            if !ids.is_empty() {
+                on_comment_click(ids);
+            }
+        }
+    };
+
+    let context_menu_content_id = content_id(&key);
+    let context_menu_spell_checker = spell_checker.clone();
+    let on_content_context_menu = move |e: MouseEvent| {
+        if let Some(checker) = &context_menu_spell_checker {
+            handle_spelling_context_menu(&context_menu_content_id, &e, checker.0.as_ref());
+        }
+    };
+
+    let on_content_blur = move |e: FocusEvent| {
+        updater.flush();
+        touched.set(true);
+        if let Some(on_blur) = &on_blur {
+            on_blur(e);
+        }
+    };
+    let on_content_focus = move |e: FocusEvent| {
+        if let Some(on_focus) = &on_focus {
+            on_focus(e);
+        }
+    };
+
     let key_clone = _key.clone();
     let selected_class = classes.selected.clone();
-    let content_unique_id = format!("{}-content", key_clone);
+    let action_options = ActionOptions {
+        paragraph_separator: default_paragraph_separator.to_string(),
+        ..actions.options().clone()
+    };
+    let content_unique_id = content_id(&key_clone);
+    let editor_class = _classes.editor.clone();
+    let editor_class = move || match (show_error(), &invalid_class) {
+        (true, Some(invalid_class)) => format!("{editor_class} {invalid_class}"),
+        _ => editor_class.clone(),
+    };
+    let hidden_input = (!name.is_empty()).then(|| {
+        view! {cx,
+            <input type="hidden" name=name value=move || content_signal.get()/>
+        }
+    });
+    let scroll_presence = presence.clone();
+    let scroll_spell_checker = spell_checker.clone();
+    let scroll_content_id = content_id(&key);
+    let on_content_scroll = move |_: Event| {
+        scroll_presence.render();
+        if let Some(checker) = &scroll_spell_checker {
+            refresh_spelling(&scroll_content_id, checker.0.as_ref());
+        }
+    };
+    // The underlines follow the text, which wraps again when the window is resized
+    if let (true, Some(checker)) = (is_browser(), spell_checker.clone()) {
+        let resize_content_id = content_id(&key);
+        let on_resize: Closure<dyn Fn(Event)> = Closure::new(move |_: Event| {
+            refresh_spelling(&resize_content_id, checker.0.as_ref());
+        });
+        window()
+            .add_event_listener_with_callback("resize", on_resize.as_ref().unchecked_ref())
+            .unwrap();
+        on_cleanup(cx, move || {
+            let _ = window()
+                .remove_event_listener_with_callback("resize", on_resize.as_ref().unchecked_ref());
+        });
+    }
+    let presence_id = presence_container_id(&content_unique_id);
+    let spelling_id = spelling_container_id(&content_unique_id);
+    let notice_key = key.clone();
+    let draft_notice = move || {
+        let draft = pending_draft.get()?;
+        let restore = move |_| {
+            if let Some(draft) = pending_draft.get() {
+                content_signal.set(draft.content);
+            }
+            pending_draft.set(None);
+        };
+        let key = notice_key.clone();
+        let autosave = autosave.clone();
+        let discard = move |_| {
+            if let Some(autosave) = &autosave {
+                autosave.clear_draft(&key);
+            }
+            pending_draft.set(None);
+        };
+        Some(view! {cx,
+            <div class="papelito-draft-notice">
+                {format!("A draft from {} was found", draft.saved_at_label())}
+                <button on:click=restore>"Restore"</button>
+                <button on:click=discard>"Discard"</button>
+            </div>
+        })
+    };
     view! { cx,
-        <div class=_classes.editor id=key>
+        <div class=editor_class id=key>
+            {hidden_input}
+            {draft_notice}
             <div class=_classes.actionbar>
                 <For
                     each=move || actions.inner().clone()
                     key=|action| action.title.clone()
                     view = move |cx, action: PapelitoAction| {
                         view! {cx,
-                            <ActionButton selected_class=selected_class.clone() content_ref=content_ref action=action editor_key=_key.clone() class=classes.button.clone()/>
+                            <ActionButton selected_class=selected_class.clone() content_ref=content_ref action=action editor_key=_key.clone() options=action_options.clone() class=classes.button.clone()/>
                         }
                     }
                 />
             </div>
-            <div id=content_unique_id on:keydown=on_content_keydown on:input=on_content_change class=_classes.content ref=content_ref contentEditable="true"></div>
+            <div id=presence_id style="position: relative; height: 0; z-index: 1; pointer-events: none;"></div>
+            <div id=spelling_id style="position: relative; height: 0; z-index: 1; pointer-events: none;"></div>
+            <div id=content_unique_id on:scroll=on_content_scroll on:keydown=on_content_keydown on:input=on_content_change on:change=on_content_change_event on:click=on_content_click on:contextmenu=on_content_context_menu on:blur=on_content_blur on:focus=on_content_focus class=_classes.content spellcheck=spellcheck.map(|s| s.to_string()) aria-required=required.then(|| "true") aria-invalid=move || show_error().to_string() ref=content_ref contentEditable="true"></div>
         </div>
     }
 }
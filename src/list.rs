@@ -0,0 +1,246 @@
+use crate::util::{dispatch_input_event, exec, selection_element, with_preserved_selection};
+use leptos_dom::document;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Element, HtmlInputElement, KeyboardEvent};
+
+/// Class of the `<ul>` elements rendered as checklists
+pub const CHECKLIST_CLASS: &str = "papelito-checklist";
+/// Class of the checkbox at the start of each checklist item
+pub const CHECKBOX_CLASS: &str = "papelito-checkbox";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ListKind {
+    Ordered,
+    Unordered,
+    Checklist,
+}
+
+fn is_list(el: &Element) -> bool {
+    matches!(el.tag_name().to_lowercase().as_str(), "ul" | "ol")
+}
+
+fn is_list_item(el: &Element) -> bool {
+    el.tag_name().eq_ignore_ascii_case("li")
+}
+
+/// Returns the closest list item at the caret
+pub fn list_item_at_caret(content_id: &str) -> Option<Element> {
+    let el = selection_element(content_id)?;
+    let li = el.closest("li").ok()??;
+    let root = document().get_element_by_id(content_id)?;
+    match root.contains(Some(&li)) {
+        true => Some(li),
+        false => None,
+    }
+}
+
+/// Returns the kind of the innermost list at the caret
+pub fn current_list_kind(content_id: &str) -> Option<ListKind> {
+    let list = list_item_at_caret(content_id)?.parent_element()?;
+    match list.tag_name().to_lowercase().as_str() {
+        "ol" => Some(ListKind::Ordered),
+        "ul" if list.class_list().contains(CHECKLIST_CLASS) => Some(ListKind::Checklist),
+        "ul" => Some(ListKind::Unordered),
+        _ => None,
+    }
+}
+
+/// Nests the list item at the caret inside the previous item, returns `false` if it
+/// can't be indented
+pub fn indent_list_item(content_id: &str) -> Result<bool, JsValue> {
+    let Some(li) = list_item_at_caret(content_id) else {
+        return Ok(false);
+    };
+    let Some(previous) = li.previous_element_sibling().filter(is_list_item) else {
+        return Ok(false);
+    };
+    let list = li.parent_element().ok_or("the list item has no list")?;
+
+    let sublist = match previous
+        .last_element_child()
+        .filter(|el| el.tag_name() == list.tag_name())
+    {
+        Some(sublist) => sublist,
+        None => {
+            let sublist = list.clone_node()?.unchecked_into::<Element>();
+            sublist.remove_attribute("id")?;
+            previous.append_child(&sublist)?;
+            sublist
+        }
+    };
+    with_preserved_selection(|| sublist.append_child(&li))?;
+
+    dispatch_input_event(content_id);
+    Ok(true)
+}
+
+/// Moves the list item at the caret out of its nested list, the items after it become its
+/// children. Returns `false` if it isn't inside a nested list
+pub fn outdent_list_item(content_id: &str) -> Result<bool, JsValue> {
+    let Some(li) = list_item_at_caret(content_id) else {
+        return Ok(false);
+    };
+    let list = li.parent_element().ok_or("the list item has no list")?;
+    // Nested lists are usually inside the parent item, but execCommand places them
+    // directly inside the parent list
+    let Some(parent) = list
+        .parent_element()
+        .filter(|el| is_list_item(el) || is_list(el))
+    else {
+        return Ok(false);
+    };
+
+    with_preserved_selection(|| -> Result<(), JsValue> {
+        let mut following = vec![];
+        let mut next = li.next_element_sibling();
+        while let Some(el) = next {
+            next = el.next_element_sibling();
+            following.push(el);
+        }
+        if !following.is_empty() {
+            let sublist = list.clone_node()?.unchecked_into::<Element>();
+            sublist.remove_attribute("id")?;
+            for el in following {
+                sublist.append_child(&el)?;
+            }
+            li.append_child(&sublist)?;
+        }
+
+        let anchor = match is_list_item(&parent) {
+            true => parent.clone(),
+            false => list.clone(),
+        };
+        anchor
+            .parent_node()
+            .ok_or("the list has no parent")?
+            .insert_before(&li, anchor.next_sibling().as_ref())?;
+        if list.first_element_child().is_none() {
+            list.remove();
+        }
+        Ok(())
+    })?;
+
+    dispatch_input_event(content_id);
+    Ok(true)
+}
+
+/// Handles Tab and Shift+Tab inside lists, returns `true` if the event was handled
+pub fn handle_list_keydown(content_id: &str, e: &KeyboardEvent) -> Result<bool, JsValue> {
+    if e.key() != "Tab" || list_item_at_caret(content_id).is_none() {
+        return Ok(false);
+    }
+    e.prevent_default();
+    match e.shift_key() {
+        true => outdent_list_item(content_id)?,
+        false => indent_list_item(content_id)?,
+    };
+    Ok(true)
+}
+
+/// Turns the block at the caret into a checklist, or the checklist at the caret back into
+/// regular blocks
+pub fn toggle_checklist(content_id: &str) -> Result<(), JsValue> {
+    if current_list_kind(content_id) == Some(ListKind::Checklist) {
+        let list = list_item_at_caret(content_id)
+            .and_then(|li| li.parent_element())
+            .ok_or("couldn't find the checklist")?;
+        remove_checkboxes(&list)?;
+        list.class_list().remove_1(CHECKLIST_CLASS)?;
+        if list.class_list().length() == 0 {
+            list.remove_attribute("class")?;
+        }
+        exec("insertUnorderedList", "")?;
+    } else {
+        if current_list_kind(content_id) != Some(ListKind::Unordered) {
+            exec("insertUnorderedList", "")?;
+        }
+        let list = list_item_at_caret(content_id)
+            .and_then(|li| li.parent_element())
+            .ok_or("couldn't create the checklist")?;
+        list.class_list().add_1(CHECKLIST_CLASS)?;
+        ensure_checkboxes(&list)?;
+    }
+    dispatch_input_event(content_id);
+    Ok(())
+}
+
+fn create_checkbox() -> Result<Element, JsValue> {
+    let checkbox = document().create_element("input")?;
+    checkbox.set_attribute("type", "checkbox")?;
+    checkbox.set_class_name(CHECKBOX_CLASS);
+    Ok(checkbox)
+}
+
+/// Makes sure every item of the checklists inside `root` (or `root` itself) starts with a
+/// checkbox, and that items of other lists don't have one. Needed because new items created
+/// by pressing Enter don't get a checkbox
+pub fn ensure_checkboxes(root: &Element) -> Result<(), JsValue> {
+    // Selectors are matched against the whole document, so this also matches the items
+    // of `root` itself
+    let items = root.query_selector_all(&format!("ul.{CHECKLIST_CLASS} > li"))?;
+    let items = (0..items.length())
+        .filter_map(|i| items.item(i))
+        .map(|node| node.unchecked_into::<Element>());
+
+    for li in items {
+        let checkboxes = li.query_selector_all(&format!(":scope > input.{CHECKBOX_CLASS}"))?;
+        let first_is_checkbox = li
+            .first_child()
+            .and_then(|node| node.dyn_into::<Element>().ok())
+            .map(|el| el.class_list().contains(CHECKBOX_CLASS))
+            .unwrap_or(false);
+        if first_is_checkbox && checkboxes.length() == 1 {
+            continue;
+        }
+        // An item split by Enter can end up with the checkbox in the middle, or with more
+        // than one, the first one is kept at the start of the item
+        let checkbox = match checkboxes.item(0) {
+            Some(checkbox) => checkbox,
+            None => create_checkbox()?.unchecked_into(),
+        };
+        for i in 1..checkboxes.length() {
+            checkboxes
+                .item(i)
+                .unwrap()
+                .unchecked_into::<Element>()
+                .remove();
+        }
+        li.insert_before(&checkbox, li.first_child().as_ref())?;
+    }
+
+    let stray = root.query_selector_all(&format!(
+        "ul:not(.{CHECKLIST_CLASS}) > li > input.{CHECKBOX_CLASS}, ol > li > input.{CHECKBOX_CLASS}"
+    ))?;
+    for i in 0..stray.length() {
+        stray.item(i).unwrap().unchecked_into::<Element>().remove();
+    }
+    Ok(())
+}
+
+fn remove_checkboxes(list: &Element) -> Result<(), JsValue> {
+    let checkboxes = list.query_selector_all(&format!("input.{CHECKBOX_CLASS}"))?;
+    for i in 0..checkboxes.length() {
+        checkboxes
+            .item(i)
+            .unwrap()
+            .unchecked_into::<Element>()
+            .remove();
+    }
+    Ok(())
+}
+
+/// Mirrors the checked state of a checklist checkbox to its `checked` attribute, so it is
+/// persisted in the HTML. Returns `true` if the element was a checklist checkbox
+pub fn sync_checkbox(el: &Element) -> bool {
+    let Some(checkbox) = el.dyn_ref::<HtmlInputElement>() else {
+        return false;
+    };
+    if !checkbox.class_list().contains(CHECKBOX_CLASS) {
+        return false;
+    }
+    let _ = match checkbox.checked() {
+        true => checkbox.set_attribute("checked", ""),
+        false => checkbox.remove_attribute("checked"),
+    };
+    true
+}
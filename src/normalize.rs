@@ -0,0 +1,444 @@
+use crate::html::{parse_html, to_html, HtmlElement, HtmlNode};
+use crate::transaction::{flatten, rebuild, Item, Mark};
+
+/// Inline elements that only format their content, they are removed when they are empty
+const FORMATTING_ELEMENTS: [&str; 18] = [
+    "a", "b", "big", "code", "del", "em", "font", "i", "ins", "mark", "s", "small", "span",
+    "strike", "strong", "sub", "sup", "u",
+];
+
+/// The order of the marks without attributes, from the outermost. Marks with attributes
+/// are kept outside of them in their order, since the order of nested colors or links
+/// changes how the text looks
+const MARK_ORDER: [&str; 10] = [
+    "strong", "em", "u", "s", "sub", "sup", "code", "mark", "small", "big",
+];
+
+/// The font sizes of the `size` attribute of `<font>`, from 1
+const FONT_SIZES: [&str; 7] = [
+    "x-small",
+    "small",
+    "medium",
+    "large",
+    "x-large",
+    "xx-large",
+    "xxx-large",
+];
+
+/// The tag the browsers use for each formatting and the one that is kept
+fn canonical_tag(tag: &str) -> &str {
+    match tag {
+        "b" => "strong",
+        "i" => "em",
+        "strike" => "s",
+        tag => tag,
+    }
+}
+
+/// Parses the declarations of a `style` attribute, with lowercase property names
+pub(crate) fn style_declarations(style: &str) -> Vec<(String, String)> {
+    style
+        .split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .filter(|(name, value)| !name.is_empty() && !value.is_empty())
+        .collect()
+}
+
+/// Returns a color as `#rrggbb`, or as `rgba(r, g, b, a)` if it is transparent, so the
+/// colors set by different browsers are equal. Other colors, such as names, are lowercased
+fn normalize_color(value: &str) -> String {
+    let value = value.trim().to_lowercase();
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() == 3 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return hex.chars().fold("#".to_string(), |mut color, c| {
+                color.push(c);
+                color.push(c);
+                color
+            });
+        }
+        return value;
+    }
+    let arguments = value
+        .strip_prefix("rgba(")
+        .or(value.strip_prefix("rgb("))
+        .and_then(|rest| rest.strip_suffix(')'));
+    let Some(arguments) = arguments else {
+        return value;
+    };
+    let parts: Vec<&str> = arguments.split(',').map(str::trim).collect();
+    let channels: Vec<u8> = parts
+        .iter()
+        .take(3)
+        .filter_map(|c| c.parse().ok())
+        .collect();
+    let alpha = match parts.get(3) {
+        Some(alpha) => alpha.parse::<f64>().ok(),
+        None => Some(1.),
+    };
+    match (channels.as_slice(), alpha, parts.len()) {
+        ([r, g, b], Some(alpha), 3 | 4) if alpha >= 1. => format!("#{r:02x}{g:02x}{b:02x}"),
+        ([r, g, b], Some(alpha), 4) => format!("rgba({r}, {g}, {b}, {alpha})"),
+        _ => value,
+    }
+}
+
+/// Returns the declarations of the attributes of a `<font>`, `None` if it has other
+/// attributes or a size without a matching CSS size
+fn font_declarations(el: &HtmlElement) -> Option<Vec<(String, String)>> {
+    el.attributes
+        .iter()
+        .filter(|(name, _)| name != "style")
+        .map(|(name, value)| match name.as_str() {
+            "color" => Some(("color".to_string(), value.clone())),
+            "face" => Some(("font-family".to_string(), value.clone())),
+            "size" => {
+                // Relative sizes, such as `+1`, depend on the size of the parent
+                let size = value.trim();
+                if !size.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                let size = size.parse::<usize>().ok()?;
+                let size = FONT_SIZES.get(size.checked_sub(1)?)?;
+                Some(("font-size".to_string(), size.to_string()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Serializes the declarations the same way as the browsers
+pub(crate) fn style_attribute(declarations: &[(String, String)]) -> String {
+    declarations
+        .iter()
+        .map(|(name, value)| format!("{name}: {value};"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns the tag of the formatting that a declaration of a `<span>` stands for
+fn declaration_marks(name: &str, value: &str) -> Option<Vec<&'static str>> {
+    let value = value.to_lowercase();
+    let marks = match name {
+        "font-weight"
+            if matches!(
+                value.as_str(),
+                "bold" | "bolder" | "600" | "700" | "800" | "900"
+            ) =>
+        {
+            vec!["strong"]
+        }
+        "font-style" if value == "italic" => vec!["em"],
+        "text-decoration" | "text-decoration-line" => {
+            let words: Vec<&str> = value.split_whitespace().collect();
+            let marks: Vec<&str> = words
+                .iter()
+                .filter_map(|word| match *word {
+                    "underline" => Some("u"),
+                    "line-through" => Some("s"),
+                    _ => None,
+                })
+                .collect();
+            if marks.is_empty() || marks.len() != words.len() {
+                return None;
+            }
+            marks
+        }
+        "vertical-align" if value == "sub" => vec!["sub"],
+        "vertical-align" if value == "super" => vec!["sup"],
+        _ => return None,
+    };
+    Some(marks)
+}
+
+/// Normalizes the tag and the attributes of an element. A `<font>` becomes a `<span>` with
+/// the same style, and a `<span>` whose style only formats text is replaced by the elements
+/// of its formatting, which are returned from the outermost
+fn normalize_element(el: &mut HtmlElement) -> Vec<HtmlElement> {
+    el.tag = canonical_tag(&el.tag).to_string();
+    let mut font_style = vec![];
+    if el.tag == "font" {
+        if let Some(declarations) = font_declarations(el) {
+            el.tag = "span".to_string();
+            el.attributes.retain(|(name, _)| name == "style");
+            font_style = declarations;
+        }
+    }
+    let mut marks = vec![];
+    if let Some(style) = el
+        .attribute("style")
+        .or((!font_style.is_empty()).then_some(""))
+    {
+        let mut declarations = font_style;
+        // The later declarations of a property win
+        for (name, value) in style_declarations(style) {
+            declarations.retain(|(other, _)| *other != name);
+            declarations.push((name, value));
+        }
+        for (name, value) in &mut declarations {
+            if name.ends_with("color") {
+                *value = normalize_color(value);
+            }
+        }
+        declarations.sort();
+        if el.tag == "span" {
+            declarations.retain(|(name, value)| match declaration_marks(name, value) {
+                Some(tags) => {
+                    marks.extend(tags.into_iter().map(HtmlElement::new));
+                    false
+                }
+                None => true,
+            });
+        }
+        el.set_attribute("style", &style_attribute(&declarations));
+    }
+    el.attributes
+        .retain(|(name, value)| !(matches!(name.as_str(), "style" | "class") && value.is_empty()));
+    el.attributes.sort();
+    marks
+}
+
+fn is_empty_formatting(node: &HtmlNode) -> bool {
+    match node {
+        HtmlNode::Element(el) => {
+            el.children.is_empty() && FORMATTING_ELEMENTS.contains(&el.tag.as_str())
+        }
+        _ => false,
+    }
+}
+
+/// Position of the mark in `MARK_ORDER`, after the marks with attributes
+fn mark_rank(mark: &Mark) -> usize {
+    match mark.attributes.is_empty() {
+        true => MARK_ORDER
+            .iter()
+            .position(|tag| *tag == mark.tag)
+            .map_or(MARK_ORDER.len() + 1, |i| i + 1),
+        false => 0,
+    }
+}
+
+fn is_br(item: &Item) -> bool {
+    matches!(item, Item::Object(HtmlNode::Element(el)) if el.tag == "br")
+}
+
+/// Normalizes a node, a `<span>` without attributes is replaced by its children
+fn normalize_node(node: &HtmlNode) -> Vec<HtmlNode> {
+    let HtmlNode::Element(el) = node else {
+        return vec![node.clone()];
+    };
+    let mut el = el.clone();
+    let marks = normalize_element(&mut el);
+    // The text of code blocks is kept as it is
+    if el.tag != "pre" {
+        el.children = normalize_children(&el.children, el.is_block());
+    }
+    let nodes = match el.tag == "span" && el.attributes.is_empty() {
+        true => el.children,
+        false => vec![HtmlNode::Element(el)],
+    };
+    let nodes = match marks.split_last() {
+        Some((innermost, outer)) if !nodes.is_empty() => {
+            let mut innermost = innermost.clone();
+            innermost.children = nodes;
+            let node = outer
+                .iter()
+                .rev()
+                .fold(HtmlNode::Element(innermost), |node, mark| {
+                    let mut mark = mark.clone();
+                    mark.children = vec![node];
+                    HtmlNode::Element(mark)
+                });
+            vec![node]
+        }
+        _ => nodes,
+    };
+    nodes
+        .into_iter()
+        .filter(|node| !is_empty_formatting(node))
+        .collect()
+}
+
+/// Normalizes the children of an element. Adjacent elements with the same formatting are
+/// merged, and the `<br>` that the browsers add at the end of a block is removed
+fn normalize_children(children: &[HtmlNode], is_block: bool) -> Vec<HtmlNode> {
+    let children: Vec<HtmlNode> = children.iter().flat_map(normalize_node).collect();
+    let mut inlines = vec![];
+    if !flatten(&children, &mut vec![], &mut inlines) {
+        return children;
+    }
+    // A mark inside the same mark has no effect, and the same marks are nested the same way
+    for inline in &mut inlines {
+        let mut marks: Vec<Mark> = vec![];
+        for mark in inline.marks.drain(..) {
+            if !marks.contains(&mark) {
+                marks.push(mark);
+            }
+        }
+        marks.sort_by_key(mark_rank);
+        inline.marks = marks;
+    }
+    if is_block && inlines.len() > 1 {
+        let last = &inlines[inlines.len() - 1].item;
+        let previous = &inlines[inlines.len() - 2].item;
+        if is_br(last) && !is_br(previous) {
+            inlines.pop();
+        }
+    }
+    rebuild(&inlines)
+}
+
+/// Returns `true` if the nodes have text or inline elements to show
+fn has_content(nodes: &[HtmlNode]) -> bool {
+    nodes.iter().any(|node| match node {
+        HtmlNode::Element(_) => true,
+        HtmlNode::Text(text) => !text.trim().is_empty(),
+        HtmlNode::Comment(_) => false,
+    })
+}
+
+/// Returns the HTML in a canonical form, so the HTML produced by different browsers for the
+/// same content is equal:
+/// - `<b>`, `<i>` and `<strike>` become `<strong>`, `<em>` and `<s>`
+/// - `<font>` becomes a `<span>` with the same style, and colors are written as `#rrggbb`
+/// - a `<span>` whose style formats text becomes the elements of its formatting
+/// - nested marks are sorted in the same order
+/// - adjacent elements with the same formatting are merged and empty ones are removed
+/// - the `<br>` at the end of a block with content is removed
+/// - top level text is wrapped in `separator` blocks, and top level `<div>` and `<p>`
+///   blocks without nested blocks become `separator` blocks
+/// - attributes and style declarations are sorted and styles are serialized the same way
+pub fn normalize_html(html: &str, separator: &str) -> String {
+    let mut blocks: Vec<HtmlNode> = vec![];
+    let mut inline_run: Vec<HtmlNode> = vec![];
+
+    fn push_run(blocks: &mut Vec<HtmlNode>, run: &mut Vec<HtmlNode>, separator: &str) {
+        let children = normalize_children(run, true);
+        if has_content(&children) {
+            let mut block = HtmlElement::new(separator);
+            block.children = children;
+            blocks.push(HtmlNode::Element(block));
+        }
+        run.clear();
+    }
+
+    for node in parse_html(html) {
+        match node {
+            HtmlNode::Element(el) if el.is_block() => {
+                push_run(&mut blocks, &mut inline_run, separator);
+                let mut el = el;
+                normalize_element(&mut el);
+                if el.tag != "pre" {
+                    el.children = normalize_children(&el.children, true);
+                }
+                let is_paragraph = matches!(el.tag.as_str(), "div" | "p")
+                    && el.attribute("class").is_none()
+                    && !el
+                        .children
+                        .iter()
+                        .any(|child| matches!(child, HtmlNode::Element(child) if child.is_block()));
+                if is_paragraph {
+                    el.tag = separator.to_lowercase();
+                }
+                blocks.push(HtmlNode::Element(el));
+            }
+            node => inline_run.push(node),
+        }
+    }
+    push_run(&mut blocks, &mut inline_run, separator);
+    to_html(&blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(html: &str) -> String {
+        normalize_html(html, "p")
+    }
+
+    #[test]
+    fn uses_the_same_tags_for_the_same_formatting() {
+        assert_eq!(
+            normalize("<div><b>a</b><i>b</i><strike>c</strike></div>"),
+            "<p><strong>a</strong><em>b</em><s>c</s></p>"
+        );
+        assert_eq!(
+            normalize("<p><span style=\"font-weight: bold; font-style: italic\">a</span></p>"),
+            "<p><strong><em>a</em></strong></p>"
+        );
+    }
+
+    #[test]
+    fn nests_marks_in_the_same_order() {
+        let expected = "<p><strong><em>both</em></strong></p>";
+        assert_eq!(normalize("<p><i><b>both</b></i></p>"), expected);
+        assert_eq!(normalize("<p><b><i>both</i></b></p>"), expected);
+        assert_eq!(
+            normalize("<p><i><a href=\"u\"><b>link</b></a></i></p>"),
+            "<p><a href=\"u\"><strong><em>link</em></strong></a></p>"
+        );
+    }
+
+    #[test]
+    fn writes_font_elements_and_colors_the_same_way() {
+        let expected = "<p><span style=\"color: #ff0000;\">red</span></p>";
+        assert_eq!(
+            normalize("<p><font color=\"#FF0000\">red</font></p>"),
+            expected
+        );
+        assert_eq!(
+            normalize("<p><font color=\"#f00\">red</font></p>"),
+            expected
+        );
+        assert_eq!(
+            normalize("<p><span style=\"color: rgb(255, 0, 0);\">red</span></p>"),
+            expected
+        );
+        assert_eq!(
+            normalize("<p><font face=\"Arial\" size=\"5\" style=\"color: red\">a</font></p>"),
+            "<p><span style=\"color: red; font-family: Arial; font-size: x-large;\">a</span></p>"
+        );
+        assert_eq!(
+            normalize("<p><font size=\"+1\">a</font></p>"),
+            "<p><font size=\"+1\">a</font></p>"
+        );
+    }
+
+    #[test]
+    fn normalizes_color_values() {
+        assert_eq!(normalize_color("RGB(0, 128, 255)"), "#0080ff");
+        assert_eq!(normalize_color("rgba(0, 128, 255, 1)"), "#0080ff");
+        assert_eq!(
+            normalize_color("rgba(0,128,255,0.5)"),
+            "rgba(0, 128, 255, 0.5)"
+        );
+        assert_eq!(normalize_color("#ABC"), "#aabbcc");
+        assert_eq!(normalize_color("Red"), "red");
+        assert_eq!(normalize_color("rgb(300, 0, 0)"), "rgb(300, 0, 0)");
+    }
+
+    #[test]
+    fn merges_and_removes_redundant_elements() {
+        assert_eq!(
+            normalize("<p><b>a</b><strong>b</strong><i></i><span>c</span><br></p>"),
+            "<p><strong>ab</strong>c</p>"
+        );
+        assert_eq!(
+            normalize("<p><b>one <b>two</b></b></p>"),
+            "<p><strong>one two</strong></p>"
+        );
+    }
+
+    #[test]
+    fn wraps_top_level_content_in_separator_blocks() {
+        assert_eq!(
+            normalize_html("text<div>line</div><ul><li>item</li></ul>", "div"),
+            "<div>text</div><div>line</div><ul><li>item</li></ul>"
+        );
+        assert_eq!(
+            normalize("<pre><b>code</b>  </pre>"),
+            "<pre><b>code</b>  </pre>"
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use crate::collab::{CharId, Collaboration};
+use crate::util::{selection_text_offsets, text_position, text_units, TextChange};
+use leptos_dom::document;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use web_sys::{DomRect, Element, HtmlElement, Range};
+
+const CARET_WIDTH: f64 = 2.;
+const LABEL_HEIGHT: f64 = 16.;
+
+/// A selection of a participant. Its ends are positions of the collaboration document, so
+/// they stay next to the same text when anyone edits the content
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PresenceSelection {
+    pub anchor: Option<CharId>,
+    pub focus: Option<CharId>,
+}
+
+/// Another participant of a collaborative session
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemotePresence {
+    pub user_id: String,
+    pub name: String,
+    /// Any CSS color
+    pub color: String,
+    /// `None` when the participant isn't focused on the content
+    pub selection: Option<PresenceSelection>,
+}
+
+/// Returns the selection of the local participant in the content, to be sent to the other
+/// participants as the selection of their `RemotePresence`
+pub fn local_selection(
+    content_id: &str,
+    collaboration: &Collaboration,
+) -> Option<PresenceSelection> {
+    let root = document().get_element_by_id(content_id)?;
+    let (anchor, focus) = selection_text_offsets(&root)?;
+    let collab_document = collaboration.document.borrow();
+    let collab_document = collab_document.as_ref()?;
+    // The content can have edits that weren't recorded in the document yet
+    let change = TextChange::between(&text_units(&root), &collab_document.text_units());
+    Some(PresenceSelection {
+        anchor: collab_document.position_at(change.map(anchor)),
+        focus: collab_document.position_at(change.map(focus)),
+    })
+}
+
+/// Id of the element that holds the overlays, it is rendered with the content so they
+/// scroll with it
+pub(crate) fn presence_container_id(content_id: &str) -> String {
+    format!("{content_id}-presence")
+}
+
+/// Draws the carets and selections of the other participants over the content
+#[derive(Clone)]
+pub(crate) struct PresenceOverlay {
+    content_id: String,
+    collaboration: Option<Collaboration>,
+    presences: Rc<RefCell<Vec<RemotePresence>>>,
+}
+
+impl PresenceOverlay {
+    pub fn new(content_id: &str, collaboration: Option<Collaboration>) -> Self {
+        Self {
+            content_id: content_id.to_string(),
+            collaboration,
+            presences: Rc::new(RefCell::new(vec![])),
+        }
+    }
+
+    pub fn set(&self, presences: Vec<RemotePresence>) {
+        *self.presences.borrow_mut() = presences;
+        self.render();
+    }
+
+    /// Returns the text offsets of the ends of the selections in the content, in the order
+    /// of the participants
+    fn selection_offsets(&self, root: &Element) -> Vec<Option<(u32, u32)>> {
+        let presences = self.presences.borrow();
+        let Some(collaboration) = &self.collaboration else {
+            return vec![None; presences.len()];
+        };
+        let collab_document = collaboration.document.borrow();
+        let Some(collab_document) = collab_document.as_ref() else {
+            return vec![None; presences.len()];
+        };
+        // The content can have local edits that weren't recorded in the document yet
+        let change = TextChange::between(&collab_document.text_units(), &text_units(root));
+        presences
+            .iter()
+            .map(|presence| {
+                let selection = presence.selection?;
+                let anchor = collab_document.offset_of(selection.anchor)?;
+                let focus = collab_document.offset_of(selection.focus)?;
+                Some((change.map(anchor), change.map(focus)))
+            })
+            .collect()
+    }
+
+    /// Draws the overlays again, after the content changed or was scrolled
+    pub fn render(&self) {
+        let (Some(root), Some(container)) = (
+            document().get_element_by_id(&self.content_id),
+            document().get_element_by_id(&presence_container_id(&self.content_id)),
+        ) else {
+            return;
+        };
+        container.set_inner_html("");
+        let origin = container.get_bounding_client_rect();
+
+        let offsets = self.selection_offsets(&root);
+        for (presence, offsets) in self.presences.borrow().iter().zip(offsets) {
+            let Some((anchor, focus)) = offsets else {
+                continue;
+            };
+            let Some(range) = selection_range(&root, anchor, focus) else {
+                continue;
+            };
+
+            if !range.collapsed() {
+                if let Some(rects) = range.get_client_rects() {
+                    for i in 0..rects.length() {
+                        if let Some(rect) = rects.get(i) {
+                            let el = overlay_box(&container, &origin, &rect, rect.width());
+                            let _ = el.style().set_property("background-color", &presence.color);
+                            let _ = el.style().set_property("opacity", "0.25");
+                        }
+                    }
+                }
+            }
+
+            let Some(caret) = caret_rect(&root, focus) else {
+                continue;
+            };
+            let el = overlay_box(&container, &origin, &caret, CARET_WIDTH);
+            let _ = el.style().set_property("background-color", &presence.color);
+
+            let label = overlay_box(&container, &origin, &caret, 0.);
+            label.set_inner_text(&presence.name);
+            label.set_title(&presence.user_id);
+            let style = label.style();
+            let _ = style.set_property("width", "auto");
+            let _ = style.set_property("height", &format!("{LABEL_HEIGHT}px"));
+            let top = caret.top() - origin.top() - LABEL_HEIGHT;
+            let _ = style.set_property("top", &format!("{top}px"));
+            let _ = style.set_property("padding", "0 4px");
+            let _ = style.set_property("font-size", "12px");
+            let _ = style.set_property("line-height", &format!("{LABEL_HEIGHT}px"));
+            let _ = style.set_property("white-space", "nowrap");
+            let _ = style.set_property("color", "white");
+            let _ = style.set_property("background-color", &presence.color);
+        }
+    }
+}
+
+/// Returns a range over the text between the offsets, in document order
+fn selection_range(root: &Element, anchor: u32, focus: u32) -> Option<Range> {
+    let start = anchor.min(focus);
+    let end = anchor.max(focus);
+    let (start_node, start_offset) = text_position(root, start)?;
+    let (end_node, end_offset) = text_position(root, end)?;
+    let range = Range::new().ok()?;
+    range.set_start(&start_node, start_offset).ok()?;
+    range.set_end(&end_node, end_offset).ok()?;
+    Some(range)
+}
+
+/// Returns the rect of a caret at the given offset, collapsed ranges have no rect at the
+/// start of some blocks, so the rect of the closest element is used then
+fn caret_rect(root: &Element, offset: u32) -> Option<DomRect> {
+    let (node, node_offset) = text_position(root, offset)?;
+    let range = Range::new().ok()?;
+    range.set_start(&node, node_offset).ok()?;
+    range.collapse_with_to_start(true);
+    if let Some(rect) = range.get_client_rects().and_then(|rects| rects.get(0)) {
+        return Some(rect);
+    }
+    let el = match node.dyn_ref::<Element>() {
+        Some(el) => el.clone(),
+        None => node.parent_element()?,
+    };
+    let rect = el.get_bounding_client_rect();
+    DomRect::new_with_x_and_y_and_width_and_height(rect.left(), rect.top(), 0., rect.height()).ok()
+}
+
+/// Adds a box at the rect of the viewport, positioned inside the container whose rect is
+/// `origin`
+fn overlay_box(container: &Element, origin: &DomRect, rect: &DomRect, width: f64) -> HtmlElement {
+    let el = document()
+        .create_element("div")
+        .unwrap()
+        .unchecked_into::<HtmlElement>();
+    let style = el.style();
+    let _ = style.set_property("position", "absolute");
+    let _ = style.set_property("pointer-events", "none");
+    let _ = style.set_property("top", &format!("{}px", rect.top() - origin.top()));
+    let _ = style.set_property("left", &format!("{}px", rect.left() - origin.left()));
+    let _ = style.set_property("width", &format!("{width}px"));
+    let _ = style.set_property("height", &format!("{}px", rect.height()));
+    container.append_child(&el).unwrap();
+    el
+}
@@ -0,0 +1,499 @@
+use crate::action::Actions;
+use crate::comment::COMMENT_ATTRIBUTE;
+use crate::embed::{sanitize_embeds, EmbedProvider};
+use crate::html::{parse_html, to_html, HtmlElement, HtmlNode};
+use crate::normalize::{style_attribute, style_declarations};
+use crate::util::{exec, restore_selection, save_selection};
+use lazy_static::lazy_static;
+use leptos_dom::document;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Mutex;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{InputEvent, Node, Range};
+
+/// Elements removed with their content, which isn't text of the document
+const REMOVED_WITH_CONTENT: [&str; 17] = [
+    "audio", "button", "canvas", "head", "iframe", "link", "meta", "noscript", "object", "script",
+    "select", "style", "svg", "template", "textarea", "title", "video",
+];
+
+/// Elements of the pasted HTML of some browsers that aren't content, they aren't reported
+const DOCUMENT_ELEMENTS: [&str; 5] = ["body", "head", "html", "meta", "title"];
+
+/// What an editor allows in its content
+#[derive(Clone)]
+struct ContentRules {
+    schema: Option<Schema>,
+    separator: String,
+    embed_providers: Vec<EmbedProvider>,
+}
+
+lazy_static! {
+    /// Rules of the content of the mounted editors, by content id
+    static ref CONTENT_RULES: Mutex<HashMap<String, ContentRules>> = Mutex::new(HashMap::new());
+}
+
+/// Receives what was removed from content that didn't fit the schema of the editor
+pub type StrippedCallback = Rc<dyn Fn(StrippedContent)>;
+
+/// The content allowed in an editor. Elements that aren't allowed are removed keeping their
+/// text, blocks become paragraphs when they are allowed
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schema {
+    pub blocks: Vec<String>,
+    /// Inline elements, including the ones without text such as `<img>` or `<br>`
+    pub marks: Vec<String>,
+    /// Pairs of an element, `*` for all of them, and the name of an attribute it can have
+    pub attributes: Vec<(String, String)>,
+    /// Pairs of an element and a property its `style` can have, when the whole `style`
+    /// attribute isn't allowed
+    pub styles: Vec<(String, String)>,
+}
+
+/// What was removed from content that didn't fit a schema
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StrippedContent {
+    /// Tags of the removed elements
+    pub elements: Vec<String>,
+    /// Pairs of an element and the name of a removed attribute
+    pub attributes: Vec<(String, String)>,
+}
+
+impl StrippedContent {
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty() && self.attributes.is_empty()
+    }
+
+    fn element(&mut self, tag: &str) {
+        if !self.elements.iter().any(|t| t == tag) {
+            self.elements.push(tag.to_string());
+        }
+    }
+
+    fn attribute(&mut self, tag: &str, name: &str) {
+        if !self.attributes.iter().any(|(t, n)| t == tag && n == name) {
+            self.attributes.push((tag.to_string(), name.to_string()));
+        }
+    }
+}
+
+impl Schema {
+    /// A schema that only allows text
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The schema of the content created by the actions, see `Action::schema`, with
+    /// paragraphs, line breaks and comments, which are always allowed
+    pub fn from_actions(actions: &Actions) -> Self {
+        let mut schema = Self::new();
+        schema
+            .allow_blocks(&["div", "p"])
+            .allow_marks(&["br", "span"])
+            .allow_attributes("span", &["class", COMMENT_ATTRIBUTE]);
+        for action_schema in actions.inner().iter().filter_map(|a| a.schema.as_ref()) {
+            schema.allow_schema(action_schema);
+        }
+        schema
+    }
+
+    pub fn allow_blocks(&mut self, tags: &[&str]) -> &mut Self {
+        for tag in tags {
+            if !self.allows_block(tag) {
+                self.blocks.push(tag.to_string());
+            }
+        }
+        self
+    }
+
+    pub fn allow_marks(&mut self, tags: &[&str]) -> &mut Self {
+        for tag in tags {
+            if !self.allows_mark(tag) {
+                self.marks.push(tag.to_string());
+            }
+        }
+        self
+    }
+
+    /// Allows the attributes on the `tag` elements, or on every element if it is `*`
+    pub fn allow_attributes(&mut self, tag: &str, names: &[&str]) -> &mut Self {
+        for name in names {
+            if !self.attributes.iter().any(|(t, n)| t == tag && n == name) {
+                self.attributes.push((tag.to_string(), name.to_string()));
+            }
+        }
+        self
+    }
+
+    /// Allows a `style` on the `tag` elements that only keeps the given properties
+    pub fn allow_styles(&mut self, tag: &str, properties: &[&str]) -> &mut Self {
+        for property in properties {
+            if !self.allows_style(tag, property) {
+                self.styles.push((tag.to_string(), property.to_string()));
+            }
+        }
+        self
+    }
+
+    /// Allows everything that the other schema allows
+    pub fn allow_schema(&mut self, other: &Schema) -> &mut Self {
+        for tag in &other.blocks {
+            self.allow_blocks(&[tag]);
+        }
+        for tag in &other.marks {
+            self.allow_marks(&[tag]);
+        }
+        for (tag, name) in &other.attributes {
+            self.allow_attributes(tag, &[name]);
+        }
+        for (tag, property) in &other.styles {
+            self.allow_styles(tag, &[property]);
+        }
+        self
+    }
+
+    pub fn allows_block(&self, tag: &str) -> bool {
+        self.blocks.iter().any(|t| t == tag)
+    }
+
+    pub fn allows_mark(&self, tag: &str) -> bool {
+        self.marks.iter().any(|t| t == tag)
+    }
+
+    pub fn allows_attribute(&self, tag: &str, name: &str) -> bool {
+        self.attributes
+            .iter()
+            .any(|(t, n)| (t == tag || t == "*") && n == name)
+    }
+
+    pub fn allows_style(&self, tag: &str, property: &str) -> bool {
+        self.styles.iter().any(|(t, p)| t == tag && p == property)
+    }
+
+    /// Returns the declarations of the `style` that are allowed on the `tag` elements, and
+    /// whether some of them weren't
+    fn enforce_style(&self, tag: &str, style: &str) -> (Vec<(String, String)>, bool) {
+        let (allowed, removed): (Vec<_>, Vec<_>) = style_declarations(style)
+            .into_iter()
+            .partition(|(property, _)| self.allows_style(tag, property));
+        (allowed, !removed.is_empty())
+    }
+
+    fn allows_element(&self, el: &HtmlElement) -> bool {
+        match el.is_block() {
+            true => self.allows_block(&el.tag),
+            false => self.allows_mark(&el.tag),
+        }
+    }
+
+    /// Removes from the HTML what the schema doesn't allow, and returns what was removed.
+    /// The blocks that aren't allowed become `separator` blocks if it is allowed, otherwise
+    /// their text is separated by line breaks or spaces
+    pub fn enforce(&self, html: &str, separator: &str) -> (String, StrippedContent) {
+        let mut stripped = StrippedContent::default();
+        let nodes = self.enforce_nodes(parse_html(html), separator, &mut stripped);
+        (to_html(&nodes), stripped)
+    }
+
+    fn enforce_nodes(
+        &self,
+        nodes: Vec<HtmlNode>,
+        separator: &str,
+        stripped: &mut StrippedContent,
+    ) -> Vec<HtmlNode> {
+        let mut enforced: Vec<HtmlNode> = vec![];
+        for node in nodes {
+            let mut el = match node {
+                HtmlNode::Element(el) => el,
+                HtmlNode::Text(text) => {
+                    enforced.push(HtmlNode::Text(text));
+                    continue;
+                }
+                HtmlNode::Comment(_) => continue,
+            };
+            let had_children = !el.children.is_empty();
+            let children =
+                self.enforce_nodes(std::mem::take(&mut el.children), separator, stripped);
+            let emptied = had_children && children.is_empty();
+
+            if self.allows_element(&el) {
+                let tag = el.tag.clone();
+                el.attributes.retain_mut(|(name, value)| {
+                    if self.allows_attribute(&tag, name) {
+                        return true;
+                    }
+                    let (declarations, removed) = match name.as_str() {
+                        "style" => self.enforce_style(&tag, value),
+                        _ => (vec![], true),
+                    };
+                    if removed {
+                        stripped.attribute(&tag, name);
+                        *value = style_attribute(&declarations);
+                    }
+                    !declarations.is_empty()
+                });
+                el.children = children;
+                if !emptied {
+                    enforced.push(HtmlNode::Element(el));
+                }
+                continue;
+            }
+
+            if !DOCUMENT_ELEMENTS.contains(&el.tag.as_str()) {
+                stripped.element(&el.tag);
+            }
+            if REMOVED_WITH_CONTENT.contains(&el.tag.as_str()) || children.is_empty() {
+                continue;
+            }
+            let has_blocks = children
+                .iter()
+                .any(|child| matches!(child, HtmlNode::Element(child) if child.is_block()));
+            if !el.is_block() || has_blocks {
+                enforced.extend(children);
+            } else if self.allows_block(separator) {
+                let mut block = HtmlElement::new(separator);
+                block.children = children;
+                enforced.push(HtmlNode::Element(block));
+            } else {
+                // The text of the block is kept on its own line
+                if !enforced.is_empty() {
+                    enforced.push(match self.allows_mark("br") {
+                        true => HtmlNode::Element(HtmlElement::new("br")),
+                        false => HtmlNode::Text(" ".to_string()),
+                    });
+                }
+                enforced.extend(children);
+            }
+        }
+        enforced
+    }
+}
+
+/// Enforces the schema of the editor, if it has one, and its embed providers on the
+/// content inserted with `insert_html`
+pub(crate) fn register_content_rules(
+    content_id: &str,
+    schema: Option<&Schema>,
+    separator: &str,
+    embed_providers: &[EmbedProvider],
+) {
+    CONTENT_RULES.lock().unwrap().insert(
+        content_id.to_string(),
+        ContentRules {
+            schema: schema.cloned(),
+            separator: separator.to_string(),
+            embed_providers: embed_providers.to_vec(),
+        },
+    );
+}
+
+/// Forgets the rules of an editor that was unmounted
+pub(crate) fn unregister_content_rules(content_id: &str) {
+    CONTENT_RULES.lock().unwrap().remove(content_id);
+}
+
+/// Enforces the schema of an editor, if it has one, on content loaded in it and reports
+/// what was removed. Returns `None` if nothing was removed
+pub(crate) fn enforce_loaded_content(
+    schema: Option<&Schema>,
+    html: &str,
+    separator: &str,
+    on_stripped: Option<&StrippedCallback>,
+) -> Option<String> {
+    let (enforced, stripped) = schema?.enforce(html, separator);
+    if stripped.is_empty() {
+        return None;
+    }
+    if let Some(on_stripped) = on_stripped {
+        on_stripped(stripped);
+    }
+    Some(enforced)
+}
+
+/// Inserts HTML at the caret, or at the end of the content if the caret is outside of it.
+/// The HTML is stripped of what the schema of the editor doesn't allow, which is returned,
+/// and of the embeds of other providers than the ones of the editor
+pub fn insert_html(content_id: &str, html: &str) -> Result<StrippedContent, JsValue> {
+    let root = document()
+        .get_element_by_id(content_id)
+        .ok_or("the content doesn't exist")?;
+    let rules = CONTENT_RULES
+        .lock()
+        .unwrap()
+        .get(content_id)
+        .cloned()
+        .ok_or("the editor isn't mounted")?;
+    let (html, stripped) = match &rules.schema {
+        Some(schema) => schema.enforce(html, &rules.separator),
+        None => (html.to_string(), StrippedContent::default()),
+    };
+
+    let in_content = save_selection()
+        .and_then(|range| range.common_ancestor_container().ok())
+        .map(|node| root.contains(Some(&node)))
+        .unwrap_or(false);
+    if !in_content {
+        let range = Range::new()?;
+        range.select_node_contents(&root)?;
+        range.collapse_with_to_start(false);
+        restore_selection(&range);
+    }
+    exec(
+        "insertHTML",
+        &sanitize_embeds(&html, &rules.embed_providers),
+    )?;
+    Ok(stripped)
+}
+
+/// Returns the range where the input will be applied, such as the place of a drop
+fn target_range(e: &InputEvent) -> Option<Range> {
+    let target = e.get_target_ranges().get(0);
+    if target.is_undefined() {
+        return None;
+    }
+    let get = |name: &str| js_sys::Reflect::get(&target, &name.into()).ok();
+    let range = Range::new().ok()?;
+    let start = get("startContainer")?.dyn_into::<Node>().ok()?;
+    let end = get("endContainer")?.dyn_into::<Node>().ok()?;
+    range
+        .set_start(&start, get("startOffset")?.as_f64()? as u32)
+        .ok()?;
+    range
+        .set_end(&end, get("endOffset")?.as_f64()? as u32)
+        .ok()?;
+    Some(range)
+}
+
+/// Strips the pasted and dropped HTML of what the schema doesn't allow before inserting it,
+/// and keeps Enter from creating blocks when paragraphs aren't allowed. Returns what was
+/// removed if the event was handled
+pub fn handle_schema_beforeinput(
+    e: &InputEvent,
+    schema: &Schema,
+    separator: &str,
+    providers: &[EmbedProvider],
+) -> Option<StrippedContent> {
+    let input_type = e.input_type();
+    if input_type == "insertParagraph" && !schema.allows_block(separator) {
+        e.prevent_default();
+        return Some(StrippedContent::default());
+    }
+    if input_type != "insertFromPaste" && input_type != "insertFromDrop" {
+        return None;
+    }
+    let html = e
+        .data_transfer()
+        .and_then(|data| data.get_data("text/html").ok())
+        .filter(|html| !html.is_empty())?;
+    let (html, stripped) = schema.enforce(&html, separator);
+    e.prevent_default();
+    if input_type == "insertFromDrop" {
+        if let Some(range) = target_range(e) {
+            restore_selection(&range);
+        }
+    }
+    let _ = exec("insertHTML", &sanitize_embeds(&html, providers));
+    Some(stripped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        let mut schema = Schema::new();
+        schema
+            .allow_blocks(&["p", "ul", "li"])
+            .allow_marks(&["strong", "a", "br"])
+            .allow_attributes("a", &["href"])
+            .allow_attributes("*", &["dir"]);
+        schema
+    }
+
+    #[test]
+    fn keeps_allowed_content() {
+        let html =
+            "<p dir=\"rtl\"><strong>a</strong><a href=\"u\">b</a><br></p><ul><li>c</li></ul>";
+        assert_eq!(
+            schema().enforce(html, "p"),
+            (html.to_string(), StrippedContent::default())
+        );
+    }
+
+    #[test]
+    fn removes_elements_keeping_their_text() {
+        let (html, stripped) = schema().enforce(
+            "<p><em>a</em><a href=\"u\" onclick=\"x()\">b</a></p>\
+             <h1>title</h1><blockquote><p>quote</p></blockquote>",
+            "p",
+        );
+        assert_eq!(html, "<p>a<a href=\"u\">b</a></p><p>title</p><p>quote</p>");
+        assert_eq!(
+            stripped,
+            StrippedContent {
+                elements: vec!["em".to_string(), "h1".to_string(), "blockquote".to_string()],
+                attributes: vec![("a".to_string(), "onclick".to_string())],
+            }
+        );
+    }
+
+    #[test]
+    fn keeps_only_the_allowed_properties_of_styles() {
+        let mut aligned = schema();
+        aligned.allow_styles("p", &["text-align"]);
+        assert_eq!(
+            aligned.enforce("<p style=\"text-align: center;\">a</p>", "p"),
+            (
+                "<p style=\"text-align: center;\">a</p>".to_string(),
+                StrippedContent::default()
+            )
+        );
+
+        let (html, stripped) = aligned.enforce(
+            "<p style=\"position: fixed; TEXT-ALIGN: right\">a</p>\
+             <li style=\"text-align: right;\">b</li>",
+            "p",
+        );
+        assert_eq!(html, "<p style=\"text-align: right;\">a</p><li>b</li>");
+        assert_eq!(
+            stripped.attributes,
+            vec![
+                ("p".to_string(), "style".to_string()),
+                ("li".to_string(), "style".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn removes_the_content_of_scripts_and_documents_silently() {
+        let (html, stripped) = schema().enforce(
+            "<html><head><title>t</title></head><body><p>text<script>x()</script>\
+             <!-- note --></p></body></html>",
+            "p",
+        );
+        assert_eq!(html, "<p>text</p>");
+        assert_eq!(stripped.elements, vec!["script".to_string()]);
+    }
+
+    #[test]
+    fn separates_blocks_without_paragraphs() {
+        let mut text_only = Schema::new();
+        assert_eq!(text_only.enforce("<p>one</p><p>two</p>", "p").0, "one two");
+        text_only.allow_marks(&["br"]);
+        assert_eq!(
+            text_only.enforce("<p>one</p><p>two</p>", "p").0,
+            "one<br>two"
+        );
+    }
+
+    #[test]
+    fn merges_schemas() {
+        let mut merged = Schema::new();
+        merged.allow_marks(&["strong"]).allow_schema(&schema());
+        assert_eq!(merged.marks, vec!["strong", "a", "br"]);
+        assert!(merged.allows_block("li"));
+        assert!(merged.allows_attribute("strong", "dir"));
+        assert!(!merged.allows_attribute("strong", "href"));
+    }
+}
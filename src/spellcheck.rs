@@ -0,0 +1,303 @@
+use crate::find::{collect_search_text, range_text};
+use crate::util::{dropdown_menu_at, exec, restore_selection, DropdownItem};
+use leptos_dom::document;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range as TextRange;
+use std::rc::Rc;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Element, HtmlElement, MouseEvent, Range};
+
+const SUGGESTIONS_WIDTH: f64 = 160.;
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Finds the misspelled words of a run of text, each block of the content is checked
+/// as a separate run
+pub trait SpellChecker {
+    /// Returns the byte ranges of the misspelled words of the text
+    fn check(&self, text: &str) -> Vec<TextRange<usize>>;
+
+    /// Returns the replacements offered for a misspelled word
+    fn suggestions(&self, _word: &str) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// A spell checker that can be stored in the editor options
+#[derive(Clone)]
+pub struct SharedSpellChecker(pub Rc<dyn SpellChecker>);
+
+impl SharedSpellChecker {
+    pub fn new(checker: impl SpellChecker + 'static) -> Self {
+        Self(Rc::new(checker))
+    }
+}
+
+impl fmt::Debug for SharedSpellChecker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedSpellChecker")
+    }
+}
+
+impl PartialEq for SharedSpellChecker {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// A spell checker that flags every word that isn't in its dictionary and suggests the
+/// closest words of it
+pub struct DictionarySpellChecker {
+    words: HashSet<String>,
+}
+
+impl DictionarySpellChecker {
+    pub fn new(words: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        Self {
+            words: words
+                .into_iter()
+                .map(|word| word.as_ref().to_lowercase())
+                .collect(),
+        }
+    }
+}
+
+impl SpellChecker for DictionarySpellChecker {
+    fn check(&self, text: &str) -> Vec<TextRange<usize>> {
+        words(text)
+            .filter(|range| !self.words.contains(&text[range.clone()].to_lowercase()))
+            .collect()
+    }
+
+    fn suggestions(&self, word: &str) -> Vec<String> {
+        let lowercase = word.to_lowercase();
+        let mut candidates: Vec<(usize, &String)> = self
+            .words
+            .iter()
+            .map(|candidate| (edit_distance(&lowercase, candidate), candidate))
+            .filter(|(distance, _)| *distance <= 2)
+            .collect();
+        candidates.sort();
+
+        let capitalized = word.chars().next().map(char::is_uppercase).unwrap_or(false);
+        candidates
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, candidate)| match capitalized {
+                true => capitalize(candidate),
+                false => candidate.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Returns the byte ranges of the words of the text, words with digits are skipped
+pub fn words(text: &str) -> impl Iterator<Item = TextRange<usize>> + '_ {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '\'' || c == '’';
+    let mut start = None;
+    text.char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+        .filter_map(move |(i, c)| match (is_word_char(c), start) {
+            (true, None) => {
+                start = Some(i);
+                None
+            }
+            (false, Some(s)) => {
+                start = None;
+                let word = text[s..i].trim_matches(|c| c == '\'' || c == '’');
+                let offset = text[s..i].find(word).unwrap_or(0);
+                Some(s + offset..s + offset + word.len())
+            }
+            _ => None,
+        })
+        .filter(move |range| {
+            !range.is_empty() && !text[range.clone()].chars().any(|c| c.is_ascii_digit())
+        })
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current.push(
+                (previous[j] + cost)
+                    .min(previous[j + 1] + 1)
+                    .min(current[j] + 1),
+            );
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+/// Returns the ranges of the misspelled words of the content
+fn find_misspellings(root: &Element, checker: &dyn SpellChecker) -> Vec<Range> {
+    let search = collect_search_text(root);
+    let mut misspellings = vec![];
+    // Offset of the current run, in UTF-16 units
+    let mut offset = 0;
+    for run in search.text.split('\n') {
+        for misspelling in checker.check(run) {
+            // The ranges of the checker aren't trusted, invalid ones are skipped
+            let (Some(before_start), Some(before_end)) =
+                (run.get(..misspelling.start), run.get(..misspelling.end))
+            else {
+                continue;
+            };
+            if misspelling.start >= misspelling.end {
+                continue;
+            }
+            let start = offset + utf16_len(before_start);
+            let end = offset + utf16_len(before_end);
+            if let Some(range) = search.range(start, end) {
+                misspellings.push(range);
+            }
+        }
+        offset += utf16_len(run) + 1;
+    }
+    misspellings
+}
+
+/// Id of the element that holds the underlines, it is rendered with the content so they
+/// scroll with it
+pub(crate) fn spelling_container_id(content_id: &str) -> String {
+    format!("{content_id}-spelling")
+}
+
+fn suggestions_key(content_id: &str) -> String {
+    format!("{content_id}-spelling-suggestions")
+}
+
+/// Checks the content and underlines its misspelled words, called whenever the content
+/// changes, is scrolled or resized. The underlines are drawn over the content and clipped
+/// to its box, so the content itself isn't modified
+pub fn refresh_spelling(content_id: &str, checker: &dyn SpellChecker) {
+    let (Some(root), Some(container)) = (
+        document().get_element_by_id(content_id),
+        document().get_element_by_id(&spelling_container_id(content_id)),
+    ) else {
+        return;
+    };
+    container.set_inner_html("");
+    let origin = container.get_bounding_client_rect();
+    let content = root.get_bounding_client_rect();
+    let clip_top = content.top() + root.client_top() as f64;
+    let clip_left = content.left() + root.client_left() as f64;
+
+    let clip = document()
+        .create_element("div")
+        .unwrap()
+        .unchecked_into::<HtmlElement>();
+    let style = clip.style();
+    let _ = style.set_property("position", "absolute");
+    let _ = style.set_property("overflow", "hidden");
+    let _ = style.set_property("top", &format!("{}px", clip_top - origin.top()));
+    let _ = style.set_property("left", &format!("{}px", clip_left - origin.left()));
+    let _ = style.set_property("width", &format!("{}px", root.client_width()));
+    let _ = style.set_property("height", &format!("{}px", root.client_height()));
+    container.append_child(&clip).unwrap();
+
+    for range in find_misspellings(&root, checker) {
+        let Some(rects) = range.get_client_rects() else {
+            continue;
+        };
+        for i in 0..rects.length() {
+            let Some(rect) = rects.get(i) else {
+                continue;
+            };
+            let el = document()
+                .create_element("div")
+                .unwrap()
+                .unchecked_into::<HtmlElement>();
+            let style = el.style();
+            let _ = style.set_property("position", "absolute");
+            let _ = style.set_property("top", &format!("{}px", rect.bottom() - clip_top - 2.));
+            let _ = style.set_property("left", &format!("{}px", rect.left() - clip_left));
+            let _ = style.set_property("width", &format!("{}px", rect.width()));
+            let _ = style.set_property("border-bottom", "2px dotted red");
+            clip.append_child(&el).unwrap();
+        }
+    }
+}
+
+/// Shows the suggestions for the misspelled word under the pointer instead of the browser
+/// context menu. Returns `true` if the event was handled
+pub fn handle_spelling_context_menu(
+    content_id: &str,
+    e: &MouseEvent,
+    checker: &dyn SpellChecker,
+) -> bool {
+    let Some(root) = document().get_element_by_id(content_id) else {
+        return false;
+    };
+    let (x, y) = (e.client_x() as f64, e.client_y() as f64);
+    let misspelling = find_misspellings(&root, checker).into_iter().find(|range| {
+        let Some(rects) = range.get_client_rects() else {
+            return false;
+        };
+        (0..rects.length())
+            .filter_map(|i| rects.get(i))
+            .any(|r| x >= r.left() && x <= r.right() && y >= r.top() && y <= r.bottom())
+    });
+    let Some(range) = misspelling else {
+        return false;
+    };
+    e.prevent_default();
+
+    let word = range_text(&range);
+    let suggestions = checker.suggestions(&word);
+    let items = match suggestions.is_empty() {
+        true => vec![DropdownItem {
+            label: format!("No suggestions for \"{word}\""),
+            class: None,
+        }],
+        false => suggestions
+            .iter()
+            .map(|suggestion| DropdownItem {
+                label: suggestion.clone(),
+                class: None,
+            })
+            .collect(),
+    };
+    let content_id = content_id.to_string();
+    dropdown_menu_at(
+        &suggestions_key(&content_id),
+        e.page_y() as f64,
+        e.page_x() as f64,
+        SUGGESTIONS_WIDTH,
+        items,
+        Rc::new(move |index| {
+            if let Some(suggestion) = suggestions.get(index) {
+                let _ = replace_misspelling(&content_id, &range, suggestion);
+            }
+        }),
+    );
+    true
+}
+
+/// Replaces the misspelled word as a single undoable edit
+fn replace_misspelling(content_id: &str, range: &Range, suggestion: &str) -> Result<(), JsValue> {
+    let content = document()
+        .get_element_by_id(content_id)
+        .ok_or("couldn't find the content div")?
+        .unchecked_into::<HtmlElement>();
+    content.focus()?;
+    restore_selection(range);
+    exec("insertText", suggestion)?;
+    Ok(())
+}
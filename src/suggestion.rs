@@ -0,0 +1,363 @@
+use crate::util::{
+    add_style, dispatch_input_event, insert_typed_text, restore_selection, save_selection,
+    split_range_text, unwrap_element,
+};
+use lazy_static::lazy_static;
+use leptos_dom::document;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Element, InputEvent, Node, Range, Text};
+
+/// Elements that mark the suggested changes
+const CHANGE_SELECTOR: &str = "ins, del";
+/// Attribute with the author of a suggested change, its time is in the `datetime` attribute
+pub const AUTHOR_ATTRIBUTE: &str = "data-author";
+/// Class of the content while it shows the result of accepting every change
+pub const FINAL_VIEW_CLASS: &str = "papelito-final-view";
+const FINAL_VIEW_STYLE_ID: &str = "papelito-final-view-style";
+
+lazy_static! {
+    /// Ids of the contents whose edits are suggested instead of applied
+    static ref SUGGESTING: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+pub fn is_suggestion_mode(content_id: &str) -> bool {
+    SUGGESTING.lock().unwrap().contains(content_id)
+}
+
+pub fn set_suggestion_mode(content_id: &str, enabled: bool) {
+    let mut suggesting = SUGGESTING.lock().unwrap();
+    match enabled {
+        true => suggesting.insert(content_id.to_string()),
+        false => suggesting.remove(content_id),
+    };
+}
+
+/// Toggles the suggestion mode, returns whether it is enabled
+pub fn toggle_suggestion_mode(content_id: &str) -> bool {
+    let enabled = !is_suggestion_mode(content_id);
+    set_suggestion_mode(content_id, enabled);
+    enabled
+}
+
+/// Whether the content hides the deletions and shows the insertions as regular text
+pub fn is_final_view(content_id: &str) -> bool {
+    document()
+        .get_element_by_id(content_id)
+        .map(|content| content.class_list().contains(FINAL_VIEW_CLASS))
+        .unwrap_or(false)
+}
+
+/// Switches between the marked up content and the content with every change accepted,
+/// returns whether the final view is shown
+pub fn toggle_final_view(content_id: &str) -> Result<bool, JsValue> {
+    let content = document()
+        .get_element_by_id(content_id)
+        .ok_or("the content doesn't exist")?;
+    add_style(
+        FINAL_VIEW_STYLE_ID,
+        &format!(
+            ".{FINAL_VIEW_CLASS} del {{ display: none; }} \
+             .{FINAL_VIEW_CLASS} ins {{ text-decoration: none; }}"
+        ),
+    )?;
+    content.class_list().toggle(FINAL_VIEW_CLASS)
+}
+
+/// Applies an edit of the content as a suggestion when the suggestion mode is enabled:
+/// typed and pasted text is wrapped in `<ins>` and deleted text in `<del>`, both marked with
+/// the author and the time of the change. Deleting text of an own insertion removes it.
+/// Returns `true` if the event was handled, the other edits, such as new paragraphs, are
+/// applied as usual
+pub fn handle_suggestion_beforeinput(content_id: &str, e: &InputEvent, author: &str) -> bool {
+    if !is_suggestion_mode(content_id) || e.is_composing() {
+        return false;
+    }
+    let Some(root) = document().get_element_by_id(content_id) else {
+        return false;
+    };
+    let Some(range) = save_selection() else {
+        return false;
+    };
+    if !root.contains(Some(&range.common_ancestor_container().unwrap())) {
+        return false;
+    }
+
+    let input_type = e.input_type();
+    let handled = match input_type.as_str() {
+        "insertText" | "insertFromPaste" => {
+            let text = e.data().or_else(|| {
+                e.data_transfer()
+                    .and_then(|data| data.get_data("text/plain").ok())
+            });
+            match text {
+                Some(text) => suggest_insertion(&root, &range, &text, author).is_ok(),
+                None => false,
+            }
+        }
+        "deleteByCut" => suggest_deletion(&root, &range, author, false).is_ok(),
+        _ => match deletion_extent(&input_type) {
+            Some((direction, granularity)) => {
+                if range.collapsed() {
+                    extend_selection(direction, granularity);
+                }
+                match save_selection() {
+                    Some(range) => {
+                        suggest_deletion(&root, &range, author, direction == "backward").is_ok()
+                    }
+                    None => false,
+                }
+            }
+            None => false,
+        },
+    };
+    if handled {
+        e.prevent_default();
+        dispatch_input_event(content_id);
+    }
+    handled
+}
+
+/// Returns the direction and the granularity of a deletion input
+fn deletion_extent(input_type: &str) -> Option<(&'static str, &'static str)> {
+    match input_type {
+        "deleteContentBackward" => Some(("backward", "character")),
+        "deleteContentForward" => Some(("forward", "character")),
+        "deleteWordBackward" => Some(("backward", "word")),
+        "deleteWordForward" => Some(("forward", "word")),
+        "deleteSoftLineBackward" | "deleteHardLineBackward" => Some(("backward", "lineboundary")),
+        "deleteSoftLineForward" | "deleteHardLineForward" => Some(("forward", "lineboundary")),
+        _ => None,
+    }
+}
+
+fn extend_selection(direction: &str, granularity: &str) {
+    if let Ok(Some(selection)) = document().get_selection() {
+        let _ = selection.modify("extend", direction, granularity);
+    }
+}
+
+fn change_element(tag: &str, author: &str) -> Result<Element, JsValue> {
+    let el = document().create_element(tag)?;
+    el.set_attribute(AUTHOR_ATTRIBUTE, author)?;
+    let now = js_sys::Date::new_0().to_iso_string();
+    el.set_attribute("datetime", &String::from(now))?;
+    Ok(el)
+}
+
+/// Returns the closest `tag` element that contains the node, inside the root
+fn closest_in(root: &Element, node: &Node, tag: &str) -> Option<Element> {
+    let el = match node.dyn_ref::<Element>() {
+        Some(el) => el.clone(),
+        None => node.parent_element()?,
+    };
+    el.closest(tag)
+        .ok()
+        .flatten()
+        .filter(|el| root.contains(Some(el)))
+}
+
+fn is_by(el: &Element, author: &str) -> bool {
+    el.get_attribute(AUTHOR_ATTRIBUTE).as_deref() == Some(author)
+}
+
+/// Returns whether the node is a change element of the same kind and author as `el`
+fn is_same_change(node: &Node, el: &Element) -> bool {
+    match node.dyn_ref::<Element>() {
+        Some(other) => {
+            other.tag_name() == el.tag_name()
+                && other.get_attribute(AUTHOR_ATTRIBUTE) == el.get_attribute(AUTHOR_ATTRIBUTE)
+        }
+        None => false,
+    }
+}
+
+/// Marks the text of the range as deleted and collapses the selection before or after it
+fn suggest_deletion(
+    root: &Element,
+    range: &Range,
+    author: &str,
+    caret_at_start: bool,
+) -> Result<(), JsValue> {
+    let texts = split_range_text(range)?;
+    let mut first: Option<Node> = None;
+    let mut last: Option<Node> = None;
+    for text in texts {
+        let node: Node = text.clone().into();
+        if let Some(ins) = closest_in(root, &node, "ins") {
+            if is_by(&ins, author) {
+                node.parent_node().unwrap().remove_child(&node)?;
+                if ins.text_content().unwrap_or_default().is_empty() {
+                    ins.remove();
+                }
+                continue;
+            }
+        }
+        let del = match closest_in(root, &node, "del") {
+            Some(del) => del,
+            None => {
+                let del = change_element("del", author)?;
+                let parent = node.parent_node().ok_or("the text has no parent")?;
+                match node.previous_sibling() {
+                    Some(previous) if is_same_change(&previous, &del) => {
+                        previous.append_child(&node)?;
+                        previous.unchecked_into()
+                    }
+                    _ => {
+                        parent.insert_before(&del, Some(&node))?;
+                        del.append_child(&node)?;
+                        del
+                    }
+                }
+            }
+        };
+        let del: Node = del.into();
+        if first.is_none() {
+            first = Some(del.clone());
+        }
+        last = Some(del);
+    }
+
+    let caret = Range::new()?;
+    match (caret_at_start, first, last) {
+        (true, Some(first), _) => caret.set_start_before(&first)?,
+        (false, _, Some(last)) => caret.set_start_after(&last)?,
+        _ => caret.set_start(&range.start_container()?, range.start_offset()?)?,
+    }
+    caret.collapse_with_to_start(true);
+    restore_selection(&caret);
+    Ok(())
+}
+
+/// Inserts the text as a suggestion at the selection, replacing the selected text
+fn suggest_insertion(
+    root: &Element,
+    range: &Range,
+    text: &str,
+    author: &str,
+) -> Result<(), JsValue> {
+    if !range.collapsed() {
+        suggest_deletion(root, range, author, false)?;
+    }
+    let caret = save_selection().ok_or("there is no selection")?;
+    let node = caret.start_container()?;
+    let offset = caret.start_offset()?;
+
+    // Typing inside an own insertion extends it
+    let own_ins = closest_in(root, &node, "ins").filter(|ins| is_by(ins, author));
+    if let (Some(_), Some(text_node)) = (&own_ins, node.dyn_ref::<Text>()) {
+        let end = insert_typed_text(text_node, offset, text)?;
+        caret.set_start(&node, end)?;
+        caret.collapse_with_to_start(true);
+        restore_selection(&caret);
+        return Ok(());
+    }
+
+    // Never insert inside a deletion
+    if let Some(del) = closest_in(root, &node, "del") {
+        caret.set_start_after(&del)?;
+        caret.collapse_with_to_start(true);
+    }
+    let ins = change_element("ins", author)?;
+    let text_node = document().create_text_node("");
+    ins.append_child(&text_node)?;
+    caret.insert_node(&ins)?;
+    insert_typed_text(&text_node, 0, text)?;
+
+    // Text typed right after an own insertion joins it
+    if let Some(previous) = ins.previous_sibling() {
+        if is_same_change(&previous, &ins) {
+            previous.append_child(&text_node)?;
+            ins.remove();
+        }
+    }
+    caret.set_start(&text_node, text_node.length())?;
+    caret.collapse_with_to_start(true);
+    restore_selection(&caret);
+    Ok(())
+}
+
+/// Returns the changes at the caret, or the ones that intersect the selection
+fn selected_changes(root: &Element) -> Vec<Element> {
+    let Some(range) = save_selection() else {
+        return vec![];
+    };
+    let start = range.start_container().unwrap();
+    if !root.contains(Some(&start)) {
+        return vec![];
+    }
+    if range.collapsed() {
+        let change = match start.dyn_ref::<Element>() {
+            Some(el) => el.closest(CHANGE_SELECTOR).ok().flatten(),
+            None => start
+                .parent_element()
+                .and_then(|el| el.closest(CHANGE_SELECTOR).ok().flatten()),
+        };
+        return change
+            .filter(|change| root.contains(Some(change)))
+            .into_iter()
+            .collect();
+    }
+    all_changes(root)
+        .into_iter()
+        .filter(|change| range.intersects_node(change).unwrap_or(false))
+        .collect()
+}
+
+fn all_changes(root: &Element) -> Vec<Element> {
+    let changes = root.query_selector_all(CHANGE_SELECTOR).unwrap();
+    (0..changes.length())
+        .filter_map(|i| changes.item(i))
+        .filter_map(|node| node.dyn_into::<Element>().ok())
+        .collect()
+}
+
+/// Accepting an insertion keeps its text, accepting a deletion removes its text
+fn resolve_change(change: &Element, accept: bool) {
+    let is_insertion = change.tag_name().eq_ignore_ascii_case("ins");
+    match is_insertion == accept {
+        true => unwrap_element(change),
+        false => change.remove(),
+    }
+}
+
+fn resolve_changes(content_id: &str, accept: bool, all: bool) -> Result<bool, JsValue> {
+    let root = document()
+        .get_element_by_id(content_id)
+        .ok_or("the content doesn't exist")?;
+    let changes = match all {
+        true => all_changes(&root),
+        false => selected_changes(&root),
+    };
+    if changes.is_empty() {
+        return Ok(false);
+    }
+    for change in changes {
+        // Changes nested in a resolved change are already gone
+        if root.contains(Some(&change)) {
+            resolve_change(&change, accept);
+        }
+    }
+    root.normalize();
+    dispatch_input_event(content_id);
+    Ok(true)
+}
+
+/// Accepts the change at the caret or the changes of the selection
+pub fn accept_change(content_id: &str) -> Result<bool, JsValue> {
+    resolve_changes(content_id, true, false)
+}
+
+/// Rejects the change at the caret or the changes of the selection
+pub fn reject_change(content_id: &str) -> Result<bool, JsValue> {
+    resolve_changes(content_id, false, false)
+}
+
+pub fn accept_all_changes(content_id: &str) -> Result<bool, JsValue> {
+    resolve_changes(content_id, true, true)
+}
+
+pub fn reject_all_changes(content_id: &str) -> Result<bool, JsValue> {
+    resolve_changes(content_id, false, true)
+}
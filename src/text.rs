@@ -0,0 +1,287 @@
+use crate::html::{escape_text, parse_html, HtmlElement, HtmlNode};
+use crate::list::{CHECKBOX_CLASS, CHECKLIST_CLASS};
+
+fn has_class(el: &HtmlElement, class: &str) -> bool {
+    el.attribute("class")
+        .map(|classes| classes.split_whitespace().any(|c| c == class))
+        .unwrap_or(false)
+}
+
+/// Appends the text of inline content, collapsing whitespace like the browsers do
+fn push_inline_text(node: &HtmlNode, text: &mut String) {
+    match node {
+        HtmlNode::Text(content) => {
+            for c in content.chars() {
+                match c {
+                    '\u{a0}' => text.push(' '),
+                    c if c.is_whitespace() => {
+                        if !text.is_empty() && !text.ends_with([' ', '\n']) {
+                            text.push(' ');
+                        }
+                    }
+                    c => text.push(c),
+                }
+            }
+        }
+        HtmlNode::Element(el) => match el.tag.as_str() {
+            "br" => {
+                let trimmed = text.trim_end_matches(' ').len();
+                text.truncate(trimmed);
+                text.push('\n');
+            }
+            "img" => {
+                if let Some(alt) = el.attribute("alt").filter(|alt| !alt.is_empty()) {
+                    text.push_str(alt);
+                }
+            }
+            "input" => {}
+            "a" => {
+                // The label is written in place, so its whitespace collapses with the text
+                // before it, and collected apart to compare it with the URL
+                let mut label = String::new();
+                for child in &el.children {
+                    push_inline_text(child, text);
+                    push_inline_text(child, &mut label);
+                }
+                let label = label.trim();
+                let href = el.attribute("href").unwrap_or_default();
+                if !href.is_empty() && label != href && label != href.trim_start_matches("mailto:")
+                {
+                    match label.is_empty() {
+                        true => {
+                            if !text.is_empty() && !text.ends_with([' ', '\n']) {
+                                text.push(' ');
+                            }
+                            text.push_str(href);
+                        }
+                        false => text.push_str(&format!(" ({href})")),
+                    }
+                }
+            }
+            _ => {
+                for child in &el.children {
+                    push_inline_text(child, text);
+                }
+            }
+        },
+        HtmlNode::Comment(_) => {}
+    }
+}
+
+/// Returns the paragraphs of the text of the nodes
+fn blocks_text(nodes: &[HtmlNode]) -> Vec<String> {
+    let mut paragraphs = vec![];
+    let mut inline = String::new();
+
+    fn flush(paragraphs: &mut Vec<String>, inline: &mut String) {
+        let lines: Vec<&str> = inline.lines().map(str::trim).collect();
+        let text = lines.join("\n").trim_matches('\n').to_string();
+        if !text.is_empty() {
+            paragraphs.push(text);
+        }
+        inline.clear();
+    }
+
+    for node in nodes {
+        match node {
+            HtmlNode::Element(el) if el.is_block() => {
+                flush(&mut paragraphs, &mut inline);
+                paragraphs.extend(block_text(el));
+            }
+            node => push_inline_text(node, &mut inline),
+        }
+    }
+    flush(&mut paragraphs, &mut inline);
+    paragraphs
+}
+
+/// Prefixes every line of the text, the first one with `first` and the rest with `rest`
+fn prefix_lines(text: &str, first: &str, rest: &str) -> String {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let prefix = if i == 0 { first } else { rest };
+            match line.is_empty() {
+                true => prefix.trim_end().to_string(),
+                false => format!("{prefix}{line}"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn block_text(el: &HtmlElement) -> Vec<String> {
+    let items = || {
+        el.children
+            .iter()
+            .filter_map(HtmlNode::as_element)
+            .filter(|child| child.tag == "li")
+    };
+    let text = match el.tag.as_str() {
+        "ul" | "ol" => {
+            let start: usize = el
+                .attribute("start")
+                .and_then(|start| start.parse().ok())
+                .unwrap_or(1);
+            let is_checklist = has_class(el, CHECKLIST_CLASS);
+            items()
+                .enumerate()
+                .map(|(i, li)| {
+                    let marker = match el.tag.as_str() {
+                        "ol" => format!("{}. ", start + i),
+                        _ if is_checklist => {
+                            let checked = li.children.iter().any(|child| {
+                                matches!(child, HtmlNode::Element(input)
+                                    if has_class(input, CHECKBOX_CLASS)
+                                        && input.attribute("checked").is_some())
+                            });
+                            match checked {
+                                true => "[x] ".to_string(),
+                                false => "[ ] ".to_string(),
+                            }
+                        }
+                        _ => "- ".to_string(),
+                    };
+                    let indent = " ".repeat(marker.len());
+                    prefix_lines(&blocks_text(&li.children).join("\n"), &marker, &indent)
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        "blockquote" => prefix_lines(&blocks_text(&el.children).join("\n\n"), "> ", "> "),
+        "pre" => HtmlNode::Element(el.clone())
+            .text_content()
+            .trim_end_matches('\n')
+            .to_string(),
+        "hr" => "---".to_string(),
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = el.tag[1..].parse().unwrap_or(1);
+            let text = blocks_text(&el.children).join(" ").replace('\n', " ");
+            if text.is_empty() {
+                return vec![];
+            }
+            format!("{} {text}", "#".repeat(level))
+        }
+        "tr" => el
+            .children
+            .iter()
+            .filter_map(HtmlNode::as_element)
+            .map(|cell| blocks_text(&cell.children).join(" "))
+            .collect::<Vec<_>>()
+            .join(" | "),
+        "table" | "thead" | "tbody" | "tfoot" => {
+            let rows = blocks_text(&el.children);
+            return match rows.is_empty() {
+                true => rows,
+                false => vec![rows.join("\n")],
+            };
+        }
+        _ => return blocks_text(&el.children),
+    };
+    match text.trim().is_empty() {
+        true => vec![],
+        false => vec![text],
+    }
+}
+
+/// Converts the content to plain text, for notifications or search indexes. Blocks are
+/// separated by a blank line, headings start with a `#` per level, list items start with
+/// `- `, `1. ` or a checkbox (`[ ] `), quotes with `> `, table cells are separated by
+/// ` | `, and links are followed by their URL in parentheses
+pub fn html_to_text(html: &str) -> String {
+    blocks_text(&parse_html(html)).join("\n\n")
+}
+
+/// Converts plain text to content. Paragraphs are separated by blank lines and become
+/// `separator` blocks, the other line breaks become `<br>`
+pub fn text_to_html(text: &str, separator: &str) -> String {
+    let text = text.replace("\r\n", "\n");
+    let mut html = String::new();
+    let mut paragraph: Vec<&str> = vec![];
+    for line in text.lines().chain([""]) {
+        if !line.trim().is_empty() {
+            paragraph.push(line);
+            continue;
+        }
+        if paragraph.is_empty() {
+            continue;
+        }
+        let lines: Vec<String> = paragraph.drain(..).map(escape_text).collect();
+        html.push_str(&format!(
+            "<{separator}>{}</{separator}>",
+            lines.join("<br>")
+        ));
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separates_blocks_and_collapses_whitespace() {
+        assert_eq!(
+            html_to_text("<p>one  \n two</p>text<div>a<br>b&nbsp;&nbsp;</div><hr>"),
+            "one two\n\ntext\n\na\nb\n\n---"
+        );
+    }
+
+    #[test]
+    fn prefixes_headings_lists_and_quotes() {
+        assert_eq!(
+            html_to_text(
+                "<h1>Title</h1><h3>Sub<br>title</h3><h2></h2>\
+                 <ol start=\"3\"><li>three</li><li>four</li></ol>\
+                 <ul><li>item<br>more</li></ul><blockquote><p>a</p><p>b</p></blockquote>"
+            ),
+            "# Title\n\n### Sub title\n\n3. three\n4. four\n\n- item\n  more\n\n> a\n>\n> b"
+        );
+    }
+
+    #[test]
+    fn writes_checklists_and_tables() {
+        assert_eq!(
+            html_to_text(
+                "<ul class=\"papelito-checklist\">\
+                 <li><input type=\"checkbox\" class=\"papelito-checkbox\" checked>done</li>\
+                 <li><input type=\"checkbox\" class=\"papelito-checkbox\">todo</li></ul>\
+                 <table><tr><td>a</td><td>b</td></tr><tr><td>c</td><td>d</td></tr></table>"
+            ),
+            "[x] done\n[ ] todo\n\na | b\nc | d"
+        );
+    }
+
+    #[test]
+    fn follows_links_with_their_url() {
+        assert_eq!(
+            html_to_text("<p>see <a href=\"https://a.b\">docs</a> or <a href=\"https://a.b\">https://a.b</a></p>"),
+            "see docs (https://a.b) or https://a.b"
+        );
+        assert_eq!(
+            html_to_text(
+                "<p><a href=\"mailto:me@a.b\">me@a.b</a><a href=\"u\"><img src=\"i\"></a></p>"
+            ),
+            "me@a.b u"
+        );
+    }
+
+    #[test]
+    fn link_labels_can_start_with_a_line_break() {
+        assert_eq!(
+            html_to_text("<div>foo&nbsp;&nbsp;<a href=\"u\"><br>é</a></div>"),
+            "foo\né (u)"
+        );
+        assert_eq!(html_to_text("<p>a <a href=\"u\"> b</a></p>"), "a b (u)");
+        assert_eq!(html_to_text("<p>a<a href=\"u\"> b</a></p>"), "a b (u)");
+    }
+
+    #[test]
+    fn converts_text_to_paragraphs() {
+        assert_eq!(
+            text_to_html("one\r\ntwo <3\n\n\n three\n", "p"),
+            "<p>one<br>two &lt;3</p><p> three</p>"
+        );
+        assert_eq!(text_to_html("", "div"), "");
+    }
+}
@@ -0,0 +1,727 @@
+use crate::html::{parse_html, to_html, HtmlElement, HtmlNode};
+use std::fmt;
+use std::rc::Rc;
+
+/// Above this number of compared pairs, the items between the common start and end of two
+/// sequences are treated as entirely replaced instead of being matched
+const MAX_COMPARISONS: usize = 250_000;
+
+/// An inline element that formats text, such as `<b>` or `<a href="...">`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mark {
+    pub tag: String,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl Mark {
+    fn from_element(el: &HtmlElement) -> Self {
+        Self {
+            tag: el.tag.clone(),
+            attributes: el.attributes.clone(),
+        }
+    }
+
+    fn to_element(&self) -> HtmlElement {
+        HtmlElement {
+            tag: self.tag.clone(),
+            attributes: self.attributes.clone(),
+            children: vec![],
+        }
+    }
+}
+
+/// A granular change of a document.
+/// Blocks are the top level nodes of the content, and are indexed as they are when the step
+/// is applied. Offsets are counted in characters of the text of a block, where inline
+/// elements without text, such as images, count as one character. Marks are listed from
+/// the outermost
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum Step {
+    /// Inserts a block before the one at `index`, or at the end
+    InsertBlock {
+        index: usize,
+        html: String,
+    },
+    RemoveBlock {
+        index: usize,
+    },
+    /// Replaces a block, describes the changes that the other steps can't
+    ReplaceBlock {
+        index: usize,
+        html: String,
+    },
+    /// Changes the tag and the attributes of a block, keeping its content
+    SetBlockType {
+        index: usize,
+        tag: String,
+        attributes: Vec<(String, String)>,
+    },
+    InsertText {
+        block: usize,
+        offset: usize,
+        text: String,
+        marks: Vec<Mark>,
+    },
+    DeleteText {
+        block: usize,
+        offset: usize,
+        length: usize,
+    },
+    /// Formats the text between the offsets, the mark is added inside the existing ones
+    AddMark {
+        block: usize,
+        start: usize,
+        end: usize,
+        mark: Mark,
+    },
+    RemoveMark {
+        block: usize,
+        start: usize,
+        end: usize,
+        mark: Mark,
+    },
+}
+
+/// The steps of an edit of the content
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Transaction {
+    pub steps: Vec<Step>,
+    /// Milliseconds since the Unix epoch
+    pub time: f64,
+}
+
+/// Receives the transactions of the edits of the content
+pub type TransactionCallback = Rc<dyn Fn(Transaction)>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayError {
+    /// Index of the step that couldn't be applied
+    pub step: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "couldn't apply step {}: {}", self.step, self.message)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Item {
+    Char(char),
+    /// An inline element without text or a comment
+    Object(HtmlNode),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Inline {
+    pub item: Item,
+    pub marks: Vec<Mark>,
+}
+
+/// Flattens inline content into characters with their marks, returns `false` if it
+/// contains blocks
+pub(crate) fn flatten(
+    nodes: &[HtmlNode],
+    marks: &mut Vec<Mark>,
+    inlines: &mut Vec<Inline>,
+) -> bool {
+    for node in nodes {
+        match node {
+            HtmlNode::Text(text) => inlines.extend(text.chars().map(|c| Inline {
+                item: Item::Char(c),
+                marks: marks.clone(),
+            })),
+            HtmlNode::Element(el) if el.is_block() => return false,
+            HtmlNode::Element(el) if !el.is_void() && !el.children.is_empty() => {
+                marks.push(Mark::from_element(el));
+                let flattened = flatten(&el.children, marks, inlines);
+                marks.pop();
+                if !flattened {
+                    return false;
+                }
+            }
+            _ => inlines.push(Inline {
+                item: Item::Object(node.clone()),
+                marks: marks.clone(),
+            }),
+        }
+    }
+    true
+}
+
+/// Nests the characters back into elements, opening and closing their marks as needed
+pub(crate) fn rebuild(inlines: &[Inline]) -> Vec<HtmlNode> {
+    // The open marks, each with its children so far, below the children of the block
+    let mut open: Vec<(Mark, Vec<HtmlNode>)> = vec![];
+    let mut children: Vec<HtmlNode> = vec![];
+
+    fn close(open: &mut Vec<(Mark, Vec<HtmlNode>)>, children: &mut Vec<HtmlNode>) {
+        if let Some((mark, mark_children)) = open.pop() {
+            let mut el = mark.to_element();
+            el.children = mark_children;
+            let parent = match open.last_mut() {
+                Some((_, parent)) => parent,
+                None => children,
+            };
+            parent.push(HtmlNode::Element(el));
+        }
+    }
+
+    for inline in inlines {
+        let common = open
+            .iter()
+            .zip(&inline.marks)
+            .take_while(|((open, _), mark)| open == *mark)
+            .count();
+        while open.len() > common {
+            close(&mut open, &mut children);
+        }
+        for mark in &inline.marks[common..] {
+            open.push((mark.clone(), vec![]));
+        }
+        let parent = match open.last_mut() {
+            Some((_, parent)) => parent,
+            None => &mut children,
+        };
+        match &inline.item {
+            Item::Char(c) => match parent.last_mut() {
+                Some(HtmlNode::Text(text)) => text.push(*c),
+                _ => parent.push(HtmlNode::Text(c.to_string())),
+            },
+            Item::Object(node) => parent.push(node.clone()),
+        }
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut children);
+    }
+    children
+}
+
+/// Returns the flattened content of a block that only has inline content
+pub(crate) fn text_block(node: &HtmlNode) -> Option<Vec<Inline>> {
+    let el = node
+        .as_element()
+        .filter(|el| el.is_block() && !el.is_void())?;
+    let mut inlines = vec![];
+    flatten(&el.children, &mut vec![], &mut inlines).then_some(inlines)
+}
+
+fn apply_step(blocks: &mut Vec<HtmlNode>, step: &Step) -> Result<(), String> {
+    fn check_index(blocks: &[HtmlNode], index: usize) -> Result<(), String> {
+        match index < blocks.len() {
+            true => Ok(()),
+            false => Err(format!("there is no block {index}")),
+        }
+    }
+
+    fn edit_text(
+        blocks: &mut [HtmlNode],
+        index: usize,
+        f: impl FnOnce(&mut Vec<Inline>) -> Result<(), String>,
+    ) -> Result<(), String> {
+        check_index(blocks, index)?;
+        let mut inlines =
+            text_block(&blocks[index]).ok_or(format!("block {index} has nested blocks"))?;
+        f(&mut inlines)?;
+        if let HtmlNode::Element(el) = &mut blocks[index] {
+            el.children = rebuild(&inlines);
+        }
+        Ok(())
+    }
+
+    fn check_range(inlines: &[Inline], start: usize, end: usize) -> Result<(), String> {
+        match start <= end && end <= inlines.len() {
+            true => Ok(()),
+            false => Err(format!(
+                "{start}..{end} is out of the text of {} characters",
+                inlines.len()
+            )),
+        }
+    }
+
+    match step {
+        Step::InsertBlock { index, html } => {
+            if *index > blocks.len() {
+                return Err(format!("there is no block {index}"));
+            }
+            blocks.splice(*index..*index, parse_html(html));
+        }
+        Step::RemoveBlock { index } => {
+            check_index(blocks, *index)?;
+            blocks.remove(*index);
+        }
+        Step::ReplaceBlock { index, html } => {
+            check_index(blocks, *index)?;
+            blocks.splice(*index..*index + 1, parse_html(html));
+        }
+        Step::SetBlockType {
+            index,
+            tag,
+            attributes,
+        } => {
+            check_index(blocks, *index)?;
+            match &mut blocks[*index] {
+                HtmlNode::Element(el) => {
+                    el.tag = tag.clone();
+                    el.attributes = attributes.clone();
+                }
+                _ => return Err(format!("block {index} isn't an element")),
+            }
+        }
+        Step::InsertText {
+            block,
+            offset,
+            text,
+            marks,
+        } => edit_text(blocks, *block, |inlines| {
+            check_range(inlines, *offset, *offset)?;
+            let inserted = text.chars().map(|c| Inline {
+                item: Item::Char(c),
+                marks: marks.clone(),
+            });
+            inlines.splice(*offset..*offset, inserted);
+            Ok(())
+        })?,
+        Step::DeleteText {
+            block,
+            offset,
+            length,
+        } => edit_text(blocks, *block, |inlines| {
+            let end = offset.checked_add(*length).ok_or_else(|| {
+                format!(
+                    "{offset}..{offset}+{length} is out of the text of {} characters",
+                    inlines.len()
+                )
+            })?;
+            check_range(inlines, *offset, end)?;
+            inlines.drain(*offset..end);
+            Ok(())
+        })?,
+        Step::AddMark {
+            block,
+            start,
+            end,
+            mark,
+        } => edit_text(blocks, *block, |inlines| {
+            check_range(inlines, *start, *end)?;
+            for inline in &mut inlines[*start..*end] {
+                if !inline.marks.contains(mark) {
+                    inline.marks.push(mark.clone());
+                }
+            }
+            Ok(())
+        })?,
+        Step::RemoveMark {
+            block,
+            start,
+            end,
+            mark,
+        } => edit_text(blocks, *block, |inlines| {
+            check_range(inlines, *start, *end)?;
+            for inline in &mut inlines[*start..*end] {
+                inline.marks.retain(|m| m != mark);
+            }
+            Ok(())
+        })?,
+    }
+    Ok(())
+}
+
+/// Applies the steps to the HTML of a document, in order
+pub fn replay(html: &str, steps: &[Step]) -> Result<String, ReplayError> {
+    let mut blocks = parse_html(html);
+    for (i, step) in steps.iter().enumerate() {
+        apply_step(&mut blocks, step).map_err(|message| ReplayError { step: i, message })?;
+    }
+    Ok(to_html(&blocks))
+}
+
+/// Groups the positions where each mark differs into ranges, `differs` returns the marks
+/// of the new inline missing from the old one
+fn mark_ranges(
+    old: &[Inline],
+    new: &[Inline],
+    differs: impl Fn(&Inline, &Inline) -> Vec<Mark>,
+) -> Vec<(Mark, usize, usize)> {
+    let mut ranges: Vec<(Mark, usize, usize)> = vec![];
+    for (i, (old, new)) in old.iter().zip(new).enumerate() {
+        for mark in differs(old, new) {
+            let open = ranges
+                .iter_mut()
+                .rev()
+                .find(|(m, _, end)| *m == mark && *end == i);
+            match open {
+                Some((_, _, end)) => *end = i + 1,
+                None => ranges.push((mark, i, i + 1)),
+            }
+        }
+    }
+    ranges
+}
+
+/// Returns the steps that turn the content of a block into the new one, `None` if they
+/// can't describe the change
+fn inline_steps(index: usize, old: &[Inline], new: &[Inline]) -> Option<Vec<Step>> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(a, b)| a.item == b.item)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a.item == b.item)
+        .count();
+
+    let mut steps = vec![];
+    let removed = old.len() - suffix - prefix;
+    if removed > 0 {
+        steps.push(Step::DeleteText {
+            block: index,
+            offset: prefix,
+            length: removed,
+        });
+    }
+    // The inserted text is inserted in runs of the same marks
+    let mut offset = prefix;
+    let inserted = &new[prefix..new.len() - suffix];
+    while offset - prefix < inserted.len() {
+        let run_start = offset - prefix;
+        let marks = &inserted[run_start].marks;
+        let run_length = inserted[run_start..]
+            .iter()
+            .take_while(|inline| inline.marks == *marks)
+            .count();
+        let text = inserted[run_start..run_start + run_length]
+            .iter()
+            .map(|inline| match inline.item {
+                Item::Char(c) => Some(c),
+                Item::Object(_) => None,
+            })
+            .collect::<Option<String>>()?;
+        steps.push(Step::InsertText {
+            block: index,
+            offset,
+            text,
+            marks: marks.clone(),
+        });
+        offset += run_length;
+    }
+
+    // The text is now the new one, with the inserted text already formatted
+    let mut current = old[..prefix].to_vec();
+    current.extend_from_slice(&new[prefix..new.len() - suffix]);
+    current.extend_from_slice(&old[old.len() - suffix..]);
+    let removed_marks = mark_ranges(&current, new, |old, new| {
+        old.marks
+            .iter()
+            .filter(|m| !new.marks.contains(m))
+            .cloned()
+            .collect()
+    });
+    let added_marks = mark_ranges(&current, new, |old, new| {
+        new.marks
+            .iter()
+            .filter(|m| !old.marks.contains(m))
+            .cloned()
+            .collect()
+    });
+    for (mark, start, end) in removed_marks {
+        steps.push(Step::RemoveMark {
+            block: index,
+            start,
+            end,
+            mark,
+        });
+    }
+    for (mark, start, end) in added_marks {
+        steps.push(Step::AddMark {
+            block: index,
+            start,
+            end,
+            mark,
+        });
+    }
+    Some(steps)
+}
+
+/// Returns the steps that turn a block into the new one
+fn block_steps(index: usize, old: &HtmlNode, new: &HtmlNode) -> Vec<Step> {
+    let replace = vec![Step::ReplaceBlock {
+        index,
+        html: new.to_html(),
+    }];
+    let (Some(old_el), Some(new_el)) = (old.as_element(), new.as_element()) else {
+        return replace;
+    };
+    let (Some(old_inlines), Some(new_inlines)) = (text_block(old), text_block(new)) else {
+        return replace;
+    };
+
+    let mut steps = vec![];
+    if old_el.tag != new_el.tag || old_el.attributes != new_el.attributes {
+        steps.push(Step::SetBlockType {
+            index,
+            tag: new_el.tag.clone(),
+            attributes: new_el.attributes.clone(),
+        });
+    }
+    match inline_steps(index, &old_inlines, &new_inlines) {
+        Some(inline) => steps.extend(inline),
+        None => return replace,
+    }
+
+    // The steps rebuild the inline content, which can nest it differently than the new
+    // block does
+    let mut blocks = vec![old.clone()];
+    let applies = steps
+        .iter()
+        .all(|step| apply_step(&mut blocks, &with_block(step, 0)).is_ok());
+    match applies && blocks[0] == *new {
+        true => steps,
+        false => replace,
+    }
+}
+
+/// Returns the step applied to another block
+fn with_block(step: &Step, block: usize) -> Step {
+    let mut step = step.clone();
+    match &mut step {
+        Step::InsertBlock { index, .. }
+        | Step::RemoveBlock { index }
+        | Step::ReplaceBlock { index, .. }
+        | Step::SetBlockType { index, .. } => *index = block,
+        Step::InsertText { block: index, .. }
+        | Step::DeleteText { block: index, .. }
+        | Step::AddMark { block: index, .. }
+        | Step::RemoveMark { block: index, .. } => *index = block,
+    }
+    step
+}
+
+/// Returns the pairs of indices of the equal items of two sequences, in order.
+/// The items between the common start and end are matched by their longest common
+/// subsequence
+pub(crate) fn matching_pairs<T: PartialEq>(old: &[T], new: &[T]) -> Vec<(usize, usize)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut pairs: Vec<(usize, usize)> = (0..prefix).map(|i| (i, i)).collect();
+    if old_middle.len() * new_middle.len() <= MAX_COMPARISONS {
+        let (n, m) = (old_middle.len(), new_middle.len());
+        let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lengths[i][j] = match old_middle[i] == new_middle[j] {
+                    true => lengths[i + 1][j + 1] + 1,
+                    false => lengths[i + 1][j].max(lengths[i][j + 1]),
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if old_middle[i] == new_middle[j] {
+                pairs.push((prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+    pairs.extend((0..suffix).map(|i| (old.len() - suffix + i, new.len() - suffix + i)));
+    pairs
+}
+
+/// Returns the steps that turn a document into the new one, so that replaying them on the
+/// old HTML returns the new one
+pub fn steps_between(old_html: &str, new_html: &str) -> Vec<Step> {
+    let old = parse_html(old_html);
+    let new = parse_html(new_html);
+    let old_html: Vec<String> = old.iter().map(HtmlNode::to_html).collect();
+    let new_html: Vec<String> = new.iter().map(HtmlNode::to_html).collect();
+
+    let mut steps = vec![];
+    // Index of the next block in the document as the steps so far leave it
+    let mut index = 0;
+    let (mut i, mut j) = (0, 0);
+    let mut matches = matching_pairs(&old_html, &new_html);
+    matches.push((old.len(), new.len()));
+    for (next_i, next_j) in matches {
+        // The blocks in between changed, they are edited in pairs and the rest is removed
+        // or inserted
+        while i < next_i && j < next_j {
+            steps.extend(block_steps(index, &old[i], &new[j]));
+            index += 1;
+            i += 1;
+            j += 1;
+        }
+        while i < next_i {
+            steps.push(Step::RemoveBlock { index });
+            i += 1;
+        }
+        while j < next_j {
+            steps.push(Step::InsertBlock {
+                index,
+                html: new_html[j].clone(),
+            });
+            index += 1;
+            j += 1;
+        }
+        // The matching block
+        index += 1;
+        i += 1;
+        j += 1;
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(old: &str, new: &str) -> Vec<Step> {
+        let steps = steps_between(old, new);
+        assert_eq!(replay(old, &steps).as_deref(), Ok(new), "{steps:?}");
+        steps
+    }
+
+    #[test]
+    fn replaying_the_steps_returns_the_new_html() {
+        let old = "<p>Hello world</p><p>Second</p><ul><li>one</li></ul>";
+        round_trip(old, old);
+        round_trip(
+            old,
+            "<p>Hello brave world</p><p>Second</p><ul><li>one</li></ul>",
+        );
+        round_trip(
+            old,
+            "<p>Hello <b>world</b></p><p>Second</p><ul><li>one</li></ul>",
+        );
+        round_trip(
+            old,
+            "<h1>Hello world</h1><p>Second</p><ul><li>one</li></ul>",
+        );
+        round_trip(
+            old,
+            "<p>Second</p><p>New</p><ul><li>one</li><li>two</li></ul>",
+        );
+        round_trip(old, "");
+        round_trip("", old);
+        round_trip(
+            "<p><b>bold <i>both</i></b> plain<img src=\"a.png\"></p>",
+            "<p><b>bold</b> both pl<i>ain</i><img src=\"a.png\">!</p>",
+        );
+    }
+
+    #[test]
+    fn describes_text_edits_granularly() {
+        assert_eq!(
+            round_trip("<p>Hello world</p>", "<p>Hello brave world</p>"),
+            vec![Step::InsertText {
+                block: 0,
+                offset: 6,
+                text: "brave ".to_string(),
+                marks: vec![],
+            }]
+        );
+        assert_eq!(
+            round_trip("<p>Hello world</p>", "<p>Hello <em>world</em></p>"),
+            vec![Step::AddMark {
+                block: 0,
+                start: 6,
+                end: 11,
+                mark: Mark {
+                    tag: "em".to_string(),
+                    attributes: vec![],
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_steps_out_of_the_document() {
+        let html = "<p>text</p>";
+        let error = |steps: &[Step]| replay(html, steps).unwrap_err();
+
+        assert_eq!(error(&[Step::RemoveBlock { index: 1 }]).step, 0);
+        assert_eq!(
+            error(&[
+                Step::RemoveBlock { index: 0 },
+                Step::DeleteText {
+                    block: 0,
+                    offset: 0,
+                    length: 1,
+                },
+            ])
+            .step,
+            1
+        );
+        assert_eq!(
+            error(&[Step::InsertBlock {
+                index: 2,
+                html: "<p>x</p>".to_string(),
+            }])
+            .message,
+            "there is no block 2"
+        );
+        assert_eq!(
+            error(&[Step::DeleteText {
+                block: 0,
+                offset: 2,
+                length: 3,
+            }])
+            .message,
+            "2..5 is out of the text of 4 characters"
+        );
+        assert_eq!(
+            error(&[Step::DeleteText {
+                block: 0,
+                offset: 2,
+                length: usize::MAX,
+            }])
+            .message,
+            format!("2..2+{} is out of the text of 4 characters", usize::MAX)
+        );
+        assert_eq!(
+            error(&[Step::AddMark {
+                block: 0,
+                start: 3,
+                end: 1,
+                mark: Mark {
+                    tag: "b".to_string(),
+                    attributes: vec![],
+                },
+            }])
+            .message,
+            "3..1 is out of the text of 4 characters"
+        );
+    }
+
+    #[test]
+    fn matches_the_longest_common_subsequence() {
+        assert_eq!(
+            matching_pairs(&['a', 'b', 'c', 'd'], &['a', 'c', 'x', 'd']),
+            vec![(0, 0), (2, 1), (3, 3)]
+        );
+        assert_eq!(matching_pairs::<char>(&[], &['a']), vec![]);
+    }
+}
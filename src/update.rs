@@ -0,0 +1,75 @@
+use leptos_dom::window;
+use std::cell::Cell;
+use std::rc::Rc;
+use wasm_bindgen::{closure::Closure, JsCast};
+
+/// When the edits of the content are written to `content_signal`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum UpdateMode {
+    /// On every input
+    #[default]
+    Immediate,
+    /// Once no input happened for the given number of milliseconds
+    Debounced(u32),
+    /// When the content loses focus
+    OnBlur,
+}
+
+/// Receives the HTML of the content after it is written to `content_signal`
+pub type ChangeCallback = Rc<dyn Fn(String)>;
+/// Receives the focus and blur events of the content
+pub type FocusCallback = Rc<dyn Fn(web_sys::FocusEvent)>;
+
+/// Writes the content according to the update mode, `commit` does the actual write
+#[derive(Clone)]
+pub(crate) struct ContentUpdater {
+    mode: UpdateMode,
+    commit: Rc<dyn Fn()>,
+    pending: Rc<Cell<Option<i32>>>,
+}
+
+impl ContentUpdater {
+    pub fn new(mode: UpdateMode, commit: impl Fn() + 'static) -> Self {
+        Self {
+            mode,
+            commit: Rc::new(commit),
+            pending: Rc::new(Cell::new(None)),
+        }
+    }
+
+    fn cancel_pending(&self) {
+        if let Some(handle) = self.pending.take() {
+            window().clear_timeout_with_handle(handle);
+        }
+    }
+
+    /// Called on every input of the content
+    pub fn input(&self) {
+        match self.mode {
+            UpdateMode::Immediate => (self.commit)(),
+            UpdateMode::Debounced(delay) => {
+                self.cancel_pending();
+                let commit = self.commit.clone();
+                let pending = self.pending.clone();
+                let on_timeout = Closure::once_into_js(move || {
+                    pending.set(None);
+                    commit();
+                });
+                let handle = window()
+                    .set_timeout_with_callback_and_timeout_and_arguments_0(
+                        on_timeout.unchecked_ref(),
+                        delay as i32,
+                    )
+                    .ok();
+                self.pending.set(handle);
+            }
+            UpdateMode::OnBlur => {}
+        }
+    }
+
+    /// Writes the content right away, called when the content loses focus
+    pub fn flush(&self) {
+        self.cancel_pending();
+        (self.commit)();
+    }
+}
@@ -1,7 +1,10 @@
 use leptos_dom::document;
-use wasm_bindgen::JsCast;
-use wasm_bindgen::{closure::Closure, JsValue};
-use web_sys::{Event, HtmlDivElement, HtmlElement};
+use std::rc::Rc;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{DomRect, Element, Event, HtmlDivElement, HtmlElement, Node, Range, Text};
+
+/// Font name used to mark the selection, it is never left in the document
+const SELECTION_MARKER: &str = "papelito-selection-marker";
 
 pub fn exec(command: &str, value: &str) -> Result<bool, wasm_bindgen::JsValue> {
     use web_sys::HtmlDocument;
@@ -20,10 +23,37 @@ pub fn unchecked_remove_class_from_el(document: &web_sys::Document, el_id: &str,
     }
 }
 
+/// Returns the id of the content div of the editor with the given key
+pub fn content_id(editor_key: &str) -> String {
+    format!("{editor_key}-content")
+}
+
 pub fn exec_format_block(separator: String) -> Result<bool, wasm_bindgen::JsValue> {
     exec("formatBlock", &format!("<{separator}>"))
 }
 
+/// Returns the id of the context menu attached to the element with the given id
+pub fn context_menu_id(element_id: &str) -> String {
+    format!("{element_id}_ctx_menu")
+}
+
+/// Removes the context menu attached to the element with the given id.
+/// Returns `true` if a menu was open
+pub fn remove_context_menu(element_id: &str) -> bool {
+    let menu_id = context_menu_id(element_id);
+    crate::CONTEXT_MENUS
+        .lock()
+        .unwrap()
+        .retain(|(id, _)| *id != menu_id);
+    match document().get_element_by_id(&menu_id) {
+        Some(menu) => {
+            menu.remove();
+            true
+        }
+        None => false,
+    }
+}
+
 pub enum ContextMenuPosition {
     TopLeft,
     TopCenter,
@@ -39,28 +69,20 @@ pub fn add_context_menu_to_el(
     height: f64,
     position: ContextMenuPosition,
 ) -> HtmlDivElement {
-    let menu_el = document().create_element("div").unwrap();
-    let menu_el = menu_el.dyn_ref::<HtmlDivElement>().unwrap();
-
     let target_el = document().get_element_by_id(element_id).unwrap();
-    let target_el = target_el.dyn_ref::<HtmlElement>().unwrap();
     let target_el_rect = target_el.get_bounding_client_rect();
+    add_context_menu_at_rect(element_id, &target_el_rect, width, height, position)
+}
 
-    menu_el
-        .style()
-        .set_property("width", &format!("{}px", width))
-        .unwrap();
-    let menu_id = format!("{element_id}_ctx_menu");
-    menu_el.set_id(&menu_id);
-    menu_el
-        .style()
-        .set_property("height", &format!("{}px", height))
-        .unwrap();
-    menu_el
-        .style()
-        .set_property("position", "absolute")
-        .unwrap();
-
+/// Adds a context menu positioned around the given rect, `element_id` is used as the owner
+/// of the menu and doesn't need to exist
+pub fn add_context_menu_at_rect(
+    element_id: &str,
+    target_el_rect: &DomRect,
+    width: f64,
+    height: f64,
+    position: ContextMenuPosition,
+) -> HtmlDivElement {
     let top = match position {
         ContextMenuPosition::TopLeft => target_el_rect.top() - height,
         ContextMenuPosition::TopCenter => target_el_rect.top() - height,
@@ -82,6 +104,36 @@ pub fn add_context_menu_to_el(
         }
         ContextMenuPosition::BottomRight => target_el_rect.right() - width,
     };
+    add_context_menu_at(element_id, top, left, width, height, vec![])
+}
+
+/// Adds a context menu at the given position. Clicks on the elements with the ids in
+/// `other_elements` don't close it
+pub fn add_context_menu_at(
+    element_id: &str,
+    top: f64,
+    left: f64,
+    width: f64,
+    height: f64,
+    other_elements: Vec<String>,
+) -> HtmlDivElement {
+    let menu_el = document().create_element("div").unwrap();
+    let menu_el = menu_el.dyn_ref::<HtmlDivElement>().unwrap();
+
+    menu_el
+        .style()
+        .set_property("width", &format!("{}px", width))
+        .unwrap();
+    let menu_id = context_menu_id(element_id);
+    menu_el.set_id(&menu_id);
+    menu_el
+        .style()
+        .set_property("height", &format!("{}px", height))
+        .unwrap();
+    menu_el
+        .style()
+        .set_property("position", "absolute")
+        .unwrap();
     menu_el
         .style()
         .set_property("top", &format!("{}px", top))
@@ -91,73 +143,513 @@ pub fn add_context_menu_to_el(
         .set_property("left", &format!("{}px", left))
         .unwrap();
 
+    let mut other_elements = other_elements;
+    other_elements.push(element_id.to_string());
     crate::CONTEXT_MENUS
         .lock()
         .unwrap()
-        .push((menu_id, vec![element_id.to_string()]));
+        .push((menu_id, other_elements));
 
     document().body().unwrap().append_child(&menu_el).unwrap();
 
     menu_el.clone()
 }
 
-static CONTEXT_MENU_COLORS: [&str; 56] = [
-    "#000000", "#44B8FF", "#1E92F7", "#0074D9", "#005DC2", "#00369B", "#b3d5f4", "#444444",
-    "#C3FFFF", "#9DF9FF", "#7FDBFF", "#68C4E8", "#419DC1", "#d9f4ff", "#666666", "#72FF84",
-    "#4CEA5E", "#2ECC40", "#17B529", "#008E02", "#c0f0c6", "#888888", "#FFFF44", "#FFFA1E",
-    "#FFDC00", "#E8C500", "#C19E00", "#fff5b3", "#aaaaaa", "#FFC95F", "#FFA339", "#FF851B",
-    "#E86E04", "#C14700", "#ffdbbb", "#cccccc", "#FF857A", "#FF5F54", "#FF4136", "#E82A1F",
-    "#C10300", "#ffc6c3", "#eeeeee", "#FF56FF", "#FF30DC", "#F012BE", "#D900A7", "#B20080",
-    "#fbb8ec", "#ffffff", "#F551FF", "#CF2BE7", "#B10DC9", "#9A00B2", "#9A00B2", "#e8b6ef",
-];
+/// Returns a copy of the current selection range, if there is one
+pub fn save_selection() -> Option<Range> {
+    let selection = document().get_selection().ok()??;
+    if selection.range_count() == 0 {
+        return None;
+    }
+    selection
+        .get_range_at(0)
+        .ok()
+        .map(|range| range.clone_range())
+}
 
-pub fn color_picker_menu(element_id: &str, width: f64, height: f64) {
-    let menu = add_context_menu_to_el(element_id, width, height, ContextMenuPosition::TopCenter);
-    style_color_picker_menu(&menu).unwrap();
+/// Replaces the current selection with the given range
+pub fn restore_selection(range: &Range) {
+    if let Ok(Some(selection)) = document().get_selection() {
+        let _ = selection.remove_all_ranges();
+        let _ = selection.add_range(range);
+    }
+}
 
-    let menu_inner = document().create_element("div").unwrap();
-    let menu_inner = menu_inner.dyn_ref::<HtmlElement>().unwrap();
-    style_color_picker_menu_inner(menu_inner).unwrap();
+/// Returns the element that contains the start of the current selection, if it is inside
+/// the element with the given id
+pub fn selection_element(root_id: &str) -> Option<Element> {
+    let root = document().get_element_by_id(root_id)?;
+    let selection = document().get_selection().ok()??;
+    let node = selection.anchor_node()?;
+    let el = match node.dyn_ref::<Element>() {
+        Some(el) => el.clone(),
+        None => node.parent_element()?,
+    };
+    match root.contains(Some(&el)) {
+        true => Some(el),
+        false => None,
+    }
+}
+
+/// Runs `f` and restores the selection afterwards, used when moving the nodes that
+/// contain the selection around, since moving a node resets the selection inside it
+pub fn with_preserved_selection<T>(f: impl FnOnce() -> T) -> T {
+    let selection = document().get_selection().ok().flatten();
+    let saved = selection.as_ref().and_then(|selection| {
+        Some((
+            selection.anchor_node()?,
+            selection.anchor_offset(),
+            selection.focus_node()?,
+            selection.focus_offset(),
+        ))
+    });
+    let result = f();
+    if let (Some(selection), Some((anchor, anchor_offset, focus, focus_offset))) =
+        (selection, saved)
+    {
+        let _ = selection.set_base_and_extent(&anchor, anchor_offset, &focus, focus_offset);
+    }
+    result
+}
+
+/// Returns the offset of the position inside the text of the root, in UTF-16 code units
+pub fn text_offset_of(root: &Element, node: &Node, offset: u32) -> Option<u32> {
+    if !root.contains(Some(node)) {
+        return None;
+    }
+    let range = Range::new().ok()?;
+    range.set_start(root, 0).ok()?;
+    range.set_end(node, offset).ok()?;
+    let text = range
+        .clone_contents()
+        .ok()?
+        .text_content()
+        .unwrap_or_default();
+    Some(text.encode_utf16().count() as u32)
+}
+
+/// Finds the text node and the offset inside it at the given offset of the node's text
+pub fn text_position(node: &Node, offset: u32) -> Option<(Node, u32)> {
+    let mut remaining = offset;
+    let children = node.child_nodes();
+    for i in 0..children.length() {
+        let child = children.item(i)?;
+        if let Some(text) = child.dyn_ref::<web_sys::Text>() {
+            if remaining <= text.length() {
+                return Some((child, remaining));
+            }
+            remaining -= text.length();
+        } else {
+            let len = child
+                .text_content()
+                .map(|t| t.encode_utf16().count() as u32)
+                .unwrap_or(0);
+            if remaining <= len {
+                return text_position(&child, remaining);
+            }
+            remaining -= len;
+        }
+    }
+    None
+}
+
+/// Returns the anchor and focus of the selection as offsets inside the text of the root,
+/// if the selection is inside it
+pub fn selection_text_offsets(root: &Element) -> Option<(u32, u32)> {
+    let selection = document().get_selection().ok()??;
+    let anchor = text_offset_of(root, &selection.anchor_node()?, selection.anchor_offset())?;
+    let focus = text_offset_of(root, &selection.focus_node()?, selection.focus_offset())?;
+    Some((anchor, focus))
+}
+
+/// Selects the text of the root between the given offsets, offsets past its end are moved
+/// to the end of the root
+pub fn set_selection_text_offsets(root: &Element, anchor: u32, focus: u32) -> Result<(), JsValue> {
+    let end = (
+        root.clone().unchecked_into::<Node>(),
+        root.child_nodes().length(),
+    );
+    let (anchor_node, anchor_offset) = text_position(root, anchor).unwrap_or_else(|| end.clone());
+    let (focus_node, focus_offset) = text_position(root, focus).unwrap_or(end);
+    let selection = document()
+        .get_selection()?
+        .ok_or("couldn't get the selection")?;
+    selection.set_base_and_extent(&anchor_node, anchor_offset, &focus_node, focus_offset)
+}
 
-    menu.append_child(&menu_inner).unwrap();
+/// Returns the text of the element in UTF-16 code units, the unit of the text offsets
+pub fn text_units(el: &Element) -> Vec<u16> {
+    el.text_content()
+        .unwrap_or_default()
+        .encode_utf16()
+        .collect()
+}
 
-    for color in CONTEXT_MENU_COLORS {
+/// The part of a text that was replaced by an edit, used to move text offsets through it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextChange {
+    /// Length of the text that is the same at the start
+    prefix: usize,
+    /// Length of the text that is the same at the end
+    suffix: usize,
+    old_len: usize,
+    new_len: usize,
+}
+
+impl TextChange {
+    pub fn between(old: &[u16], new: &[u16]) -> Self {
+        let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        Self {
+            prefix,
+            suffix,
+            old_len: old.len(),
+            new_len: new.len(),
+        }
+    }
+
+    /// Moves an offset of the old text to the same place in the new text. Offsets inside
+    /// the replaced text move to the end of the new text
+    pub fn map(&self, offset: u32) -> u32 {
+        let offset = offset as usize;
+        let mapped = if offset <= self.prefix {
+            offset
+        } else if offset >= self.old_len - self.suffix {
+            offset + self.new_len - self.old_len
+        } else {
+            self.new_len - self.suffix
+        };
+        mapped as u32
+    }
+}
+
+/// Returns the text with `text` inserted between `before` and `after` like typing does:
+/// the runs of spaces around the inserted text alternate spaces and non-breaking spaces so
+/// none of them collapses, and start or end with a non-breaking space at the edges
+pub fn typed_text(before: &str, text: &str, after: &str) -> String {
+    let mut chars: Vec<char> = format!("{before}{text}{after}").chars().collect();
+    let start = before.chars().count();
+    let end = start + text.chars().count();
+    let is_space = |c: char| c == ' ' || c == '\u{a0}';
+
+    let mut i = 0;
+    while i < chars.len() {
+        if !is_space(chars[i]) {
+            i += 1;
+            continue;
+        }
+        let run_start = i;
+        while i < chars.len() && is_space(chars[i]) {
+            i += 1;
+        }
+        // Only the spaces next to the inserted text are changed
+        if i < start || run_start > end {
+            continue;
+        }
+        let at_start = run_start == 0;
+        for (j, c) in chars[run_start..i].iter_mut().enumerate() {
+            *c = match (j % 2 == 0) == at_start {
+                true => '\u{a0}',
+                false => ' ',
+            };
+        }
+        if i == chars.len() {
+            chars[i - 1] = '\u{a0}';
+        }
+    }
+    chars.into_iter().collect()
+}
+
+/// Inserts text in a text node like typing does, see `typed_text`. The text of code blocks
+/// is inserted as it is. Returns the offset after the inserted text
+pub fn insert_typed_text(node: &Text, offset: u32, text: &str) -> Result<u32, JsValue> {
+    let data: Vec<u16> = node.data().encode_utf16().collect();
+    let offset = (offset as usize).min(data.len());
+    let before = String::from_utf16_lossy(&data[..offset]);
+    let after = String::from_utf16_lossy(&data[offset..]);
+    let is_preformatted = node
+        .parent_element()
+        .and_then(|el| el.closest("pre").ok().flatten())
+        .is_some();
+    match is_preformatted {
+        true => node.set_data(&format!("{before}{text}{after}")),
+        false => node.set_data(&typed_text(&before, text, &after)),
+    }
+    Ok((offset + text.encode_utf16().count()) as u32)
+}
+
+/// Collects the text nodes inside the range, splitting the ones at its boundaries
+pub fn split_range_text(range: &Range) -> Result<Vec<Text>, JsValue> {
+    fn collect(node: &Node, range: &Range, texts: &mut Vec<Text>) {
+        if !range.intersects_node(node).unwrap_or(false) {
+            return;
+        }
+        match node.dyn_ref::<Text>() {
+            Some(text) => texts.push(text.clone()),
+            None => {
+                let children = node.child_nodes();
+                for i in 0..children.length() {
+                    if let Some(child) = children.item(i) {
+                        collect(&child, range, texts);
+                    }
+                }
+            }
+        }
+    }
+
+    let start = range.start_container()?;
+    let start_offset = range.start_offset()?;
+    let end = range.end_container()?;
+    let end_offset = range.end_offset()?;
+    let mut texts = vec![];
+    collect(&range.common_ancestor_container()?, range, &mut texts);
+
+    let mut split = vec![];
+    for mut text in texts {
+        let node: &Node = text.as_ref();
+        let from = match node == &start {
+            true => start_offset,
+            false => 0,
+        };
+        let to = match node == &end {
+            true => end_offset,
+            false => text.length(),
+        };
+        if from >= to {
+            continue;
+        }
+        if to < text.length() {
+            text.split_text(to)?;
+        }
+        if from > 0 {
+            text = text.split_text(from)?;
+        }
+        split.push(text);
+    }
+    Ok(split)
+}
+
+/// Adds a style sheet to the document, unless the one with the given id was already added
+pub fn add_style(id: &str, css: &str) -> Result<(), JsValue> {
+    if document().get_element_by_id(id).is_some() {
+        return Ok(());
+    }
+    let style = document().create_element("style")?;
+    style.set_id(id);
+    style.set_text_content(Some(css));
+    document()
+        .body()
+        .ok_or("the document has no body")?
+        .append_child(&style)?;
+    Ok(())
+}
+
+/// Places the caret right after the given node
+pub fn set_caret_after(node: &web_sys::Node) -> Result<(), JsValue> {
+    let range = Range::new()?;
+    range.set_start_after(node)?;
+    range.collapse_with_to_start(true);
+    restore_selection(&range);
+    Ok(())
+}
+
+/// Dispatches an `input` event on the element with the given id, used to notify the editor
+/// of changes that were made directly to the DOM instead of through `execCommand`
+pub fn dispatch_input_event(element_id: &str) {
+    dispatch_event(element_id, "input");
+}
+
+/// Dispatches an event with the given name on the element with the given id
+pub fn dispatch_event(element_id: &str, name: &str) {
+    if let Some(el) = document().get_element_by_id(element_id) {
+        let _ = el.dispatch_event(&Event::new(name).unwrap());
+    }
+}
+
+/// Replaces the element with its children
+pub fn unwrap_element(el: &web_sys::Element) {
+    if let Some(parent) = el.parent_node() {
+        while let Some(child) = el.first_child() {
+            parent.insert_before(&child, Some(el)).unwrap();
+        }
+        el.remove();
+    }
+}
+
+/// Removes a class from the element, dropping the `class` attribute when it becomes empty
+/// and unwrapping `<span>`s that are left without any attributes
+pub fn remove_class(el: &Element, class: &str) {
+    let _ = el.class_list().remove_1(class);
+    if el.class_list().length() == 0 {
+        let _ = el.remove_attribute("class");
+    }
+    if el.tag_name().eq_ignore_ascii_case("span") && !el.has_attributes() {
+        unwrap_element(el);
+    }
+}
+
+/// Wraps the selected content of `root` in marker elements, splitting the existing formatting
+/// at the selection boundaries. The markers must be replaced or unwrapped by the caller
+pub fn mark_selection(root: &Element) -> Vec<Element> {
+    let _ = exec("fontName", SELECTION_MARKER);
+    let markers = root
+        .query_selector_all(&format!("font[face=\"{SELECTION_MARKER}\"]"))
+        .unwrap();
+    (0..markers.length())
+        .filter_map(|i| markers.item(i))
+        .filter_map(|node| node.dyn_into::<Element>().ok())
+        .collect()
+}
+
+/// Splits `ancestor` so that `el` is its only content, the content before and after `el`
+/// is moved to shallow copies of `ancestor`
+pub fn isolate_from_ancestor(el: &Element, ancestor: &Element) -> Result<(), JsValue> {
+    let parent = ancestor.parent_node().ok_or("the ancestor has no parent")?;
+
+    let range = Range::new()?;
+    range.set_start(ancestor, 0)?;
+    range.set_end_before(el)?;
+    let before = range.extract_contents()?;
+    if before.has_child_nodes() {
+        let copy = ancestor.clone_node()?;
+        copy.append_child(&before)?;
+        parent.insert_before(&copy, Some(ancestor))?;
+    }
+
+    range.set_start_after(el)?;
+    range.set_end(ancestor, ancestor.child_nodes().length())?;
+    let after = range.extract_contents()?;
+    if after.has_child_nodes() {
+        let copy = ancestor.clone_node()?;
+        copy.append_child(&after)?;
+        parent.insert_before(&copy, ancestor.next_sibling().as_ref())?;
+    }
+    Ok(())
+}
+
+/// Height in pixels of each dropdown item
+const DROPDOWN_ITEM_HEIGHT: f64 = 24.;
+
+/// An item of a dropdown menu, `class` is added to the item so it can preview its effect
+pub struct DropdownItem {
+    pub label: String,
+    pub class: Option<String>,
+}
+
+/// Toggles a dropdown menu below the element with the given id, `on_select` receives the
+/// index of the clicked item with the selection that existed when the menu was opened
+pub fn dropdown_menu(
+    element_id: &str,
+    width: f64,
+    items: Vec<DropdownItem>,
+    on_select: Rc<dyn Fn(usize)>,
+) {
+    if remove_context_menu(element_id) {
+        return;
+    }
+    let selection = save_selection();
+
+    let height = items.len() as f64 * DROPDOWN_ITEM_HEIGHT;
+    let menu = add_context_menu_to_el(element_id, width, height, ContextMenuPosition::BottomLeft);
+    fill_dropdown_menu(&menu, element_id, items, selection, on_select);
+}
+
+/// Opens a dropdown menu at the given position, replacing the one that is already open
+/// for the element with the given id. The element doesn't need to exist
+pub fn dropdown_menu_at(
+    element_id: &str,
+    top: f64,
+    left: f64,
+    width: f64,
+    items: Vec<DropdownItem>,
+    on_select: Rc<dyn Fn(usize)>,
+) {
+    remove_context_menu(element_id);
+    let selection = save_selection();
+
+    let height = items.len() as f64 * DROPDOWN_ITEM_HEIGHT;
+    let menu = add_context_menu_at(element_id, top, left, width, height, vec![]);
+    fill_dropdown_menu(&menu, element_id, items, selection, on_select);
+}
+
+fn fill_dropdown_menu(
+    menu: &HtmlDivElement,
+    element_id: &str,
+    items: Vec<DropdownItem>,
+    selection: Option<Range>,
+    on_select: Rc<dyn Fn(usize)>,
+) {
+    style_dropdown_menu(menu).unwrap();
+
+    for (index, item) in items.into_iter().enumerate() {
         let el = document().create_element("a").unwrap();
-        let el = el.dyn_ref::<HtmlElement>().unwrap();
-        style_color_picker_item(el, color).unwrap();
-
-        let on_click_item: Closure<dyn Fn(Event)> = Closure::new(|e: Event| {
-            let target = e.target().unwrap();
-            let target = target.dyn_ref::<HtmlElement>().unwrap();
-            let color = target
-                .style()
-                .get_property_value("background-color")
-                .unwrap();
-            let _ = exec("foreColor", &color);
+        let el = el.dyn_into::<HtmlElement>().unwrap();
+        el.set_inner_text(&item.label);
+        if let Some(class) = &item.class {
+            el.set_class_name(class);
+        }
+        style_dropdown_item(&el).unwrap();
+
+        let element_id = element_id.to_string();
+        let selection = selection.clone();
+        let on_select = on_select.clone();
+        let on_click_item: Closure<dyn Fn(Event)> = Closure::new(move |_: Event| {
+            if let Some(range) = &selection {
+                restore_selection(range);
+            }
+            on_select(index);
+            remove_context_menu(&element_id);
         });
         el.add_event_listener_with_callback("click", on_click_item.as_ref().unchecked_ref())
             .unwrap();
         on_click_item.forget();
-        menu_inner.append_child(&el).unwrap();
+        menu.append_child(&el).unwrap();
     }
 }
 
-fn style_color_picker_menu_inner(el: &HtmlElement) -> Result<(), JsValue> {
-    el.style().set_property("width", "100%")?;
-    el.style().set_property("height", "100%")?;
+fn style_dropdown_menu(el: &HtmlDivElement) -> Result<(), JsValue> {
+    el.style().set_property("border", "1px solid black")?;
+    el.style().set_property("background-color", "white")?;
     el.style().set_property("display", "flex")?;
-    el.style().set_property("flex-wrap", "wrap")
+    el.style().set_property("flex-direction", "column")
 }
 
-fn style_color_picker_menu(el: &HtmlDivElement) -> Result<(), JsValue> {
-    el.style().set_property("border", "1px solid black")?;
-    el.style().set_property("background-color", "white")
+fn style_dropdown_item(el: &HtmlElement) -> Result<(), JsValue> {
+    el.style()
+        .set_property("height", &format!("{DROPDOWN_ITEM_HEIGHT}px"))?;
+    el.style()
+        .set_property("line-height", &format!("{DROPDOWN_ITEM_HEIGHT}px"))?;
+    el.style().set_property("padding", "0 4px")?;
+    el.style().set_property("overflow", "hidden")?;
+    el.style().set_property("white-space", "nowrap")?;
+    el.style().set_property("cursor", "pointer")?;
+    el.style().set_property("user-select", "none")
 }
 
-fn style_color_picker_item(el: &HtmlElement, color: &str) -> Result<(), JsValue> {
-    el.style().set_property("width", "14px")?;
-    el.style().set_property("height", "14px")?;
-    el.style().set_property("background-color", color)?;
-    el.style().set_property("border", "2px solid #ffffff")?;
-    el.style().set_property("user-select", "none")
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_spaces_dont_collapse() {
+        assert_eq!(typed_text("word", " ", ""), "word\u{a0}");
+        assert_eq!(typed_text("", " ", "word"), "\u{a0}word");
+        assert_eq!(typed_text("one ", " ", "two"), "one \u{a0}two");
+        assert_eq!(typed_text("one\u{a0}", "two", ""), "one two");
+        assert_eq!(typed_text("a", "   ", "b"), "a \u{a0} b");
+    }
+
+    #[test]
+    fn only_the_spaces_next_to_the_typed_text_change() {
+        assert_eq!(
+            typed_text("10\u{a0}km and", " ", ""),
+            "10\u{a0}km and\u{a0}"
+        );
+        assert_eq!(typed_text("x", "y", " z"), "xy z");
+    }
 }
@@ -0,0 +1,41 @@
+//! Runs in a browser with `wasm-pack test --headless --firefox`
+#![cfg(target_arch = "wasm32")]
+
+use papelito::find::{close_find_panel, open_find_panel, replace_all};
+use papelito::util::{exec, restore_selection};
+use wasm_bindgen::JsCast;
+use wasm_bindgen_test::*;
+use web_sys::{HtmlElement, Range};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn undoes_replace_all_at_once() {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let content = document
+        .create_element("div")
+        .unwrap()
+        .unchecked_into::<HtmlElement>();
+    content.set_id("find-content");
+    content.set_content_editable("true");
+    content.set_inner_html("<p>cat and <b>cat</b></p><p>a cat</p>");
+    document.body().unwrap().append_child(&content).unwrap();
+    let html = content.inner_html();
+
+    // The selected text is the query of the search
+    let first = content.query_selector("p").unwrap().unwrap();
+    let range = Range::new().unwrap();
+    range.set_start(&first.first_child().unwrap(), 0).unwrap();
+    range.set_end(&first.first_child().unwrap(), 3).unwrap();
+    restore_selection(&range);
+    open_find_panel("find-content", true);
+
+    replace_all("find-content", "dog").unwrap();
+    assert_eq!(content.text_content().unwrap(), "dog and doga dog");
+
+    exec("undo", "").unwrap();
+    assert_eq!(content.inner_html(), html);
+
+    close_find_panel("find-content");
+    content.remove();
+}